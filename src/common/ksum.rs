@@ -0,0 +1,510 @@
+//! K-sum solver
+//!
+//! Find `k` distinct entries from a list of values whose sum equals (or is
+//! closest to) a target value.
+//!
+//! Solutions are returned as sorted lists of indices in the original values.
+
+use std::{cmp::Ordering, collections::HashMap};
+
+use itertools::Itertools;
+
+/// K-sum solver.
+pub struct KSum<'a> {
+    values: &'a [usize],
+    sorted: Vec<usize>,
+}
+
+impl<'a> KSum<'a> {
+    /// Create a new solver from values.
+    ///
+    /// # Arguments
+    ///
+    /// * `values` - Values
+    pub fn new(values: &'a [usize]) -> Self {
+        let mut sorted: Vec<usize> = (0..values.len()).collect();
+        sorted.sort_by_key(|&i| values[i]);
+
+        Self { values, sorted }
+    }
+
+    /// Get values for indices.
+    ///
+    /// # Arguments
+    ///
+    /// * `indices` - Indices
+    pub fn values_at(&self, indices: &[usize]) -> Vec<usize> {
+        indices.iter().map(|&i| self.values[i]).collect()
+    }
+
+    /// Find first `k` entries which sum equals to `target`.
+    ///
+    /// # Arguments
+    ///
+    /// * `k` - Number of entries
+    /// * `target` - Target value
+    pub fn find(&self, k: usize, target: usize) -> Option<Vec<usize>> {
+        let mut output = None;
+        self.solve(k, target, &mut |indices| {
+            output = Some(indices);
+            true
+        });
+
+        output
+    }
+
+    /// Find all sets of `k` entries which sum equals to `target`.
+    ///
+    /// # Arguments
+    ///
+    /// * `k` - Number of entries
+    /// * `target` - Target value
+    pub fn find_all(&self, k: usize, target: usize) -> Vec<Vec<usize>> {
+        let mut output = Vec::new();
+        self.solve(k, target, &mut |indices| {
+            output.push(indices);
+            false
+        });
+
+        output.sort();
+        output
+    }
+
+    /// Find `k` entries which sum is the closest to `target`.
+    ///
+    /// # Arguments
+    ///
+    /// * `k` - Number of entries
+    /// * `target` - Target value
+    pub fn find_closest(&self, k: usize, target: usize) -> Option<Vec<usize>> {
+        self.closest_from(0, k, target as i128)
+            .map(|(_, positions)| self.positions_to_indices(&positions))
+    }
+
+    /// Run the appropriate strategy, calling `visit` on each solution.
+    /// The search stops as soon as `visit` returns `true`.
+    fn solve<F>(&self, k: usize, target: usize, visit: &mut F)
+    where
+        F: FnMut(Vec<usize>) -> bool,
+    {
+        if k > self.values.len() {
+            return;
+        }
+
+        match k {
+            0 => {
+                if target == 0 {
+                    visit(vec![]);
+                }
+            }
+            1 => {
+                for (idx, &v) in self.values.iter().enumerate() {
+                    if v == target && visit(vec![idx]) {
+                        return;
+                    }
+                }
+            }
+            2 => self.solve_two_sum(target, visit),
+            3 => self.solve_three_sum(target, visit),
+            k => self.solve_meet_in_the_middle(k, target, visit),
+        }
+    }
+
+    /// Hash-based two-sum.
+    fn solve_two_sum<F>(&self, target: usize, visit: &mut F)
+    where
+        F: FnMut(Vec<usize>) -> bool,
+    {
+        let mut seen: HashMap<usize, Vec<usize>> = HashMap::new();
+
+        for (j, &v) in self.values.iter().enumerate() {
+            if v <= target {
+                if let Some(candidates) = seen.get(&(target - v)) {
+                    for &i in candidates {
+                        if visit(vec![i, j]) {
+                            return;
+                        }
+                    }
+                }
+            }
+
+            seen.entry(v).or_default().push(j);
+        }
+    }
+
+    /// Sorted two-pointer three-sum.
+    fn solve_three_sum<F>(&self, target: usize, visit: &mut F)
+    where
+        F: FnMut(Vec<usize>) -> bool,
+    {
+        for first in 0..self.sorted.len() {
+            let v = self.value_at_position(first);
+            if v > target {
+                break;
+            }
+
+            let stop = self.two_pointer_from(first + 1, target - v, &mut |a, b| {
+                visit(self.positions_to_indices(&[first, a, b]))
+            });
+
+            if stop {
+                return;
+            }
+        }
+    }
+
+    /// Enumerate every pair of sorted positions starting from `start` which
+    /// sum equals to `target`.
+    /// Returns `true` if `visit` asked to stop.
+    fn two_pointer_from<F>(&self, start: usize, target: usize, visit: &mut F) -> bool
+    where
+        F: FnMut(usize, usize) -> bool,
+    {
+        if self.sorted.len() < start + 2 {
+            return false;
+        }
+
+        let (mut lo, mut hi) = (start, self.sorted.len() - 1);
+        while lo < hi {
+            let (a, b) = (self.value_at_position(lo), self.value_at_position(hi));
+            let ordering = a
+                .checked_add(b)
+                .map_or(Ordering::Greater, |s| s.cmp(&target));
+
+            if ordering == Ordering::Less {
+                lo += 1;
+            } else if ordering == Ordering::Greater {
+                hi -= 1;
+            } else if a == b {
+                // Every pair in the run is a solution
+                for p in lo..hi {
+                    for q in p + 1..=hi {
+                        if visit(p, q) {
+                            return true;
+                        }
+                    }
+                }
+                return false;
+            } else {
+                let mut lo_end = lo;
+                while self.value_at_position(lo_end + 1) == a {
+                    lo_end += 1;
+                }
+                let mut hi_start = hi;
+                while self.value_at_position(hi_start - 1) == b {
+                    hi_start -= 1;
+                }
+
+                for p in lo..=lo_end {
+                    for q in hi_start..=hi {
+                        if visit(p, q) {
+                            return true;
+                        }
+                    }
+                }
+
+                lo = lo_end + 1;
+                hi = hi_start - 1;
+            }
+        }
+
+        false
+    }
+
+    /// Meet-in-the-middle k-sum.
+    ///
+    /// Every pair of indices `(i, j)` with `i < j` whose sum does not exceed
+    /// `target` is stored once as a `(sum, i, j)` tuple, sorted by sum. Each solution is made of the
+    /// `k - 4` lowest indices, enumerated directly, then two pairs joined
+    /// with a two-pointer pass, the lower pair ending before the upper pair
+    /// starts so every set is only seen once.
+    fn solve_meet_in_the_middle<F>(&self, k: usize, target: usize, visit: &mut F)
+    where
+        F: FnMut(Vec<usize>) -> bool,
+    {
+        let mut pairs: Vec<(usize, usize, usize)> = vec![];
+        for p in 0..self.sorted.len() {
+            for q in p + 1..self.sorted.len() {
+                match self
+                    .value_at_position(p)
+                    .checked_add(self.value_at_position(q))
+                {
+                    Some(sum) if sum <= target => {
+                        let (i, j) = (self.sorted[p], self.sorted[q]);
+                        pairs.push((sum, i.min(j), i.max(j)));
+                    }
+                    // Values are sorted, next pairs are even larger
+                    _ => break,
+                }
+            }
+        }
+        pairs.sort_unstable();
+
+        for prefix in (0..self.values.len()).combinations(k - 4) {
+            let rest = prefix
+                .iter()
+                .try_fold(target, |rest, &i| rest.checked_sub(self.values[i]));
+            let rest = match rest {
+                Some(rest) => rest,
+                None => continue,
+            };
+
+            let start = prefix.last().map_or(0, |&i| i + 1);
+            let stop = Self::join_pairs(&pairs, start, rest, &mut |lower, upper| {
+                let mut indices = prefix.clone();
+                indices.extend_from_slice(&[lower.1, lower.2, upper.1, upper.2]);
+                visit(indices)
+            });
+
+            if stop {
+                return;
+            }
+        }
+    }
+
+    /// Enumerate every two pairs from `pairs`, sorted by sum, whose sums add up
+    /// to `target`, whose indices are at least `start`, and where the lower
+    /// pair ends before the upper pair starts.
+    /// Returns `true` if `visit` asked to stop.
+    fn join_pairs<F>(
+        pairs: &[(usize, usize, usize)],
+        start: usize,
+        target: usize,
+        visit: &mut F,
+    ) -> bool
+    where
+        F: FnMut((usize, usize, usize), (usize, usize, usize)) -> bool,
+    {
+        let mut visit_ordered = |x: (usize, usize, usize), y: (usize, usize, usize)| {
+            if x.1 < start || y.1 < start {
+                false
+            } else if x.2 < y.1 {
+                visit(x, y)
+            } else if y.2 < x.1 {
+                visit(y, x)
+            } else {
+                false
+            }
+        };
+
+        let (mut lo, mut hi) = (0, pairs.len());
+        while lo < hi {
+            let (a, b) = (pairs[lo].0, pairs[hi - 1].0);
+
+            let ordering = if b > target {
+                Ordering::Greater
+            } else {
+                a.cmp(&(target - b))
+            };
+            match ordering {
+                Ordering::Less => {
+                    lo += 1;
+                    continue;
+                }
+                Ordering::Greater => {
+                    hi -= 1;
+                    continue;
+                }
+                Ordering::Equal => (),
+            }
+
+            // Sums match, join both runs of equal sums
+            let lo_end = lo + pairs[lo..hi].iter().take_while(|p| p.0 == a).count();
+            let hi_start = hi - pairs[lo..hi].iter().rev().take_while(|p| p.0 == b).count();
+
+            if a == b {
+                for (p, &x) in pairs[lo..hi].iter().enumerate() {
+                    for &y in &pairs[lo + p + 1..hi] {
+                        if visit_ordered(x, y) {
+                            return true;
+                        }
+                    }
+                }
+                return false;
+            }
+
+            for &x in &pairs[lo..lo_end] {
+                for &y in &pairs[hi_start..hi] {
+                    if visit_ordered(x, y) {
+                        return true;
+                    }
+                }
+            }
+
+            lo = lo_end;
+            hi = hi_start;
+        }
+
+        false
+    }
+
+    /// Find `k` sorted positions starting from `start` which sum is the closest
+    /// to `target`.
+    /// Returns a `(distance, positions)` tuple.
+    fn closest_from(&self, start: usize, k: usize, target: i128) -> Option<(u128, Vec<usize>)> {
+        if self.sorted.len() < start + k {
+            return None;
+        }
+
+        match k {
+            0 => Some((target.unsigned_abs(), vec![])),
+            1 => (start..self.sorted.len())
+                .map(|p| {
+                    let v = self.value_at_position(p) as i128;
+                    ((v - target).unsigned_abs(), vec![p])
+                })
+                .min_by_key(|(distance, _)| *distance),
+            2 => {
+                let mut best: Option<(u128, Vec<usize>)> = None;
+                let (mut lo, mut hi) = (start, self.sorted.len() - 1);
+
+                while lo < hi {
+                    let sum =
+                        self.value_at_position(lo) as i128 + self.value_at_position(hi) as i128;
+                    let distance = (sum - target).unsigned_abs();
+                    if best.as_ref().is_none_or(|(d, _)| distance < *d) {
+                        best = Some((distance, vec![lo, hi]));
+                    }
+
+                    match sum.cmp(&target) {
+                        Ordering::Less => lo += 1,
+                        Ordering::Greater => hi -= 1,
+                        Ordering::Equal => break,
+                    }
+                }
+
+                best
+            }
+            k => {
+                let mut best: Option<(u128, Vec<usize>)> = None;
+
+                for first in start..self.sorted.len() {
+                    let v = self.value_at_position(first) as i128;
+                    if let Some((distance, rest)) = self.closest_from(first + 1, k - 1, target - v)
+                    {
+                        if best.as_ref().is_none_or(|(d, _)| distance < *d) {
+                            let mut positions = vec![first];
+                            positions.extend(rest);
+                            best = Some((distance, positions));
+                        }
+
+                        if distance == 0 {
+                            break;
+                        }
+                    }
+                }
+
+                best
+            }
+        }
+    }
+
+    fn value_at_position(&self, position: usize) -> usize {
+        self.values[self.sorted[position]]
+    }
+
+    fn positions_to_indices(&self, positions: &[usize]) -> Vec<usize> {
+        let mut indices: Vec<usize> = positions.iter().map(|&p| self.sorted[p]).collect();
+        indices.sort_unstable();
+        indices
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SAMPLE: &[usize] = &[1721, 979, 366, 299, 675, 1456];
+
+    #[test]
+    fn test_find() {
+        let ksum = KSum::new(SAMPLE);
+        assert_eq!(ksum.find(2, 2020), Some(vec![0, 3]));
+        assert_eq!(ksum.find(3, 2020), Some(vec![1, 2, 4]));
+        assert_eq!(ksum.find(4, 2020), None);
+        assert_eq!(ksum.find(4, 1721 + 979 + 366 + 299), Some(vec![0, 1, 2, 3]));
+        assert_eq!(ksum.find(7, 0), None);
+        assert_eq!(ksum.find(0, 0), Some(vec![]));
+        assert_eq!(ksum.values_at(&[0, 3]), vec![1721, 299]);
+    }
+
+    #[test]
+    fn test_find_all() {
+        let values = &[1, 1, 2, 3, 3, 4];
+        let ksum = KSum::new(values);
+
+        assert_eq!(
+            ksum.find_all(2, 4),
+            vec![vec![0, 3], vec![0, 4], vec![1, 3], vec![1, 4]]
+        );
+        assert_eq!(
+            ksum.find_all(3, 6),
+            vec![
+                vec![0, 1, 5],
+                vec![0, 2, 3],
+                vec![0, 2, 4],
+                vec![1, 2, 3],
+                vec![1, 2, 4],
+            ]
+        );
+        assert_eq!(
+            ksum.find_all(4, 8),
+            vec![vec![0, 1, 2, 5], vec![0, 1, 3, 4]]
+        );
+    }
+
+    #[test]
+    fn test_find_all_matches_brute_force() {
+        let values: Vec<usize> = (0..20).map(|x| (x * 37 + 11) % 23).collect();
+        let ksum = KSum::new(&values);
+
+        for k in 1..=6 {
+            for target in 0..60 {
+                let expected: Vec<Vec<usize>> = (0..values.len())
+                    .combinations(k)
+                    .filter(|c| c.iter().map(|&i| values[i]).sum::<usize>() == target)
+                    .collect();
+                assert_eq!(
+                    ksum.find_all(k, target),
+                    expected,
+                    "k={} target={}",
+                    k,
+                    target
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_find_large() {
+        // Only pairs below the target are stored
+        let mut values: Vec<usize> = (0..20_000).map(|x| 1000 + x * 7).collect();
+        values.extend_from_slice(&[1, 2, 3, 4]);
+        let ksum = KSum::new(&values);
+        assert_eq!(ksum.find(4, 10), Some(vec![20_000, 20_001, 20_002, 20_003]));
+
+        // Sums which do not fit in a machine word are skipped
+        let values = &[usize::MAX, usize::MAX - 1, 1, 2, 3, 4];
+        let ksum = KSum::new(values);
+        assert_eq!(ksum.find(4, usize::MAX), None);
+        assert_eq!(ksum.find(4, 10), Some(vec![2, 3, 4, 5]));
+        assert_eq!(ksum.find(2, usize::MAX), Some(vec![1, 2]));
+        assert_eq!(ksum.find(3, usize::MAX), None);
+        assert_eq!(ksum.find(5, usize::MAX), None);
+    }
+
+    #[test]
+    fn test_find_closest() {
+        let ksum = KSum::new(SAMPLE);
+        assert_eq!(ksum.find_closest(2, 2020), Some(vec![0, 3]));
+        assert_eq!(
+            ksum.values_at(&ksum.find_closest(2, 1000).unwrap()),
+            vec![299, 675]
+        );
+        assert_eq!(
+            ksum.values_at(&ksum.find_closest(3, 0).unwrap()),
+            vec![366, 299, 675]
+        );
+        assert_eq!(ksum.find_closest(7, 0), None);
+    }
+}
@@ -0,0 +1,249 @@
+//! Number theory helpers
+
+use std::collections::HashMap;
+
+/// Compute `(a * b) % modulus` without overflowing.
+fn mul_mod(a: u64, b: u64, modulus: u64) -> u64 {
+    ((u128::from(a) * u128::from(b)) % u128::from(modulus)) as u64
+}
+
+/// Compute `base^exp % modulus` using fast exponentiation.
+///
+/// # Arguments
+///
+/// * `base` - Base
+/// * `exp` - Exponent
+/// * `modulus` - Modulus
+pub fn mod_pow(base: u64, exp: u64, modulus: u64) -> u64 {
+    if modulus == 1 {
+        return 0;
+    }
+
+    let mut result = 1;
+    let mut base = base % modulus;
+    let mut exp = exp;
+
+    while exp > 0 {
+        if exp & 1 == 1 {
+            result = mul_mod(result, base, modulus);
+        }
+        base = mul_mod(base, base, modulus);
+        exp >>= 1;
+    }
+
+    result
+}
+
+/// Extended euclidean algorithm.
+/// Returns a `(gcd, x, y)` tuple where `a * x + b * y == gcd`.
+///
+/// # Arguments
+///
+/// * `a` - First number
+/// * `b` - Second number
+pub fn extended_gcd(a: i128, b: i128) -> (i128, i128, i128) {
+    let (mut old_r, mut r) = (a, b);
+    let (mut old_s, mut s) = (1, 0);
+    let (mut old_t, mut t) = (0, 1);
+
+    while r != 0 {
+        let q = old_r / r;
+        (old_r, r) = (r, old_r - q * r);
+        (old_s, s) = (s, old_s - q * s);
+        (old_t, t) = (t, old_t - q * t);
+    }
+
+    if old_r < 0 {
+        (-old_r, -old_s, -old_t)
+    } else {
+        (old_r, old_s, old_t)
+    }
+}
+
+/// Compute the modular inverse of `a` modulo `modulus`.
+/// Returns `None` if `a` and `modulus` are not coprime.
+///
+/// # Arguments
+///
+/// * `a` - Number
+/// * `modulus` - Modulus
+pub fn mod_inverse(a: i128, modulus: i128) -> Option<i128> {
+    let (g, x, _) = extended_gcd(a.rem_euclid(modulus), modulus);
+    if g == 1 {
+        Some(x.rem_euclid(modulus))
+    } else {
+        None
+    }
+}
+
+/// Chinese Remainder Theorem error.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CrtError {
+    /// Congruence has a zero modulus, with its index
+    ZeroModulus(usize),
+    /// Congruence contradicts the previous ones, with its index
+    Unsatisfiable(usize),
+    /// Combined modulus does not fit in 128 bits
+    Overflow,
+}
+
+/// Solve a system of congruences `x = residue (mod modulus)` using the
+/// Chinese Remainder Theorem.
+/// Moduli do not need to be pairwise coprime.
+///
+/// Returns the smallest non-negative solution along with the combined modulus
+/// as a `(x, lcm)` tuple, or `None` if the system is unsatisfiable or if the
+/// combined modulus overflows.
+///
+/// # Arguments
+///
+/// * `congruences` - `(residue, modulus)` pairs
+pub fn crt(congruences: &[(i128, u64)]) -> Option<(u128, u128)> {
+    try_crt(congruences).ok()
+}
+
+/// Solve a system of congruences `x = residue (mod modulus)` using the
+/// Chinese Remainder Theorem, reporting why it has no solution.
+/// Moduli do not need to be pairwise coprime.
+///
+/// Returns the smallest non-negative solution along with the combined modulus
+/// as a `(x, lcm)` tuple.
+///
+/// # Arguments
+///
+/// * `congruences` - `(residue, modulus)` pairs
+pub fn try_crt(congruences: &[(i128, u64)]) -> Result<(u128, u128), CrtError> {
+    let mut x: u128 = 0;
+    let mut lcm: u128 = 1;
+
+    for (idx, &(residue, modulus)) in congruences.iter().enumerate() {
+        if modulus == 0 {
+            return Err(CrtError::ZeroModulus(idx));
+        }
+
+        let m = i128::from(modulus);
+        let r = residue.rem_euclid(m);
+        let (g, _, _) = extended_gcd((lcm % u128::from(modulus)) as i128, m);
+
+        // Both solutions must agree modulo the gcd
+        let diff = (r - (x % u128::from(modulus)) as i128).rem_euclid(m);
+        if diff % g != 0 {
+            return Err(CrtError::Unsatisfiable(idx));
+        }
+
+        let step = (m / g) as u64;
+        let lcm_reduced = ((lcm / g as u128) % u128::from(step)) as i128;
+        let inv = mod_inverse(lcm_reduced, i128::from(step))
+            .expect("Reduced moduli should be coprime") as u64;
+        let k = mul_mod(((diff / g) as u64) % step, inv, step);
+
+        let next_lcm = lcm
+            .checked_mul(u128::from(step))
+            .ok_or(CrtError::Overflow)?;
+        x = (x + lcm * u128::from(k)) % next_lcm;
+        lcm = next_lcm;
+    }
+
+    Ok((x, lcm))
+}
+
+/// Solve `base^x = target (mod modulus)` using the baby-step giant-step algorithm.
+/// Returns the smallest non-negative `x`, or `None` if there is no solution.
+///
+/// `base` must be coprime with `modulus`.
+///
+/// # Arguments
+///
+/// * `base` - Base
+/// * `target` - Target value
+/// * `modulus` - Modulus
+pub fn discrete_log(base: u64, target: u64, modulus: u64) -> Option<u64> {
+    if modulus == 1 {
+        return Some(0);
+    }
+
+    let base = base % modulus;
+    let target = target % modulus;
+    let m = (modulus as f64).sqrt().ceil() as u64;
+
+    // Baby steps: base^j for j in 0..m
+    let mut table = HashMap::with_capacity(m as usize);
+    let mut value = 1;
+    for j in 0..m {
+        table.entry(value).or_insert(j);
+        value = mul_mod(value, base, modulus);
+    }
+
+    // Giant steps: target * base^(-m * i)
+    let factor = mod_inverse(i128::from(mod_pow(base, m, modulus)), i128::from(modulus))? as u64;
+    let mut gamma = target;
+    for i in 0..m {
+        if let Some(j) = table.get(&gamma) {
+            return Some(i * m + j);
+        }
+        gamma = mul_mod(gamma, factor, modulus);
+    }
+
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_mod_pow() {
+        assert_eq!(mod_pow(7, 8, 20_201_227), 5_764_801);
+        assert_eq!(mod_pow(2, 0, 13), 1);
+        assert_eq!(mod_pow(5, 3, 1), 0);
+        assert_eq!(mod_pow(u64::MAX - 1, u64::MAX, u64::MAX), u64::MAX - 1);
+    }
+
+    #[test]
+    fn test_extended_gcd() {
+        assert_eq!(extended_gcd(240, 46), (2, -9, 47));
+        assert_eq!(extended_gcd(17, 0), (17, 1, 0));
+    }
+
+    #[test]
+    fn test_mod_inverse() {
+        assert_eq!(mod_inverse(3, 11), Some(4));
+        assert_eq!(mod_inverse(-3, 11), Some(7));
+        assert_eq!(mod_inverse(6, 9), None);
+    }
+
+    #[test]
+    fn test_crt() {
+        assert_eq!(crt(&[(2, 3), (3, 5), (2, 7)]), Some((23, 105)));
+        assert_eq!(crt(&[(0, 17), (-2, 13), (-3, 19)]), Some((3417, 4199)));
+        // Non-coprime moduli
+        assert_eq!(crt(&[(2, 4), (4, 6)]), Some((10, 12)));
+        assert_eq!(crt(&[(1, 4), (2, 6)]), None);
+        assert_eq!(crt(&[]), Some((0, 1)));
+    }
+
+    #[test]
+    fn test_try_crt() {
+        assert_eq!(try_crt(&[(2, 3), (3, 5), (2, 7)]), Ok((23, 105)));
+        assert_eq!(
+            try_crt(&[(1, 2), (1, 4), (2, 6)]),
+            Err(CrtError::Unsatisfiable(2))
+        );
+        assert_eq!(try_crt(&[(1, 3), (0, 0)]), Err(CrtError::ZeroModulus(1)));
+
+        let large = [(1, u64::MAX), (2, u64::MAX - 2), (3, u64::MAX - 4)];
+        assert_eq!(try_crt(&large), Err(CrtError::Overflow));
+    }
+
+    #[test]
+    fn test_discrete_log() {
+        assert_eq!(discrete_log(7, 5_764_801, 20_201_227), Some(8));
+        assert_eq!(discrete_log(7, 17_807_724, 20_201_227), Some(11));
+        assert_eq!(discrete_log(2, 1, 13), Some(0));
+        assert_eq!(discrete_log(2, 0, 13), None);
+        assert_eq!(
+            discrete_log(3, mod_pow(3, 123_456_789, 1_000_000_007), 1_000_000_007),
+            Some(123_456_789)
+        );
+    }
+}
@@ -1,5 +1,8 @@
 //! Common types
 
+pub mod ksum;
+pub mod math;
+
 use std::cmp::Ordering;
 
 /// Vec3
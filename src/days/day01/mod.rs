@@ -36,34 +36,48 @@
 //!
 //! In your expense report, what is the product of the three entries that sum to 2020?
 
-use itertools::Itertools;
+use crate::common::ksum::KSum;
 
 const INPUT_VALUES: &str = include_str!("input.txt");
 
 /// Part one answer.
 pub fn run_ex1() -> usize {
-    search_if_eq(INPUT_VALUES, 2, 2020).into_iter().product()
+    search_if_eq(&parse_entries(INPUT_VALUES), 2, 2020)
+        .into_iter()
+        .product()
 }
 
 /// Part two answer.
 pub fn run_ex2() -> usize {
-    search_if_eq(INPUT_VALUES, 3, 2020).into_iter().product()
+    search_if_eq(&parse_entries(INPUT_VALUES), 3, 2020)
+        .into_iter()
+        .product()
 }
 
-/// Search first combination of length `combinations` which sum equals to `target`.
+/// Parse entries from input text.
 ///
 /// # Arguments
 ///
 /// * `entries_content` - Input text
-/// * `combinations` - Combinations length
-/// * `target` - Target value
-pub fn search_if_eq(entries_content: &str, combinations: usize, target: usize) -> Vec<usize> {
+pub fn parse_entries(entries_content: &str) -> Vec<usize> {
     entries_content
         .lines()
         .filter_map(|s| s.parse::<usize>().ok())
-        .combinations(combinations)
-        .find(|v| v.iter().sum::<usize>() == target)
-        .unwrap_or_else(Vec::new)
+        .collect()
+}
+
+/// Search first combination of length `combinations` which sum equals to `target`.
+///
+/// # Arguments
+///
+/// * `entries` - Entries
+/// * `combinations` - Combinations length
+/// * `target` - Target value
+pub fn search_if_eq(entries: &[usize], combinations: usize, target: usize) -> Vec<usize> {
+    let ksum = KSum::new(entries);
+    ksum.find(combinations, target)
+        .map(|indices| ksum.values_at(&indices))
+        .unwrap_or_default()
 }
 
 #[cfg(test)]
@@ -75,10 +89,16 @@ mod tests {
 
     #[test]
     fn test_search_if_eq() {
-        assert_eq!(search_if_eq("1234\n5678\n2020\n0", 2, 2020), vec![2020, 0]);
-        assert_eq!(search_if_eq("1234\n5678", 2, 2020), vec![]);
-        assert_eq!(search_if_eq("", 2, 2020), vec![]);
-        assert_eq!(search_if_eq("1234", 2, 2020), vec![]);
+        let search = |input, k, target| search_if_eq(&parse_entries(input), k, target);
+
+        assert_eq!(search("1234\n5678\n2020\n0", 2, 2020), vec![2020, 0]);
+        assert_eq!(search("1234\n5678", 2, 2020), vec![]);
+        assert_eq!(search("", 2, 2020), vec![]);
+        assert_eq!(search("1234", 2, 2020), vec![]);
+        assert_eq!(
+            search("1721\n979\n366\n299\n675\n1456", 3, 2020),
+            vec![979, 366, 675]
+        );
     }
 
     #[test]
@@ -38,12 +38,16 @@
 //!
 //! How many passwords are valid according to the new interpretation of the policies?
 
+use std::collections::HashMap;
+
 use once_cell::sync::Lazy;
 use regex::Regex;
+use thiserror::Error;
 
 const INPUT_VALUES: &str = include_str!("input.txt");
-static PASSWORD_RGX: Lazy<Regex> =
-    Lazy::new(|| Regex::new(r"(?P<min>\d+)-(?P<max>\d+) (?P<char>\w): (?P<password>\w+)").unwrap());
+static PASSWORD_RGX: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r"^(?P<min>\d+)-(?P<max>\d+) (?P<char>\S): (?P<password>\S+)$").unwrap()
+});
 
 /// Part one answer.
 pub fn run_ex1() -> usize {
@@ -55,6 +59,410 @@ pub fn run_ex2() -> usize {
     validate_multiple_passwords_with_position(INPUT_VALUES)
 }
 
+/// Day error.
+#[derive(Debug, Error)]
+pub enum DayError {
+    /// Entry parse error
+    #[error("Entry parse error: {0}")]
+    EntryParseError(String),
+    /// Unknown policy name
+    #[error("Unknown policy '{0}'")]
+    UnknownPolicy(String),
+}
+
+/// Policy violation.
+#[derive(Debug, Error, PartialEq, Eq, Clone)]
+pub enum PolicyViolation {
+    /// Character count is out of range
+    #[error("'{letter}' appears {count} times, expected between {min} and {max}")]
+    CountOutOfRange {
+        /// Letter
+        letter: char,
+        /// Letter count
+        count: usize,
+        /// Minimum count
+        min: usize,
+        /// Maximum count
+        max: usize,
+    },
+    /// Character should appear at exactly one of the positions
+    #[error(
+        "'{letter}' appears at {matches} of positions {first} and {second}, expected exactly one"
+    )]
+    PositionMismatch {
+        /// Letter
+        letter: char,
+        /// Matching positions count
+        matches: usize,
+        /// First position
+        first: usize,
+        /// Second position
+        second: usize,
+    },
+    /// Position is invalid (positions start at 1)
+    #[error("Invalid position {0}, positions start at 1")]
+    InvalidPosition(usize),
+    /// Password contains a forbidden substring
+    #[error("Password contains forbidden substring '{0}'")]
+    ForbiddenSubstring(String),
+    /// Password does not contain enough character classes
+    #[error("Password contains {found} character classes, expected at least {required}")]
+    NotEnoughClasses {
+        /// Found classes
+        found: usize,
+        /// Required classes
+        required: usize,
+    },
+}
+
+/// Policy entry.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub struct PolicyEntry<'a> {
+    /// First number (minimum count or first position)
+    pub first: usize,
+    /// Second number (maximum count or second position)
+    pub second: usize,
+    /// Policy letter
+    pub letter: char,
+    /// Password
+    pub password: &'a str,
+}
+
+impl<'a> PolicyEntry<'a> {
+    /// Create entry.
+    ///
+    /// # Arguments
+    ///
+    /// * `first` - First number
+    /// * `second` - Second number
+    /// * `letter` - Letter
+    /// * `password` - Password
+    pub const fn new(first: usize, second: usize, letter: char, password: &'a str) -> Self {
+        Self {
+            first,
+            second,
+            letter,
+            password,
+        }
+    }
+}
+
+/// Password policy.
+pub trait PasswordPolicy {
+    /// Policy name.
+    fn name(&self) -> &str;
+
+    /// Check entry against policy.
+    ///
+    /// # Arguments
+    ///
+    /// * `entry` - Policy entry
+    fn check(&self, entry: &PolicyEntry) -> Result<(), PolicyViolation>;
+
+    /// Validate entry against policy.
+    ///
+    /// # Arguments
+    ///
+    /// * `entry` - Policy entry
+    fn validate(&self, entry: &PolicyEntry) -> bool {
+        self.check(entry).is_ok()
+    }
+}
+
+/// Letter count should be in the `first..=second` range.
+pub struct CountRangePolicy;
+
+impl PasswordPolicy for CountRangePolicy {
+    fn name(&self) -> &str {
+        "count-range"
+    }
+
+    fn check(&self, entry: &PolicyEntry) -> Result<(), PolicyViolation> {
+        let count = entry
+            .password
+            .chars()
+            .filter(|c| *c == entry.letter)
+            .count();
+        if (entry.first..=entry.second).contains(&count) {
+            Ok(())
+        } else {
+            Err(PolicyViolation::CountOutOfRange {
+                letter: entry.letter,
+                count,
+                min: entry.first,
+                max: entry.second,
+            })
+        }
+    }
+}
+
+/// Letter should be at exactly one of the `first` or `second` positions.
+pub struct ExactlyOnePositionPolicy;
+
+impl PasswordPolicy for ExactlyOnePositionPolicy {
+    fn name(&self) -> &str {
+        "exactly-one-position"
+    }
+
+    fn check(&self, entry: &PolicyEntry) -> Result<(), PolicyViolation> {
+        let matches = [entry.first, entry.second]
+            .iter()
+            .map(|&pos| {
+                if pos == 0 {
+                    Err(PolicyViolation::InvalidPosition(pos))
+                } else {
+                    Ok(entry.password.chars().nth(pos - 1) == Some(entry.letter))
+                }
+            })
+            .collect::<Result<Vec<_>, _>>()?
+            .into_iter()
+            .filter(|&m| m)
+            .count();
+
+        if matches == 1 {
+            Ok(())
+        } else {
+            Err(PolicyViolation::PositionMismatch {
+                letter: entry.letter,
+                matches,
+                first: entry.first,
+                second: entry.second,
+            })
+        }
+    }
+}
+
+/// Password should not contain any of the forbidden substrings.
+pub struct ForbiddenSubstringsPolicy {
+    substrings: Vec<String>,
+}
+
+impl ForbiddenSubstringsPolicy {
+    /// Create policy from forbidden substrings.
+    ///
+    /// # Arguments
+    ///
+    /// * `substrings` - Forbidden substrings
+    pub fn new<S: Into<String>>(substrings: impl IntoIterator<Item = S>) -> Self {
+        Self {
+            substrings: substrings.into_iter().map(Into::into).collect(),
+        }
+    }
+}
+
+impl PasswordPolicy for ForbiddenSubstringsPolicy {
+    fn name(&self) -> &str {
+        "forbidden-substrings"
+    }
+
+    fn check(&self, entry: &PolicyEntry) -> Result<(), PolicyViolation> {
+        match self
+            .substrings
+            .iter()
+            .find(|s| entry.password.contains(s.as_str()))
+        {
+            Some(s) => Err(PolicyViolation::ForbiddenSubstring(s.clone())),
+            None => Ok(()),
+        }
+    }
+}
+
+/// Password should contain at least `min_classes` character classes
+/// (lowercase, uppercase, digits, others).
+pub struct MinClassesPolicy {
+    min_classes: usize,
+}
+
+impl MinClassesPolicy {
+    /// Create policy from minimum classes count.
+    ///
+    /// # Arguments
+    ///
+    /// * `min_classes` - Minimum character classes
+    pub const fn new(min_classes: usize) -> Self {
+        Self { min_classes }
+    }
+}
+
+impl PasswordPolicy for MinClassesPolicy {
+    fn name(&self) -> &str {
+        "min-classes"
+    }
+
+    fn check(&self, entry: &PolicyEntry) -> Result<(), PolicyViolation> {
+        let chars = || entry.password.chars();
+        let found = [
+            chars().any(|c| c.is_ascii_lowercase()),
+            chars().any(|c| c.is_ascii_uppercase()),
+            chars().any(|c| c.is_ascii_digit()),
+            chars().any(|c| !c.is_ascii_alphanumeric()),
+        ]
+        .iter()
+        .filter(|&&x| x)
+        .count();
+
+        if found >= self.min_classes {
+            Ok(())
+        } else {
+            Err(PolicyViolation::NotEnoughClasses {
+                found,
+                required: self.min_classes,
+            })
+        }
+    }
+}
+
+/// Policy registry, indexed by policy name.
+pub struct PolicyRegistry {
+    policies: HashMap<String, Box<dyn PasswordPolicy>>,
+}
+
+impl Default for PolicyRegistry {
+    fn default() -> Self {
+        let mut registry = Self::empty();
+        registry.register(CountRangePolicy);
+        registry.register(ExactlyOnePositionPolicy);
+        registry
+    }
+}
+
+impl PolicyRegistry {
+    /// Create an empty registry.
+    pub fn empty() -> Self {
+        Self {
+            policies: HashMap::new(),
+        }
+    }
+
+    /// Register policy, replacing any policy with the same name.
+    ///
+    /// # Arguments
+    ///
+    /// * `policy` - Policy
+    pub fn register<P: PasswordPolicy + 'static>(&mut self, policy: P) {
+        self.policies
+            .insert(policy.name().to_string(), Box::new(policy));
+    }
+
+    /// Get policy from name.
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - Policy name
+    pub fn get(&self, name: &str) -> Option<&dyn PasswordPolicy> {
+        self.policies.get(name).map(AsRef::as_ref)
+    }
+
+    /// Get sorted policy names.
+    pub fn names(&self) -> Vec<&str> {
+        let mut names: Vec<&str> = self.policies.keys().map(String::as_str).collect();
+        names.sort_unstable();
+        names
+    }
+
+    /// Build a report for input entries against named policies.
+    /// Fails on the first unknown policy name.
+    ///
+    /// # Arguments
+    ///
+    /// * `entries` - Input text
+    /// * `names` - Policy names
+    pub fn report(&self, entries: &str, names: &[&str]) -> Result<PolicyReport, DayError> {
+        let policies = names
+            .iter()
+            .map(|&name| {
+                self.get(name)
+                    .ok_or_else(|| DayError::UnknownPolicy(name.to_string()))
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        Ok(build_report(entries, &policies))
+    }
+}
+
+/// Policy failure.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct PolicyFailure {
+    /// Line number (starting at 1)
+    pub line: usize,
+    /// Raw entry
+    pub entry: String,
+    /// Broken rule name
+    pub rule: String,
+    /// Failure reason
+    pub reason: String,
+}
+
+/// Policy report.
+#[derive(Debug, Default)]
+pub struct PolicyReport {
+    /// Total entry count
+    pub total: usize,
+    /// Valid entry count
+    pub valid: usize,
+    /// Failures
+    pub failures: Vec<PolicyFailure>,
+}
+
+impl std::fmt::Display for PolicyReport {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for failure in &self.failures {
+            writeln!(
+                f,
+                "line {}: '{}' breaks {}: {}",
+                failure.line, failure.entry, failure.rule, failure.reason
+            )?;
+        }
+        write!(f, "{}/{} entries valid", self.valid, self.total)
+    }
+}
+
+/// Build a report for input entries against policies.
+/// An entry is valid when it satisfies every policy.
+///
+/// # Arguments
+///
+/// * `entries` - Input text
+/// * `policies` - Policies
+pub fn build_report(entries: &str, policies: &[&dyn PasswordPolicy]) -> PolicyReport {
+    let mut report = PolicyReport::default();
+
+    for (idx, line) in entries.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() {
+            continue;
+        }
+
+        report.total += 1;
+        let failure = |rule: &str, reason: String| PolicyFailure {
+            line: idx + 1,
+            entry: line.to_string(),
+            rule: rule.to_string(),
+            reason,
+        };
+
+        let failures: Vec<PolicyFailure> = match parse_password_entry(line) {
+            Ok(entry) => policies
+                .iter()
+                .filter_map(|p| {
+                    p.check(&entry)
+                        .err()
+                        .map(|e| failure(p.name(), e.to_string()))
+                })
+                .collect(),
+            Err(e) => vec![failure("parse", e.to_string())],
+        };
+
+        if failures.is_empty() {
+            report.valid += 1;
+        } else {
+            report.failures.extend(failures);
+        }
+    }
+
+    report
+}
+
 /// Validate multiple passwords with count.
 ///
 /// # Arguments
@@ -83,10 +491,7 @@ pub fn validate_multiple_passwords_with_fn<F>(entries: &str, func: F) -> usize
 where
     F: Fn(&str) -> bool,
 {
-    entries
-        .lines()
-        .filter_map(|s| if func(s) { Some(true as usize) } else { None })
-        .sum::<usize>()
+    entries.lines().filter(|s| func(s)).count()
 }
 
 /// Validate password with count.
@@ -95,9 +500,7 @@ where
 ///
 /// * `entry` - Password
 pub fn validate_password_with_count(entry: &str) -> bool {
-    let (min_v, max_v, char_v, password) = parse_password_entry(entry);
-    let count = password.chars().filter(|c| *c == char_v).count();
-    count <= max_v && count >= min_v
+    validate_password_with_policy(entry, &CountRangePolicy)
 }
 
 /// Validate password with character position.
@@ -106,19 +509,18 @@ pub fn validate_password_with_count(entry: &str) -> bool {
 ///
 /// * `entry` - Password
 pub fn validate_password_with_position(entry: &str) -> bool {
-    let (min_v, max_v, char_v, password) = parse_password_entry(entry);
-    let bytes = password.as_bytes();
-    let char_b = char_v as u8;
-    let char_min = bytes[min_v - 1];
-    let char_max = bytes[max_v - 1];
+    validate_password_with_policy(entry, &ExactlyOnePositionPolicy)
+}
 
-    if char_min == char_b && char_max == char_b {
-        false
-    } else if char_min == char_b {
-        true
-    } else {
-        char_max == char_b
-    }
+/// Validate password with policy.
+/// Unparsable entries are invalid.
+///
+/// # Arguments
+///
+/// * `entry` - Password
+/// * `policy` - Policy
+pub fn validate_password_with_policy(entry: &str, policy: &dyn PasswordPolicy) -> bool {
+    parse_password_entry(entry).is_ok_and(|e| policy.validate(&e))
 }
 
 /// Parse password entry.
@@ -126,24 +528,22 @@ pub fn validate_password_with_position(entry: &str) -> bool {
 /// # Arguments
 ///
 /// * `entry` - Password
-pub fn parse_password_entry(entry: &str) -> (usize, usize, char, &str) {
-    let captures = PASSWORD_RGX.captures(entry).unwrap();
-
-    (
-        captures
-            .name("min")
-            .map(|x| x.as_str().parse().unwrap())
-            .unwrap(),
-        captures
-            .name("max")
-            .map(|x| x.as_str().parse().unwrap())
-            .unwrap(),
-        captures
-            .name("char")
-            .map(|x| x.as_str().parse().unwrap())
-            .unwrap(),
-        captures.name("password").map(|x| x.as_str()).unwrap(),
-    )
+pub fn parse_password_entry(entry: &str) -> Result<PolicyEntry<'_>, DayError> {
+    let captures = PASSWORD_RGX
+        .captures(entry)
+        .ok_or_else(|| DayError::EntryParseError(entry.to_string()))?;
+    let number = |name: &str| {
+        captures[name]
+            .parse()
+            .map_err(|_| DayError::EntryParseError(entry.to_string()))
+    };
+
+    Ok(PolicyEntry::new(
+        number("min")?,
+        number("max")?,
+        captures["char"].chars().next().unwrap(),
+        captures.name("password").unwrap().as_str(),
+    ))
 }
 
 #[cfg(test)]
@@ -156,13 +556,20 @@ mod tests {
     #[test]
     fn test_parse_password_entry() {
         assert_eq!(
-            parse_password_entry("1-3 c: tototutu"),
-            (1, 3, 'c', "tototutu")
+            parse_password_entry("1-3 c: tototutu").unwrap(),
+            PolicyEntry::new(1, 3, 'c', "tototutu")
         );
         assert_eq!(
-            parse_password_entry("10-30 z: zzzzzz"),
-            (10, 30, 'z', "zzzzzz")
+            parse_password_entry("10-30 z: zzzzzz").unwrap(),
+            PolicyEntry::new(10, 30, 'z', "zzzzzz")
         );
+        assert_eq!(
+            parse_password_entry("1-3 !: ab!cd").unwrap(),
+            PolicyEntry::new(1, 3, '!', "ab!cd")
+        );
+        assert!(parse_password_entry("1-3: zzzzzz").is_err());
+        assert!(parse_password_entry("1-3 c: ab cd").is_err());
+        assert!(parse_password_entry("x1-3 c: abcd").is_err());
     }
 
     #[test]
@@ -176,6 +583,69 @@ mod tests {
         assert!(validate_password_with_position("1-3 c: cabc"));
         assert!(!validate_password_with_position("1-3 c: cacc"));
         assert!(validate_password_with_position("1-3 c: aacc"));
+        assert!(!validate_password_with_position("1-30 c: aacc"));
+        assert!(!validate_password_with_position("0-3 c: aacc"));
+    }
+
+    #[test]
+    fn test_extra_policies() {
+        let entry = PolicyEntry::new(1, 3, 'a', "abc1");
+
+        assert_eq!(
+            ForbiddenSubstringsPolicy::new(vec!["bc"]).check(&entry),
+            Err(PolicyViolation::ForbiddenSubstring("bc".into()))
+        );
+        assert!(ForbiddenSubstringsPolicy::new(vec!["cb"]).validate(&entry));
+        assert!(MinClassesPolicy::new(2).validate(&entry));
+        assert!(MinClassesPolicy::new(3).validate(&PolicyEntry::new(1, 3, 'a', "ab!c1")));
+        assert_eq!(
+            MinClassesPolicy::new(3).check(&entry),
+            Err(PolicyViolation::NotEnoughClasses {
+                found: 2,
+                required: 3
+            })
+        );
+    }
+
+    #[test]
+    fn test_report() {
+        let mut registry = PolicyRegistry::default();
+        registry.register(MinClassesPolicy::new(2));
+        assert_eq!(
+            registry.names(),
+            vec!["count-range", "exactly-one-position", "min-classes"]
+        );
+
+        let report = registry
+            .report(
+                "1-3 a: abcde\n1-3 b: cdefg\n2-9 c: ccccccccc\nfoo",
+                &["count-range", "exactly-one-position"],
+            )
+            .unwrap();
+        assert_eq!(report.total, 4);
+        assert_eq!(report.valid, 1);
+        assert_eq!(
+            report
+                .failures
+                .iter()
+                .map(|f| (f.line, f.rule.as_str()))
+                .collect::<Vec<_>>(),
+            vec![
+                (2, "count-range"),
+                (2, "exactly-one-position"),
+                (3, "exactly-one-position"),
+                (4, "parse")
+            ]
+        );
+        assert_eq!(
+            report.failures[0].reason,
+            "'b' appears 0 times, expected between 1 and 3"
+        );
+
+        assert!(matches!(
+            registry.report("1-3 a: abcde", &["count-range", "count-rnage"]),
+            Err(DayError::UnknownPolicy(name)) if name == "count-rnage"
+        ));
     }
 
     #[test]
@@ -76,6 +76,8 @@
 //!
 //! What do you get if you multiply together the number of trees encountered on each of the listed slopes?
 
+use std::collections::HashSet;
+
 use once_cell::sync::Lazy;
 
 const INPUT_VALUES: &str = include_str!("input.txt");
@@ -122,6 +124,15 @@ impl MapCell {
     }
 }
 
+/// Toboggan path
+#[derive(Debug, PartialEq, Eq)]
+pub struct TobogganPath {
+    /// Tree count
+    pub trees: usize,
+    /// Visited positions, including the starting position
+    pub positions: Vec<(usize, usize)>,
+}
+
 /// Handle toboggan map data
 pub struct TobogganMap {
     data: Vec<MapCell>,
@@ -166,6 +177,146 @@ impl TobogganMap {
         tree_counter
     }
 
+    /// Get map height.
+    pub fn height(&self) -> usize {
+        self.data.len() / self.width
+    }
+
+    /// Get positions visited when following slope, including the starting position.
+    /// X positions are not wrapped around the map width.
+    ///
+    /// # Arguments
+    ///
+    /// * `offset_x` - X offset
+    /// * `offset_y` - Y offset
+    pub fn slope_positions(&self, offset_x: usize, offset_y: usize) -> Vec<(usize, usize)> {
+        if offset_y == 0 {
+            return vec![];
+        }
+
+        (0..self.height())
+            .step_by(offset_y)
+            .enumerate()
+            .map(|(step, y)| (step * offset_x, y))
+            .collect()
+    }
+
+    /// Count trees for every slope with `0..=max_x` and `1..=max_y` offsets.
+    /// Returns `((offset_x, offset_y), tree_count)` tuples.
+    ///
+    /// # Arguments
+    ///
+    /// * `max_x` - Maximum X offset
+    /// * `max_y` - Maximum Y offset
+    pub fn scan_slopes(&self, max_x: usize, max_y: usize) -> Vec<((usize, usize), usize)> {
+        (1..=max_y)
+            .flat_map(|y| (0..=max_x).map(move |x| (x, y)))
+            .map(|(x, y)| ((x, y), self.follow_slope(x, y)))
+            .collect()
+    }
+
+    /// Find slope with the fewest trees, with `0..=max_x` and `1..=max_y` offsets.
+    /// On ties, the first scanned slope wins.
+    ///
+    /// # Arguments
+    ///
+    /// * `max_x` - Maximum X offset
+    /// * `max_y` - Maximum Y offset
+    pub fn find_best_slope(&self, max_x: usize, max_y: usize) -> Option<((usize, usize), usize)> {
+        self.scan_slopes(max_x, max_y)
+            .into_iter()
+            .min_by_key(|(_, trees)| *trees)
+    }
+
+    /// Find the path hitting the fewest trees, when the toboggan can choose
+    /// one of `moves` at each step.
+    /// Moves without vertical offset are ignored.
+    ///
+    /// # Arguments
+    ///
+    /// * `moves` - `(offset_x, offset_y)` moves
+    pub fn find_min_tree_path(&self, moves: &[(usize, usize)]) -> Option<TobogganPath> {
+        let moves: Vec<(usize, usize)> = moves.iter().copied().filter(|(_, y)| *y > 0).collect();
+        let (width, height) = (self.width, self.height());
+        if moves.is_empty() || height == 0 {
+            return None;
+        }
+
+        // cost[y][x]: trees hit from (x, y) to the bottom, with the best move index
+        let mut cost = vec![vec![(0, 0); width]; height];
+        for y in (0..height).rev() {
+            for x in 0..width {
+                let tree = matches!(self.get_xy(x, y), Some(MapCell::Tree)) as usize;
+                let (best_idx, best_cost) = moves
+                    .iter()
+                    .map(|(dx, dy)| {
+                        if y + dy >= height {
+                            0
+                        } else {
+                            cost[y + dy][(x + dx) % width].0
+                        }
+                    })
+                    .enumerate()
+                    .min_by_key(|(_, c)| *c)
+                    .unwrap();
+
+                cost[y][x] = (tree + best_cost, best_idx);
+            }
+        }
+
+        let mut positions = vec![];
+        let (mut x, mut y) = (0, 0);
+        while y < height {
+            positions.push((x, y));
+            let (dx, dy) = moves[cost[y][x % width].1];
+            x += dx;
+            y += dy;
+        }
+
+        Some(TobogganPath {
+            trees: cost[0][0].0,
+            positions,
+        })
+    }
+
+    /// Render map with visited positions marked as `O` (empty) or `X` (tree).
+    /// The map is repeated to the right to include every position.
+    ///
+    /// # Arguments
+    ///
+    /// * `positions` - Visited positions
+    pub fn render_path(&self, positions: &[(usize, usize)]) -> String {
+        let max_x = positions.iter().map(|(x, _)| *x).max().unwrap_or(0);
+        let repeat = max_x / self.width + 1;
+        let marked: HashSet<&(usize, usize)> = positions.iter().collect();
+
+        (0..self.height())
+            .map(|y| {
+                (0..self.width * repeat)
+                    .map(|x| match (self.get_xy(x, y), marked.contains(&(x, y))) {
+                        (Some(MapCell::Tree), true) => 'X',
+                        (Some(MapCell::Tree), false) => '#',
+                        (_, true) => 'O',
+                        (_, false) => '.',
+                    })
+                    .collect::<String>()
+            })
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+
+    /// Render map with the slope trajectory, like in the puzzle text.
+    /// The starting position is not marked.
+    ///
+    /// # Arguments
+    ///
+    /// * `offset_x` - X offset
+    /// * `offset_y` - Y offset
+    pub fn render_slope(&self, offset_x: usize, offset_y: usize) -> String {
+        let positions = self.slope_positions(offset_x, offset_y);
+        self.render_path(positions.get(1..).unwrap_or_default())
+    }
+
     /// Parse toboggan map from input text.
     ///
     /// # Arguments
@@ -190,6 +341,80 @@ mod tests {
     const EX1_OUTPUT: usize = 299;
     const EX2_OUTPUT: usize = 3_621_285_278;
 
+    const SAMPLE: &str = indoc::indoc!(
+        "
+        ..##.......
+        #...#...#..
+        .#....#..#.
+        ..#.#...#.#
+        .#...##..#.
+        ..#.##.....
+        .#.#.#....#
+        .#........#
+        #.##...#...
+        #...##....#
+        .#..#...#.#"
+    );
+
+    #[test]
+    fn test_follow_slope() {
+        let map = TobogganMap::from_input(SAMPLE);
+        let trees: Vec<usize> = EX2_SLOPES
+            .iter()
+            .map(|(x, y)| map.follow_slope(*x, *y))
+            .collect();
+
+        assert_eq!(trees, vec![2, 7, 3, 4, 2]);
+    }
+
+    #[test]
+    fn test_find_best_slope() {
+        let map = TobogganMap::from_input(SAMPLE);
+        let scan = map.scan_slopes(7, 2);
+
+        assert_eq!(scan.len(), 16);
+        assert!(scan.contains(&((3, 1), 7)));
+        assert_eq!(map.find_best_slope(7, 2), Some(((5, 2), 0)));
+    }
+
+    #[test]
+    fn test_render_slope() {
+        let map = TobogganMap::from_input(SAMPLE);
+        let expected = indoc::indoc!(
+            "
+            ..##.........##.........##.......
+            #..O#...#..#...#...#..#...#...#..
+            .#....X..#..#....#..#..#....#..#.
+            ..#.#...#O#..#.#...#.#..#.#...#.#
+            .#...##..#..X...##..#..#...##..#.
+            ..#.##.......#.X#.......#.##.....
+            .#.#.#....#.#.#.#.O..#.#.#.#....#
+            .#........#.#........X.#........#
+            #.##...#...#.##...#...#.X#...#...
+            #...##....##...##....##...#X....#
+            .#..#...#.#.#..#...#.#.#..#...X.#"
+        );
+
+        assert_eq!(map.render_slope(3, 1), expected);
+    }
+
+    #[test]
+    fn test_find_min_tree_path() {
+        let map = TobogganMap::from_input(SAMPLE);
+        let path = map.find_min_tree_path(&[(3, 1)]).unwrap();
+        assert_eq!(path.trees, 7);
+        assert_eq!(path.positions, map.slope_positions(3, 1));
+
+        let path = map.find_min_tree_path(&EX2_SLOPES).unwrap();
+        assert_eq!(path.trees, 0);
+        assert!(path
+            .positions
+            .iter()
+            .all(|(x, y)| matches!(map.get_xy(*x, *y), Some(MapCell::Empty))));
+
+        assert_eq!(map.find_min_tree_path(&[(1, 0)]), None);
+    }
+
     #[test]
     fn test_run_ex1() {
         assert_eq!(run_ex1(), EX1_OUTPUT);
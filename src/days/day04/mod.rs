@@ -125,18 +125,19 @@
 //!
 //! Count the number of valid passports - those that have all required fields and valid values. Continue to treat cid as optional. In your batch file, how many passports are valid?
 
-use std::collections::HashMap;
+use std::{collections::BTreeMap, convert::TryFrom, path::Path};
 
 use once_cell::sync::Lazy;
 use regex::Regex;
+use serde::Deserialize;
+use thiserror::Error;
 
 const INPUT_VALUES: &str = include_str!("input.txt");
-const REQUIRED_FIELDS: &[&str] = &["byr", "iyr", "eyr", "hgt", "hcl", "ecl", "pid"];
-const VALID_EYE_COLOR: &[&str] = &["amb", "blu", "brn", "gry", "grn", "hzl", "oth"];
-static HEIGHT_RGX: Lazy<Regex> =
-    Lazy::new(|| Regex::new(r"^(?P<amount>\d+)(?P<unit>cm|in)$").unwrap());
-static COLOR_RGX: Lazy<Regex> = Lazy::new(|| Regex::new(r"^#(?P<color>[0-9a-f]{6})$").unwrap());
-static PID_RGX: Lazy<Regex> = Lazy::new(|| Regex::new(r"^(?P<num>\d{9})$").unwrap());
+const SCHEMA_VALUES: &str = include_str!("schema.toml");
+static DEFAULT_SCHEMA: Lazy<PassportSchema> =
+    Lazy::new(|| PassportSchema::from_toml(SCHEMA_VALUES).unwrap());
+static UNIT_VALUE_RGX: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"^(?P<amount>\d+)(?P<unit>[a-z]+)$").unwrap());
 
 /// Part one answer.
 pub fn run_ex1() -> usize {
@@ -154,8 +155,260 @@ pub fn run_ex2() -> usize {
         .count()
 }
 
+/// Day error.
+#[derive(Debug, Error)]
+pub enum DayError {
+    /// Schema parse error
+    #[error("Schema parse error: {0}")]
+    SchemaParseError(String),
+    /// Schema read error
+    #[error("Schema read error: {0}")]
+    SchemaReadError(#[from] std::io::Error),
+}
+
+/// Field validation error.
+#[derive(Debug, Error, PartialEq, Eq, Clone)]
+pub enum ValidationError {
+    /// Value is not an integer
+    #[error("'{0}' is not an integer")]
+    NotAnInteger(String),
+    /// Value is out of range
+    #[error("{value} is not between {min} and {max}")]
+    OutOfRange {
+        /// Value
+        value: i64,
+        /// Minimum value
+        min: i64,
+        /// Maximum value
+        max: i64,
+    },
+    /// Value has no unit, or an unknown unit
+    #[error("'{0}' has no valid unit")]
+    UnknownUnit(String),
+    /// Value does not match pattern
+    #[error("'{value}' does not match pattern '{pattern}'")]
+    PatternMismatch {
+        /// Value
+        value: String,
+        /// Pattern
+        pattern: String,
+    },
+    /// Value is not in the allowed set
+    #[error("'{0}' is not an allowed value")]
+    NotAllowed(String),
+}
+
+/// Field validator.
+#[derive(Debug, Deserialize)]
+#[serde(tag = "type", rename_all = "kebab-case")]
+pub enum FieldValidator {
+    /// Integer in the `min..=max` range
+    IntRange {
+        /// Minimum value
+        min: i64,
+        /// Maximum value
+        max: i64,
+    },
+    /// Integer followed by a unit, each unit having its own `[min, max]` range
+    UnitRange {
+        /// Ranges per unit
+        units: BTreeMap<String, (i64, i64)>,
+    },
+    /// Whole value matches a regular expression
+    Regex {
+        /// Pattern
+        pattern: FieldPattern,
+    },
+    /// Value is one of a fixed set
+    OneOf {
+        /// Allowed values
+        values: Vec<String>,
+    },
+}
+
+/// Regular expression matching a whole field value.
+#[derive(Debug, Deserialize)]
+#[serde(try_from = "String")]
+pub struct FieldPattern {
+    source: String,
+    regex: Regex,
+}
+
+impl TryFrom<String> for FieldPattern {
+    type Error = regex::Error;
+
+    fn try_from(source: String) -> Result<Self, Self::Error> {
+        let regex = Regex::new(&format!("^(?:{})$", source))?;
+        Ok(Self { source, regex })
+    }
+}
+
+impl FieldValidator {
+    /// Validate value.
+    ///
+    /// # Arguments
+    ///
+    /// * `value` - Field value
+    pub fn validate(&self, value: &str) -> Result<(), ValidationError> {
+        match self {
+            Self::IntRange { min, max } => {
+                Self::validate_range(Self::parse_int(value)?, *min, *max)
+            }
+            Self::UnitRange { units } => {
+                let captures = UNIT_VALUE_RGX
+                    .captures(value)
+                    .ok_or_else(|| ValidationError::UnknownUnit(value.to_string()))?;
+                let (min, max) = units
+                    .get(&captures["unit"])
+                    .ok_or_else(|| ValidationError::UnknownUnit(value.to_string()))?;
+                Self::validate_range(Self::parse_int(&captures["amount"])?, *min, *max)
+            }
+            Self::Regex { pattern } => {
+                if pattern.regex.is_match(value) {
+                    Ok(())
+                } else {
+                    Err(ValidationError::PatternMismatch {
+                        value: value.to_string(),
+                        pattern: pattern.source.clone(),
+                    })
+                }
+            }
+            Self::OneOf { values } => {
+                if values.iter().any(|v| v == value) {
+                    Ok(())
+                } else {
+                    Err(ValidationError::NotAllowed(value.to_string()))
+                }
+            }
+        }
+    }
+
+    fn parse_int(value: &str) -> Result<i64, ValidationError> {
+        value
+            .parse()
+            .map_err(|_| ValidationError::NotAnInteger(value.to_string()))
+    }
+
+    fn validate_range(value: i64, min: i64, max: i64) -> Result<(), ValidationError> {
+        if (min..=max).contains(&value) {
+            Ok(())
+        } else {
+            Err(ValidationError::OutOfRange { value, min, max })
+        }
+    }
+}
+
+/// Field schema.
+#[derive(Debug, Deserialize)]
+pub struct FieldSchema {
+    /// Field name
+    pub name: String,
+    /// Is the field required
+    #[serde(default)]
+    pub required: bool,
+    /// Field validator, any value is valid if missing
+    pub validator: Option<FieldValidator>,
+}
+
+/// Passport schema.
+#[derive(Debug, Deserialize)]
+pub struct PassportSchema {
+    /// Fields
+    pub fields: Vec<FieldSchema>,
+}
+
+impl PassportSchema {
+    /// Get the puzzle schema.
+    pub fn puzzle() -> &'static Self {
+        &DEFAULT_SCHEMA
+    }
+
+    /// Parse schema from TOML.
+    ///
+    /// # Arguments
+    ///
+    /// * `input` - TOML input
+    pub fn from_toml(input: &str) -> Result<Self, DayError> {
+        toml::from_str(input).map_err(|e| DayError::SchemaParseError(e.to_string()))
+    }
+
+    /// Load schema from a TOML file.
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - File path
+    pub fn from_file<P: AsRef<Path>>(path: P) -> Result<Self, DayError> {
+        Self::from_toml(&std::fs::read_to_string(path)?)
+    }
+
+    /// Get field schema from name.
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - Field name
+    pub fn get_field(&self, name: &str) -> Option<&FieldSchema> {
+        self.fields.iter().find(|f| f.name == name)
+    }
+}
+
+/// Field validation result.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub enum FieldResult {
+    /// Required field is missing
+    Missing,
+    /// Optional field is absent
+    Absent,
+    /// Field is invalid
+    Invalid(ValidationError),
+    /// Field is valid
+    Valid,
+}
+
+/// Passport validation report.
+#[derive(Debug)]
+pub struct PassportReport {
+    /// Results for each schema field, in schema order
+    pub fields: Vec<(String, FieldResult)>,
+}
+
+impl PassportReport {
+    /// Check if every required field is present.
+    pub fn has_required_fields(&self) -> bool {
+        self.fields.iter().all(|(_, r)| *r != FieldResult::Missing)
+    }
+
+    /// Check if every required field is present and every field is valid.
+    pub fn is_valid(&self) -> bool {
+        self.fields
+            .iter()
+            .all(|(_, r)| matches!(r, FieldResult::Valid | FieldResult::Absent))
+    }
+
+    /// Get failing fields.
+    pub fn failures(&self) -> impl Iterator<Item = &(String, FieldResult)> {
+        self.fields
+            .iter()
+            .filter(|(_, r)| matches!(r, FieldResult::Missing | FieldResult::Invalid(_)))
+    }
+}
+
+impl std::fmt::Display for PassportReport {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for (name, result) in &self.fields {
+            match result {
+                FieldResult::Missing => writeln!(f, "{}: missing", name)?,
+                FieldResult::Absent => writeln!(f, "{}: absent", name)?,
+                FieldResult::Invalid(e) => writeln!(f, "{}: invalid ({})", name, e)?,
+                FieldResult::Valid => writeln!(f, "{}: valid", name)?,
+            }
+        }
+
+        Ok(())
+    }
+}
+
 /// Passport validator.
-pub struct PassportValidator(HashMap<String, String>);
+pub struct PassportValidator(BTreeMap<String, String>);
 
 impl PassportValidator {
     /// Parse passport entry.
@@ -167,85 +420,65 @@ impl PassportValidator {
         Self(
             entry
                 .split_whitespace()
-                .map(|e| {
-                    let mut spl = e.split_terminator(':');
-                    let key = spl.next().map(ToOwned::to_owned).unwrap();
-                    let value = spl.next().map(ToOwned::to_owned).unwrap();
-                    (key, value)
+                .filter_map(|e| {
+                    let mut spl = e.splitn(2, ':');
+                    let key = spl.next().map(ToOwned::to_owned)?;
+                    let value = spl.next().map(ToOwned::to_owned)?;
+                    Some((key, value))
                 })
                 .collect(),
         )
     }
 
-    /// Validate passport field.
+    /// Validate passport field against the puzzle schema.
+    /// Unknown fields are invalid.
     ///
     /// # Arguments
     ///
     /// * `key` - Field key
     /// * `value` - Field value
     pub fn try_validate_field(key: &str, value: &str) -> bool {
-        match key {
-            "byr" => {
-                // At least 1920 at most 2002
-                let value: usize = value.parse().unwrap();
-                (1920..=2002).contains(&value)
-            }
-            "iyr" => {
-                // At least 2010 at most 2020
-                let value: usize = value.parse().unwrap();
-                (2010..=2020).contains(&value)
-            }
-            "eyr" => {
-                // At least 2020 at most 2030
-                let value: usize = value.parse().unwrap();
-                (2020..=2030).contains(&value)
-            }
-            "hgt" => {
-                if let Some(v) = HEIGHT_RGX.captures(value) {
-                    let amount = v
-                        .name("amount")
-                        .and_then(|x| x.as_str().parse::<usize>().ok())
-                        .unwrap();
-                    let unit = v.name("unit").map(|x| x.as_str()).unwrap();
-
-                    match unit {
-                        // At least 150 at most 193
-                        "cm" => (150..=193).contains(&amount),
-                        // At least 59 at most 76
-                        "in" => (59..=76).contains(&amount),
-                        _ => unreachable!(),
-                    }
-                } else {
-                    false
-                }
-            }
-            "hcl" => COLOR_RGX.is_match(value),
-            "ecl" => VALID_EYE_COLOR.contains(&value),
-            "pid" => PID_RGX.is_match(value),
-            _ => false,
+        PassportSchema::puzzle()
+            .get_field(key)
+            .and_then(|f| f.validator.as_ref())
+            .is_some_and(|v| v.validate(value).is_ok())
+    }
+
+    /// Validate passport against a schema.
+    ///
+    /// # Arguments
+    ///
+    /// * `schema` - Passport schema
+    pub fn validate(&self, schema: &PassportSchema) -> PassportReport {
+        PassportReport {
+            fields: schema
+                .fields
+                .iter()
+                .map(|field| {
+                    let result = match (self.0.get(&field.name), &field.validator) {
+                        (None, _) if field.required => FieldResult::Missing,
+                        (None, _) => FieldResult::Absent,
+                        (Some(value), Some(validator)) => validator
+                            .validate(value)
+                            .map_or_else(FieldResult::Invalid, |_| FieldResult::Valid),
+                        (Some(_), None) => FieldResult::Valid,
+                    };
+
+                    (field.name.clone(), result)
+                })
+                .collect(),
         }
     }
 
-    /// Check if passport is valid.
+    /// Check if passport has every required field of the puzzle schema.
     pub fn is_valid(&self) -> bool {
-        REQUIRED_FIELDS
-            .iter()
-            .filter(|&x| self.0.contains_key(*x))
-            .count()
-            == REQUIRED_FIELDS.len()
+        self.validate(PassportSchema::puzzle())
+            .has_required_fields()
     }
 
-    /// Check if passport is valid.
+    /// Check if passport is valid against the puzzle schema.
     pub fn is_valid_full(&self) -> bool {
-        REQUIRED_FIELDS
-            .iter()
-            .filter(|&x| {
-                self.0
-                    .get(*x)
-                    .map_or(false, |v| Self::try_validate_field(x, v))
-            })
-            .count()
-            == REQUIRED_FIELDS.len()
+        self.validate(PassportSchema::puzzle()).is_valid()
     }
 
     /// Parse multiple passport entries.
@@ -265,6 +498,108 @@ mod tests {
     const EX1_OUTPUT: usize = 213;
     const EX2_OUTPUT: usize = 147;
 
+    #[test]
+    fn test_try_validate_field() {
+        assert!(PassportValidator::try_validate_field("byr", "2002"));
+        assert!(!PassportValidator::try_validate_field("byr", "2003"));
+        assert!(!PassportValidator::try_validate_field("byr", "abcd"));
+        assert!(PassportValidator::try_validate_field("hgt", "60in"));
+        assert!(PassportValidator::try_validate_field("hgt", "190cm"));
+        assert!(!PassportValidator::try_validate_field("hgt", "190in"));
+        assert!(!PassportValidator::try_validate_field("hgt", "190"));
+        assert!(PassportValidator::try_validate_field("hcl", "#123abc"));
+        assert!(!PassportValidator::try_validate_field("hcl", "#123abz"));
+        assert!(!PassportValidator::try_validate_field("hcl", "123abc"));
+        assert!(PassportValidator::try_validate_field("ecl", "brn"));
+        assert!(!PassportValidator::try_validate_field("ecl", "wat"));
+        assert!(PassportValidator::try_validate_field("pid", "000000001"));
+        assert!(!PassportValidator::try_validate_field("pid", "0123456789"));
+    }
+
+    #[test]
+    fn test_validate_report() {
+        let passport = PassportValidator::parse_entry(
+            "eyr:1972 cid:100 hcl:#18171d ecl:amb hgt:170 pid:186cm iyr:2018 byr:19x6",
+        );
+        let report = passport.validate(PassportSchema::puzzle());
+
+        assert!(report.has_required_fields());
+        assert!(!report.is_valid());
+        assert_eq!(
+            report.failures().cloned().collect::<Vec<_>>(),
+            vec![
+                (
+                    "byr".to_string(),
+                    FieldResult::Invalid(ValidationError::NotAnInteger("19x6".into()))
+                ),
+                (
+                    "eyr".to_string(),
+                    FieldResult::Invalid(ValidationError::OutOfRange {
+                        value: 1972,
+                        min: 2020,
+                        max: 2030
+                    })
+                ),
+                (
+                    "hgt".to_string(),
+                    FieldResult::Invalid(ValidationError::UnknownUnit("170".into()))
+                ),
+                (
+                    "pid".to_string(),
+                    FieldResult::Invalid(ValidationError::PatternMismatch {
+                        value: "186cm".into(),
+                        pattern: "[0-9]{9}".into()
+                    })
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_custom_schema() {
+        let schema = PassportSchema::from_toml(indoc::indoc!(
+            r#"
+            [[fields]]
+            name = "name"
+            required = true
+
+            [[fields]]
+            name = "age"
+            validator = { type = "int-range", min = 0, max = 150 }
+            "#
+        ))
+        .unwrap();
+
+        let report = PassportValidator::parse_entry("age:200").validate(&schema);
+        assert_eq!(
+            report.fields,
+            vec![
+                ("name".to_string(), FieldResult::Missing),
+                (
+                    "age".to_string(),
+                    FieldResult::Invalid(ValidationError::OutOfRange {
+                        value: 200,
+                        min: 0,
+                        max: 150
+                    })
+                )
+            ]
+        );
+        assert_eq!(
+            report.to_string(),
+            "name: missing\nage: invalid (200 is not between 0 and 150)\n"
+        );
+
+        let report = PassportValidator::parse_entry("name:Bob").validate(&schema);
+        assert!(report.is_valid());
+
+        assert!(PassportSchema::from_toml("[[fields]]\nrequired = true").is_err());
+        assert!(PassportSchema::from_toml(
+            "[[fields]]\nname = \"a\"\nvalidator = { type = \"regex\", pattern = \"(\" }"
+        )
+        .is_err());
+    }
+
     #[test]
     fn test_run_ex1() {
         assert_eq!(run_ex1(), EX1_OUTPUT);
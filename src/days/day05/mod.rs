@@ -50,11 +50,20 @@
 //!
 //! What is the ID of your seat?
 
+use std::{collections::BTreeSet, ops::RangeInclusive};
+
+use itertools::Itertools;
+use thiserror::Error;
+
 const INPUT_VALUES: &str = include_str!("input.txt");
 
+/// Maximum seat count of a printable seat map.
+pub const MAX_MAP_SEATS: usize = 1 << 20;
+
 /// Part one answer.
 pub fn run_ex1() -> usize {
     BoardingPass::from_entries(INPUT_VALUES)
+        .unwrap()
         .iter()
         .map(BoardingPass::get_seat_id)
         .max()
@@ -63,21 +72,288 @@ pub fn run_ex1() -> usize {
 
 /// Part two answer.
 pub fn run_ex2() -> usize {
-    let passes = BoardingPass::from_entries(INPUT_VALUES);
+    let passes = BoardingPass::from_entries(INPUT_VALUES).unwrap();
+
+    PlaneLayout::default()
+        .find_seat_gaps(&passes)
+        .into_iter()
+        .find(|gap| gap.size() == 1 && gap.before.is_some() && gap.after.is_some())
+        .map(|gap| gap.start)
+        .expect("Seat not found")
+}
+
+/// Day error.
+#[derive(Debug, Error, PartialEq, Eq)]
+pub enum DayError {
+    /// Entry has a wrong length
+    #[error("Entry '{entry}' should have {expected} letters")]
+    InvalidLength {
+        /// Entry
+        entry: String,
+        /// Expected length
+        expected: usize,
+    },
+    /// Entry has an unexpected letter
+    #[error("Unexpected letter '{letter}' at position {position} in entry '{entry}'")]
+    InvalidLetter {
+        /// Entry
+        entry: String,
+        /// Letter
+        letter: char,
+        /// Position
+        position: usize,
+    },
+    /// Layout seat IDs do not fit in a machine word
+    #[error("Layout with {row_bits} row bits and {column_bits} column bits is too large")]
+    InvalidLayout {
+        /// Row bit width
+        row_bits: u32,
+        /// Column bit width
+        column_bits: u32,
+    },
+    /// Seat map is too large to be printed
+    #[error("Seat map with {rows} rows and {columns} columns is too large to print")]
+    MapTooLarge {
+        /// Row count
+        rows: usize,
+        /// Column count
+        columns: usize,
+    },
+    /// Seat is outside of the layout
+    #[error("Seat at row {row} and column {column} is outside of the layout")]
+    SeatOutOfRange {
+        /// Row
+        row: usize,
+        /// Column
+        column: usize,
+    },
+}
+
+/// Plane layout, with row and column bit widths.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PlaneLayout {
+    row_bits: u32,
+    column_bits: u32,
+}
+
+impl Default for PlaneLayout {
+    fn default() -> Self {
+        Self {
+            row_bits: 7,
+            column_bits: 3,
+        }
+    }
+}
+
+impl PlaneLayout {
+    /// Create plane layout.
+    /// Seat IDs use `row_bits + column_bits` bits, which must be less than `usize::BITS`.
+    ///
+    /// # Arguments
+    ///
+    /// * `row_bits` - Row bit width
+    /// * `column_bits` - Column bit width
+    pub fn new(row_bits: u32, column_bits: u32) -> Result<Self, DayError> {
+        if row_bits
+            .checked_add(column_bits)
+            .is_none_or(|bits| bits >= usize::BITS)
+        {
+            return Err(DayError::InvalidLayout {
+                row_bits,
+                column_bits,
+            });
+        }
+
+        Ok(Self {
+            row_bits,
+            column_bits,
+        })
+    }
+
+    /// Get row count.
+    pub const fn rows(&self) -> usize {
+        1 << self.row_bits
+    }
+
+    /// Get column count.
+    pub const fn columns(&self) -> usize {
+        1 << self.column_bits
+    }
+
+    /// Get seat ID from row and column.
+    ///
+    /// # Arguments
+    ///
+    /// * `row` - Row
+    /// * `column` - Column
+    pub const fn seat_id(&self, row: usize, column: usize) -> usize {
+        row * self.columns() + column
+    }
+
+    /// Decode boarding pass entry.
+    ///
+    /// # Arguments
+    ///
+    /// * `entry` - Boarding entry
+    pub fn decode(&self, entry: &str) -> Result<BoardingPass, DayError> {
+        let expected = (self.row_bits + self.column_bits) as usize;
+        if entry.chars().count() != expected {
+            return Err(DayError::InvalidLength {
+                entry: entry.to_string(),
+                expected,
+            });
+        }
 
-    let mut seats: Vec<usize> = passes.iter().map(BoardingPass::get_seat_id).collect();
-    seats.sort_unstable();
+        let mut row = 0;
+        let mut column = 0;
+        for (position, letter) in entry.chars().enumerate() {
+            let in_row = position < self.row_bits as usize;
+            match (in_row, letter) {
+                (true, 'F') => row <<= 1,
+                (true, 'B') => row = (row << 1) | 1,
+                (false, 'L') => column <<= 1,
+                (false, 'R') => column = (column << 1) | 1,
+                _ => {
+                    return Err(DayError::InvalidLetter {
+                        entry: entry.to_string(),
+                        letter,
+                        position,
+                    })
+                }
+            }
+        }
+
+        Ok(BoardingPass { row, column })
+    }
+
+    /// Encode boarding pass to entry.
+    /// Fails if the seat is outside of the layout.
+    ///
+    /// # Arguments
+    ///
+    /// * `pass` - Boarding pass
+    pub fn encode(&self, pass: &BoardingPass) -> Result<String, DayError> {
+        if pass.row >= self.rows() || pass.column >= self.columns() {
+            return Err(DayError::SeatOutOfRange {
+                row: pass.row,
+                column: pass.column,
+            });
+        }
+
+        let bits = |value: usize, width: u32, zero: char, one: char| {
+            (0..width)
+                .rev()
+                .map(move |b| if value >> b & 1 == 1 { one } else { zero })
+        };
+
+        Ok(bits(pass.row, self.row_bits, 'F', 'B')
+            .chain(bits(pass.column, self.column_bits, 'L', 'R'))
+            .collect())
+    }
+
+    /// Find every gap of free seats, in seat ID order.
+    /// Free seats before the first or after the last occupied seat are
+    /// reported as gaps without neighbours.
+    ///
+    /// # Arguments
+    ///
+    /// * `passes` - Boarding passes
+    pub fn find_seat_gaps(&self, passes: &[BoardingPass]) -> Vec<SeatGap> {
+        let last_seat = self.rows() * self.columns() - 1;
+        let occupied = self.occupied_seats(passes);
+
+        // Gaps can only lie between consecutive occupied seats, or at both ends
+        std::iter::once(None)
+            .chain(occupied.into_iter().map(Some))
+            .chain(std::iter::once(None))
+            .tuple_windows()
+            .filter_map(|(before, after)| {
+                let start = before.map_or(0, |b| b + 1);
+                let end = after.map_or(Some(last_seat), |a| a.checked_sub(1))?;
+
+                (start <= end).then_some(SeatGap {
+                    start,
+                    end,
+                    before,
+                    after,
+                })
+            })
+            .collect()
+    }
+
+    /// Get every range of free seat IDs.
+    ///
+    /// # Arguments
+    ///
+    /// * `passes` - Boarding passes
+    pub fn find_missing_seats(&self, passes: &[BoardingPass]) -> Vec<RangeInclusive<usize>> {
+        self.find_seat_gaps(passes)
+            .iter()
+            .map(|g| g.start..=g.end)
+            .collect()
+    }
 
-    let mut last = 0_usize;
-    for s in seats {
-        if last != 0 && last != s - 1 {
-            return s - 1;
+    /// Render seat map, with one line per row.
+    /// Occupied seats are shown as `#` and free seats as `.`.
+    /// Fails if the layout has more than `MAX_MAP_SEATS` seats.
+    ///
+    /// # Arguments
+    ///
+    /// * `passes` - Boarding passes
+    pub fn render_seat_map(&self, passes: &[BoardingPass]) -> Result<String, DayError> {
+        if self.rows() * self.columns() > MAX_MAP_SEATS {
+            return Err(DayError::MapTooLarge {
+                rows: self.rows(),
+                columns: self.columns(),
+            });
         }
 
-        last = s;
+        let occupied = self.occupied_seats(passes);
+
+        Ok((0..self.rows())
+            .map(|row| {
+                (0..self.columns())
+                    .map(|column| {
+                        if occupied.contains(&self.seat_id(row, column)) {
+                            '#'
+                        } else {
+                            '.'
+                        }
+                    })
+                    .collect::<String>()
+            })
+            .collect::<Vec<_>>()
+            .join("\n"))
     }
 
-    panic!("Seat not found");
+    /// Get occupied seat IDs, ignoring seats outside of the layout.
+    fn occupied_seats(&self, passes: &[BoardingPass]) -> BTreeSet<usize> {
+        passes
+            .iter()
+            .filter(|p| p.row < self.rows() && p.column < self.columns())
+            .map(|p| self.seat_id(p.row, p.column))
+            .collect()
+    }
+}
+
+/// Gap of consecutive free seats.
+#[derive(Debug, PartialEq, Eq)]
+pub struct SeatGap {
+    /// First free seat ID
+    pub start: usize,
+    /// Last free seat ID
+    pub end: usize,
+    /// Occupied seat ID before the gap
+    pub before: Option<usize>,
+    /// Occupied seat ID after the gap
+    pub after: Option<usize>,
+}
+
+impl SeatGap {
+    /// Get free seat count.
+    pub const fn size(&self) -> usize {
+        self.end - self.start + 1
+    }
 }
 
 /// Boarding pass.
@@ -88,12 +364,22 @@ pub struct BoardingPass {
 }
 
 impl BoardingPass {
+    /// Create boarding pass.
+    ///
+    /// # Arguments
+    ///
+    /// * `row` - Row
+    /// * `column` - Column
+    pub const fn new(row: usize, column: usize) -> Self {
+        Self { row, column }
+    }
+
     /// Convert entries to boarding passes.
     ///
     /// # Arguments
     ///
     /// * `entries` - Boarding entries
-    pub fn from_entries(entries: &str) -> Vec<Self> {
+    pub fn from_entries(entries: &str) -> Result<Vec<Self>, DayError> {
         entries.lines().map(Self::from_entry).collect()
     }
 
@@ -102,39 +388,19 @@ impl BoardingPass {
     /// # Arguments
     ///
     /// * `entry` - Boarding entry
-    pub fn from_entry(entry: &str) -> Self {
-        // Convert row as binary to decimal (7 first letters)
-        let row = usize::from_str_radix(
-            &entry
-                .chars()
-                .take(7)
-                .map(|x| match x {
-                    'F' => '0',
-                    'B' => '1',
-                    e => panic!("Unknown letter {}", e),
-                })
-                .collect::<String>(),
-            2,
-        )
-        .unwrap();
-
-        // Convert column as binary to decimal (3 last letters)
-        let column = usize::from_str_radix(
-            &entry
-                .chars()
-                .skip(7)
-                .take(3)
-                .map(|x| match x {
-                    'L' => '0',
-                    'R' => '1',
-                    e => panic!("Unknown letter {}", e),
-                })
-                .collect::<String>(),
-            2,
-        )
-        .unwrap();
+    pub fn from_entry(entry: &str) -> Result<Self, DayError> {
+        PlaneLayout::default().decode(entry)
+    }
 
-        Self { row, column }
+    /// Encode row and column to boarding entry.
+    /// Fails if the seat is outside of the default layout.
+    ///
+    /// # Arguments
+    ///
+    /// * `row` - Row
+    /// * `column` - Column
+    pub fn encode(row: usize, column: usize) -> Result<String, DayError> {
+        PlaneLayout::default().encode(&Self::new(row, column))
     }
 
     /// Get seat ID from boarding pass.
@@ -154,22 +420,83 @@ mod tests {
     fn test_from_entry() {
         assert_eq!(
             BoardingPass::from_entry("FBFBBFFRLR"),
-            BoardingPass { row: 44, column: 5 }
+            Ok(BoardingPass { row: 44, column: 5 })
         );
         assert_eq!(
             BoardingPass::from_entry("BFFFBBFRRR"),
-            BoardingPass { row: 70, column: 7 }
+            Ok(BoardingPass { row: 70, column: 7 })
         );
         assert_eq!(
             BoardingPass::from_entry("FFFBBBFRRR"),
-            BoardingPass { row: 14, column: 7 }
+            Ok(BoardingPass { row: 14, column: 7 })
         );
         assert_eq!(
             BoardingPass::from_entry("BBFFBBFRLL"),
-            BoardingPass {
+            Ok(BoardingPass {
                 row: 102,
                 column: 4
-            }
+            })
+        );
+        assert_eq!(
+            BoardingPass::from_entry("BBFFBBFRL"),
+            Err(DayError::InvalidLength {
+                entry: "BBFFBBFRL".into(),
+                expected: 10
+            })
+        );
+        assert_eq!(
+            BoardingPass::from_entry("BBFFBBRRLL"),
+            Err(DayError::InvalidLetter {
+                entry: "BBFFBBRRLL".into(),
+                letter: 'R',
+                position: 6
+            })
+        );
+    }
+
+    #[test]
+    fn test_encode() {
+        assert_eq!(BoardingPass::encode(44, 5), Ok("FBFBBFFRLR".into()));
+        assert_eq!(BoardingPass::encode(102, 4), Ok("BBFFBBFRLL".into()));
+        assert_eq!(
+            BoardingPass::encode(128, 4),
+            Err(DayError::SeatOutOfRange {
+                row: 128,
+                column: 4
+            })
+        );
+
+        let layout = PlaneLayout::new(3, 2).unwrap();
+        let pass = BoardingPass::new(5, 2);
+        assert_eq!(layout.encode(&pass), Ok("BFBRL".into()));
+        assert!(layout.encode(&BoardingPass::new(5, 4)).is_err());
+        assert_eq!(layout.decode("BFBRL"), Ok(pass));
+        assert_eq!(layout.seat_id(5, 2), 22);
+
+        assert_eq!(
+            PlaneLayout::new(usize::BITS, 0),
+            Err(DayError::InvalidLayout {
+                row_bits: usize::BITS,
+                column_bits: 0
+            })
+        );
+        assert!(PlaneLayout::new(u32::MAX, 2).is_err());
+
+        // Huge layouts are only scanned around occupied seats
+        let layout = PlaneLayout::new(60, 3).unwrap();
+        let passes = [BoardingPass::new(1 << 59, 4), BoardingPass::new(1 << 59, 6)];
+        let seat = layout.seat_id(1 << 59, 5);
+        assert_eq!(layout.rows(), 1 << 60);
+        assert_eq!(
+            layout.find_missing_seats(&passes),
+            vec![0..=seat - 2, seat..=seat, seat + 2..=(1 << 63) - 1]
+        );
+        assert_eq!(
+            layout.render_seat_map(&passes),
+            Err(DayError::MapTooLarge {
+                rows: 1 << 60,
+                columns: 8
+            })
         );
     }
 
@@ -188,6 +515,53 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_seat_map_and_gaps() {
+        let layout = PlaneLayout::new(2, 2).unwrap();
+        let passes: Vec<BoardingPass> = [(0, 2), (0, 3), (1, 0), (1, 2), (2, 2), (2, 3)]
+            .iter()
+            .map(|&(r, c)| BoardingPass::new(r, c))
+            .collect();
+
+        assert_eq!(
+            layout.render_seat_map(&passes),
+            Ok("..##\n#.#.\n..##\n....".into())
+        );
+        assert_eq!(
+            layout.find_missing_seats(&passes),
+            vec![0..=1, 5..=5, 7..=9, 12..=15]
+        );
+        assert_eq!(
+            layout.find_seat_gaps(&passes),
+            vec![
+                SeatGap {
+                    start: 0,
+                    end: 1,
+                    before: None,
+                    after: Some(2)
+                },
+                SeatGap {
+                    start: 5,
+                    end: 5,
+                    before: Some(4),
+                    after: Some(6)
+                },
+                SeatGap {
+                    start: 7,
+                    end: 9,
+                    before: Some(6),
+                    after: Some(10)
+                },
+                SeatGap {
+                    start: 12,
+                    end: 15,
+                    before: Some(11),
+                    after: None
+                },
+            ]
+        );
+    }
+
     #[test]
     fn test_run_ex1() {
         assert_eq!(run_ex1(), EX1_OUTPUT);
@@ -84,24 +84,241 @@
 //!
 //! For each group, count the number of questions to which everyone answered "yes". What is the sum of those counts?
 
-use std::collections::HashMap;
+use std::ops::{BitAnd, BitOr, BitXor};
 
 const INPUT_VALUES: &str = include_str!("input.txt");
+const QUESTION_COUNT: usize = 26;
 
 /// Part one answer.
 pub fn run_ex1() -> usize {
-    INPUT_VALUES
-        .split("\n\n")
-        .map(count_unique_questions_for_anyone)
-        .sum()
+    Survey::from_input(INPUT_VALUES).sum_by(|g| g.anyone().len())
 }
 
 /// Part two answer.
 pub fn run_ex2() -> usize {
-    INPUT_VALUES
-        .split("\n\n")
-        .map(count_unique_questions_for_everyone)
-        .sum()
+    Survey::from_input(INPUT_VALUES).sum_by(|g| g.everyone().len())
+}
+
+/// Answers of a person, as a set of questions `a` to `z` stored in a bitset.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Answers(u32);
+
+impl Answers {
+    /// Every question answered.
+    pub const ALL: Self = Self((1 << QUESTION_COUNT) - 1);
+
+    /// Parse answers from line. Characters outside of `a..=z` are ignored.
+    ///
+    /// # Arguments
+    ///
+    /// * `line` - Input line
+    pub fn parse(line: &str) -> Self {
+        Self(
+            line.chars()
+                .filter(char::is_ascii_lowercase)
+                .fold(0, |acc, c| acc | 1 << (c as u8 - b'a')),
+        )
+    }
+
+    /// Check if question was answered.
+    ///
+    /// # Arguments
+    ///
+    /// * `question` - Question, from `a` to `z`
+    pub fn contains(&self, question: char) -> bool {
+        question.is_ascii_lowercase() && self.0 & 1 << (question as u8 - b'a') != 0
+    }
+
+    /// Get answered question count.
+    pub const fn len(&self) -> usize {
+        self.0.count_ones() as usize
+    }
+
+    /// Check if no question was answered.
+    pub const fn is_empty(&self) -> bool {
+        self.0 == 0
+    }
+
+    /// Iterate over answered questions.
+    pub fn iter(&self) -> impl Iterator<Item = char> + '_ {
+        (b'a'..=b'z')
+            .map(char::from)
+            .filter(move |&c| self.contains(c))
+    }
+}
+
+impl BitOr for Answers {
+    type Output = Self;
+
+    fn bitor(self, rhs: Self) -> Self {
+        Self(self.0 | rhs.0)
+    }
+}
+
+impl BitAnd for Answers {
+    type Output = Self;
+
+    fn bitand(self, rhs: Self) -> Self {
+        Self(self.0 & rhs.0)
+    }
+}
+
+impl BitXor for Answers {
+    type Output = Self;
+
+    fn bitxor(self, rhs: Self) -> Self {
+        Self(self.0 ^ rhs.0)
+    }
+}
+
+impl std::fmt::Display for Answers {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.iter().collect::<String>())
+    }
+}
+
+/// Group of persons answers.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct Group {
+    people: Vec<Answers>,
+}
+
+impl Group {
+    /// Parse group from group entries, one person per line.
+    ///
+    /// # Arguments
+    ///
+    /// * `group_entries` - Group entries
+    pub fn parse(group_entries: &str) -> Self {
+        Self {
+            people: group_entries
+                .lines()
+                .map(str::trim)
+                .filter(|l| !l.is_empty())
+                .map(Answers::parse)
+                .collect(),
+        }
+    }
+
+    /// Get answers per person.
+    pub fn people(&self) -> &[Answers] {
+        &self.people
+    }
+
+    /// Get questions where anyone answered 'yes'.
+    pub fn anyone(&self) -> Answers {
+        self.people
+            .iter()
+            .fold(Answers::default(), |acc, &a| acc | a)
+    }
+
+    /// Get questions where everyone answered 'yes'.
+    pub fn everyone(&self) -> Answers {
+        if self.people.is_empty() {
+            return Answers::default();
+        }
+
+        self.people.iter().fold(Answers::ALL, |acc, &a| acc & a)
+    }
+
+    /// Get questions where at least `k` persons answered 'yes'.
+    ///
+    /// # Arguments
+    ///
+    /// * `k` - Minimum person count
+    pub fn at_least(&self, k: usize) -> Answers {
+        self.filter_by_count(|count| count >= k)
+    }
+
+    /// Get questions where exactly one person answered 'yes'.
+    pub fn exactly_one(&self) -> Answers {
+        self.filter_by_count(|count| count == 1)
+    }
+
+    /// Get questions where an odd number of persons answered 'yes'.
+    /// For two persons, this is the symmetric difference of their answers.
+    pub fn symmetric_difference(&self) -> Answers {
+        self.people
+            .iter()
+            .fold(Answers::default(), |acc, &a| acc ^ a)
+    }
+
+    /// Get 'yes' count per question, from `a` to `z`.
+    pub fn histogram(&self) -> [usize; QUESTION_COUNT] {
+        let mut histogram = [0; QUESTION_COUNT];
+        for answers in &self.people {
+            for c in answers.iter() {
+                histogram[(c as u8 - b'a') as usize] += 1;
+            }
+        }
+
+        histogram
+    }
+
+    fn filter_by_count<F>(&self, predicate: F) -> Answers
+    where
+        F: Fn(usize) -> bool,
+    {
+        let histogram = self.histogram();
+        Answers(
+            (0..QUESTION_COUNT)
+                .filter(|&i| predicate(histogram[i]))
+                .fold(0, |acc, i| acc | 1 << i),
+        )
+    }
+}
+
+/// Survey of every group.
+#[derive(Debug, Default)]
+pub struct Survey {
+    groups: Vec<Group>,
+}
+
+impl Survey {
+    /// Parse survey from input string, with groups separated by blank lines.
+    ///
+    /// # Arguments
+    ///
+    /// * `input` - Input string
+    pub fn from_input(input: &str) -> Self {
+        Self {
+            groups: input.split("\n\n").map(Group::parse).collect(),
+        }
+    }
+
+    /// Get groups.
+    pub fn groups(&self) -> &[Group] {
+        &self.groups
+    }
+
+    /// Get total person count.
+    pub fn person_count(&self) -> usize {
+        self.groups.iter().map(|g| g.people.len()).sum()
+    }
+
+    /// Sum a value computed for each group.
+    ///
+    /// # Arguments
+    ///
+    /// * `func` - Function
+    pub fn sum_by<F>(&self, func: F) -> usize
+    where
+        F: Fn(&Group) -> usize,
+    {
+        self.groups.iter().map(func).sum()
+    }
+
+    /// Get 'yes' count per question for every person, from `a` to `z`.
+    pub fn histogram(&self) -> [usize; QUESTION_COUNT] {
+        let mut histogram = [0; QUESTION_COUNT];
+        for group in &self.groups {
+            for (total, count) in histogram.iter_mut().zip(group.histogram().iter()) {
+                *total += count;
+            }
+        }
+
+        histogram
+    }
 }
 
 /// Count unique questions where anyone answered 'yes' for group entries.
@@ -110,13 +327,7 @@ pub fn run_ex2() -> usize {
 ///
 /// * `group_entries` - Group entries
 pub fn count_unique_questions_for_anyone(group_entries: &str) -> usize {
-    let mut entries: Vec<char> = group_entries
-        .lines()
-        .flat_map(|x| x.chars().collect::<Vec<char>>())
-        .collect();
-    entries.sort_unstable();
-    entries.dedup();
-    entries.len()
+    Group::parse(group_entries).anyone().len()
 }
 
 /// Count unique questions where everyone answered 'yes' for group entries.
@@ -125,18 +336,7 @@ pub fn count_unique_questions_for_anyone(group_entries: &str) -> usize {
 ///
 /// * `group_entries` - Group entries
 pub fn count_unique_questions_for_everyone(group_entries: &str) -> usize {
-    let lines: Vec<&str> = group_entries.lines().collect();
-    let group_count = lines.len();
-
-    let mut counter = HashMap::new();
-    for l in lines {
-        for c in l.chars() {
-            let v = counter.entry(c).or_insert(0);
-            *v += 1;
-        }
-    }
-
-    counter.keys().filter(|k| counter[k] == group_count).count()
+    Group::parse(group_entries).everyone().len()
 }
 
 #[cfg(test)]
@@ -146,6 +346,69 @@ mod tests {
     const EX1_OUTPUT: usize = 6714;
     const EX2_OUTPUT: usize = 3435;
 
+    const SAMPLE: &str = indoc::indoc!(
+        "
+        abc
+
+        a
+        b
+        c
+
+        ab
+        ac
+
+        a
+        a
+        a
+        a
+
+        b"
+    );
+
+    #[test]
+    pub fn test_count_unique_questions() {
+        let anyone: Vec<usize> = SAMPLE
+            .split("\n\n")
+            .map(count_unique_questions_for_anyone)
+            .collect();
+        let everyone: Vec<usize> = SAMPLE
+            .split("\n\n")
+            .map(count_unique_questions_for_everyone)
+            .collect();
+
+        assert_eq!(anyone, vec![3, 3, 3, 1, 1]);
+        assert_eq!(everyone, vec![3, 0, 1, 1, 1]);
+    }
+
+    #[test]
+    pub fn test_group_queries() {
+        let group = Group::parse("abcx\nabcy\nabcz\nax");
+
+        assert_eq!(group.anyone().to_string(), "abcxyz");
+        assert_eq!(group.everyone().to_string(), "a");
+        assert_eq!(group.at_least(3).to_string(), "abc");
+        assert_eq!(group.exactly_one().to_string(), "yz");
+        assert_eq!(group.symmetric_difference().to_string(), "bcyz");
+        assert_eq!(
+            Answers::parse("abc") ^ Answers::parse("bcd"),
+            Answers::parse("ad")
+        );
+        assert_eq!(group.histogram()[0], 4);
+        assert_eq!(group.histogram()[23], 2);
+        assert!(Group::parse("").everyone().is_empty());
+    }
+
+    #[test]
+    pub fn test_survey() {
+        let survey = Survey::from_input(SAMPLE);
+
+        assert_eq!(survey.groups().len(), 5);
+        assert_eq!(survey.person_count(), 11);
+        assert_eq!(survey.sum_by(|g| g.anyone().len()), 11);
+        assert_eq!(survey.sum_by(|g| g.everyone().len()), 6);
+        assert_eq!(survey.histogram()[..3], [8, 4, 3]);
+    }
+
     #[test]
     pub fn test_run_ex1() {
         assert_eq!(run_ex1(), EX1_OUTPUT);
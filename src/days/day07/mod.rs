@@ -64,10 +64,11 @@
 //!
 //! How many individual bags are required inside your single shiny gold bag?
 
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 
 use once_cell::sync::Lazy;
 use regex::Regex;
+use thiserror::Error;
 
 const INPUT_VALUES: &str = include_str!("input.txt");
 const INPUT_COLOR_NAME: &str = "shiny gold";
@@ -91,13 +92,30 @@ pub fn run_ex1() -> usize {
 pub fn run_ex2() -> usize {
     let system = BagSystem::new_from_rules(INPUT_VALUES);
     let color: BagColor = INPUT_COLOR_NAME.into();
-    system.count_needed_bags_for_color(&color)
+    system.count_needed_bags_for_color(&color).unwrap()
+}
+
+/// Day error.
+#[derive(Debug, Error, PartialEq, Eq)]
+pub enum DayError {
+    /// Containment cycle
+    #[error("Cycle detected: {}", .0.iter().map(ToString::to_string).collect::<Vec<_>>().join(" -> "))]
+    CycleDetected(Vec<BagColor>),
+    /// Color without rule
+    #[error("Unknown color: {0}")]
+    UnknownColor(BagColor),
 }
 
 /// Bag color
 #[derive(Debug, Clone, Hash, PartialEq, Eq, Ord, PartialOrd)]
 pub struct BagColor(String);
 
+impl std::fmt::Display for BagColor {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
 impl BagColor {
     /// Create a bag color from a str.
     ///
@@ -251,23 +269,153 @@ impl BagSystem {
     /// # Arguments
     ///
     /// * `color` - Known color
-    pub fn count_needed_bags_for_color(&self, color: &BagColor) -> usize {
-        self.count_inner_bags_for_color(color) - 1
+    pub fn count_needed_bags_for_color(&self, color: &BagColor) -> Result<usize, DayError> {
+        Ok(self.count_inner_bags_for_color(color)? - 1)
     }
 
-    /// Count inner bags for a target color.
+    /// Count inner bags for a target color, including the bag itself.
     ///
     /// # Arguments
     ///
     /// * `color` - Known color
-    pub fn count_inner_bags_for_color(&self, inner_color: &BagColor) -> usize {
-        let relations = self.0.get(inner_color).unwrap();
-        let sum = relations
-            .iter()
-            .map(|x| x.amount * self.count_inner_bags_for_color(&x.color))
-            .sum::<usize>()
-            + 1;
-        sum
+    pub fn count_inner_bags_for_color(&self, inner_color: &BagColor) -> Result<usize, DayError> {
+        let mut memory = HashMap::new();
+        let mut stack = vec![];
+        self.count_inner_bags_memoized(inner_color, &mut memory, &mut stack)
+    }
+
+    fn count_inner_bags_memoized(
+        &self,
+        color: &BagColor,
+        memory: &mut HashMap<BagColor, usize>,
+        stack: &mut Vec<BagColor>,
+    ) -> Result<usize, DayError> {
+        if let Some(&count) = memory.get(color) {
+            return Ok(count);
+        }
+
+        if let Some(pos) = stack.iter().position(|c| c == color) {
+            let mut cycle = stack[pos..].to_vec();
+            cycle.push(color.clone());
+            return Err(DayError::CycleDetected(cycle));
+        }
+
+        let relations = self
+            .0
+            .get(color)
+            .ok_or_else(|| DayError::UnknownColor(color.clone()))?;
+
+        stack.push(color.clone());
+        let mut count = 1;
+        for relation in relations {
+            count +=
+                relation.amount * self.count_inner_bags_memoized(&relation.color, memory, stack)?;
+        }
+        stack.pop();
+
+        memory.insert(color.clone(), count);
+        Ok(count)
+    }
+
+    /// Find a containment cycle, if any.
+    /// Returns the cycle path, starting and ending with the same color.
+    pub fn find_cycle(&self) -> Option<Vec<BagColor>> {
+        let mut done: HashSet<&BagColor> = HashSet::new();
+
+        for color in self.sorted_colors() {
+            let mut stack = vec![];
+            if let Some(cycle) = self.find_cycle_from(color, &mut done, &mut stack) {
+                return Some(cycle);
+            }
+        }
+
+        None
+    }
+
+    fn find_cycle_from<'a>(
+        &'a self,
+        color: &'a BagColor,
+        done: &mut HashSet<&'a BagColor>,
+        stack: &mut Vec<&'a BagColor>,
+    ) -> Option<Vec<BagColor>> {
+        if let Some(pos) = stack.iter().position(|&c| c == color) {
+            let mut cycle: Vec<BagColor> = stack[pos..].iter().map(|&c| c.clone()).collect();
+            cycle.push(color.clone());
+            return Some(cycle);
+        }
+
+        if done.contains(color) {
+            return None;
+        }
+
+        stack.push(color);
+        for relation in self.0.get(color).into_iter().flatten() {
+            if let Some(cycle) = self.find_cycle_from(&relation.color, done, stack) {
+                return Some(cycle);
+            }
+        }
+        stack.pop();
+        done.insert(color);
+
+        None
+    }
+
+    /// Export containment graph to Graphviz DOT format, with amounts as edge labels.
+    pub fn to_dot(&self) -> String {
+        let mut output = String::from("digraph bags {\n");
+
+        for color in self.sorted_colors() {
+            output.push_str(&format!("    \"{}\";\n", color));
+            for relation in &self.0[color] {
+                output.push_str(&format!(
+                    "    \"{}\" -> \"{}\" [label=\"{}\"];\n",
+                    color, relation.color, relation.amount
+                ));
+            }
+        }
+
+        output.push('}');
+        output
+    }
+
+    /// Render the nested contents tree of a color.
+    /// Each line shows the amount in the parent bag and the total amount.
+    ///
+    /// # Arguments
+    ///
+    /// * `color` - Known color
+    pub fn bill_of_materials(&self, color: &BagColor) -> Result<String, DayError> {
+        let total = self.count_needed_bags_for_color(color)?;
+        let mut lines = vec![format!("{} ({} bags inside)", color, total)];
+        self.push_bill_lines(color, 1, 1, &mut lines);
+
+        Ok(lines.join("\n"))
+    }
+
+    fn push_bill_lines(
+        &self,
+        color: &BagColor,
+        depth: usize,
+        factor: usize,
+        lines: &mut Vec<String>,
+    ) {
+        for relation in &self.0[color] {
+            let total = factor * relation.amount;
+            lines.push(format!(
+                "{}- {} {} (total: {})",
+                "  ".repeat(depth),
+                relation.amount,
+                relation.color,
+                total
+            ));
+            self.push_bill_lines(&relation.color, depth + 1, total, lines);
+        }
+    }
+
+    fn sorted_colors(&self) -> Vec<&BagColor> {
+        let mut colors: Vec<&BagColor> = self.0.keys().collect();
+        colors.sort();
+        colors
     }
 }
 
@@ -296,6 +444,12 @@ mod tests {
     dark blue bags contain 2 dark violet bags.
     dark violet bags contain no other bags."#;
 
+    const EXAMPLE_FIXTURE_CYCLE: &str = r#"shiny gold bags contain 2 dark red bags.
+    dark red bags contain 2 dark orange bags.
+    dark orange bags contain 2 dark yellow bags.
+    dark yellow bags contain 1 dark red bag, 2 dark green bags.
+    dark green bags contain no other bags."#;
+
     #[test]
     fn test_parse_rules() {
         BagSystem::new_from_rules(EXAMPLE_FIXTURE_EX1);
@@ -320,7 +474,87 @@ mod tests {
     fn test_count_needed_bags_for_color() {
         let system = BagSystem::new_from_rules(EXAMPLE_FIXTURE_EX2);
         let color: BagColor = "shiny gold".into();
-        assert_eq!(system.count_needed_bags_for_color(&color), 126);
+        assert_eq!(system.count_needed_bags_for_color(&color).unwrap(), 126);
+
+        let system = BagSystem::new_from_rules(EXAMPLE_FIXTURE_EX1);
+        assert_eq!(system.count_needed_bags_for_color(&color).unwrap(), 32);
+    }
+
+    #[test]
+    fn test_cycle_detection() {
+        let system = BagSystem::new_from_rules(EXAMPLE_FIXTURE_CYCLE);
+        let color: BagColor = "shiny gold".into();
+        let cycle: Vec<BagColor> = vec!["dark red", "dark orange", "dark yellow", "dark red"]
+            .into_iter()
+            .map(Into::into)
+            .collect();
+
+        let mut rotated = cycle[1..].to_vec();
+        rotated.push(cycle[1].clone());
+
+        assert_eq!(system.find_cycle(), Some(rotated));
+        assert_eq!(
+            system.count_needed_bags_for_color(&color),
+            Err(DayError::CycleDetected(cycle))
+        );
+        assert_eq!(
+            DayError::CycleDetected(vec!["a b".into(), "c d".into(), "a b".into()]).to_string(),
+            "Cycle detected: a b -> c d -> a b"
+        );
+        assert_eq!(
+            BagSystem::new_from_rules(EXAMPLE_FIXTURE_EX1).find_cycle(),
+            None
+        );
+    }
+
+    #[test]
+    fn test_unknown_color() {
+        let system = BagSystem::new_from_rules("shiny gold bags contain 2 dark red bags.");
+        assert_eq!(
+            system.count_needed_bags_for_color(&"shiny gold".into()),
+            Err(DayError::UnknownColor("dark red".into()))
+        );
+    }
+
+    #[test]
+    fn test_to_dot() {
+        let system = BagSystem::new_from_rules(
+            r#"shiny gold bags contain 2 dark red bags, 1 dark blue bag.
+            dark red bags contain no other bags.
+            dark blue bags contain no other bags."#,
+        );
+
+        assert_eq!(
+            system.to_dot(),
+            indoc::indoc!(
+                r#"
+                digraph bags {
+                    "dark blue";
+                    "dark red";
+                    "shiny gold";
+                    "shiny gold" -> "dark red" [label="2"];
+                    "shiny gold" -> "dark blue" [label="1"];
+                }"#
+            )
+        );
+    }
+
+    #[test]
+    fn test_bill_of_materials() {
+        let system = BagSystem::new_from_rules(EXAMPLE_FIXTURE_EX1);
+        assert_eq!(
+            system.bill_of_materials(&"shiny gold".into()).unwrap(),
+            indoc::indoc!(
+                "
+                shiny gold (32 bags inside)
+                  - 1 dark olive (total: 1)
+                    - 3 faded blue (total: 3)
+                    - 4 dotted black (total: 4)
+                  - 2 vibrant plum (total: 2)
+                    - 5 faded blue (total: 10)
+                    - 6 dotted black (total: 12)"
+            )
+        );
     }
 
     #[test]
@@ -0,0 +1,616 @@
+//! Static control-flow analysis
+
+use std::{collections::VecDeque, convert::TryFrom};
+
+use super::vm::{Instruction, OpCode, Operand};
+
+/// Successors of an instruction.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Successors {
+    /// Statically known successors.
+    /// The exit node is the instruction count.
+    Static(Vec<usize>),
+    /// Jump target depends on runtime values
+    Dynamic,
+}
+
+/// Program repair.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Repair {
+    /// Repaired instruction index
+    pub index: usize,
+    /// Replacement instruction
+    pub instruction: Instruction,
+    /// Accumulator value at the end of the repaired program
+    pub accumulator: isize,
+}
+
+/// Static analysis diagnostic.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Diagnostic {
+    /// Jump to an instruction outside of the program
+    JumpOutOfRange {
+        /// Jump instruction index
+        from: usize,
+        /// Target index
+        target: isize,
+    },
+    /// Instructions which can never be executed (inclusive range)
+    UnreachableCode {
+        /// First instruction index
+        start: usize,
+        /// Last instruction index
+        end: usize,
+    },
+    /// Instructions looping forever once entered
+    InfiniteLoop(Vec<usize>),
+}
+
+impl std::fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::JumpOutOfRange { from, target } => {
+                write!(f, "Jump out of range from {} to {}", from, target)
+            }
+            Self::UnreachableCode { start, end } if start == end => {
+                write!(f, "Unreachable instruction {}", start)
+            }
+            Self::UnreachableCode { start, end } => {
+                write!(f, "Unreachable instructions {}-{}", start, end)
+            }
+            Self::InfiniteLoop(nodes) => write!(
+                f,
+                "Infinite loop on instructions {}",
+                nodes
+                    .iter()
+                    .map(ToString::to_string)
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            ),
+        }
+    }
+}
+
+/// Control-flow graph.
+#[derive(Debug)]
+pub struct ControlFlowGraph {
+    successors: Vec<Successors>,
+}
+
+impl ControlFlowGraph {
+    /// Build graph from instructions.
+    ///
+    /// # Arguments
+    ///
+    /// * `instructions` - Instructions
+    pub fn new(instructions: &[Instruction]) -> Self {
+        let len = instructions.len();
+        let successors = instructions
+            .iter()
+            .enumerate()
+            .map(|(idx, instr)| Self::instruction_successors(idx, instr, len))
+            .collect();
+
+        Self { successors }
+    }
+
+    /// Get the exit node, reached when the program terminates.
+    pub fn exit(&self) -> usize {
+        self.successors.len()
+    }
+
+    /// Get successors of an instruction.
+    ///
+    /// # Arguments
+    ///
+    /// * `idx` - Instruction index
+    pub fn successors(&self, idx: usize) -> &Successors {
+        &self.successors[idx]
+    }
+
+    /// Compute, for each node, whether the exit node can be reached from it.
+    /// The returned vector includes the exit node.
+    ///
+    /// Dynamic jumps have no known successor and never reach termination.
+    pub fn terminating_nodes(&self) -> Vec<bool> {
+        self.reaching(&[self.exit()])
+    }
+
+    /// Compute, for each node, whether it can be reached from the first instruction.
+    /// The returned vector includes the exit node.
+    ///
+    /// Dynamic jumps have no known successor, so nodes only reachable through them
+    /// are not reported.
+    pub fn reachable_nodes(&self) -> Vec<bool> {
+        let mut reachable = vec![false; self.exit() + 1];
+        let mut queue = VecDeque::new();
+        reachable[0] = true;
+        queue.push_back(0);
+
+        while let Some(node) = queue.pop_front() {
+            if let Some(Successors::Static(targets)) = self.successors.get(node) {
+                for &target in targets {
+                    if !reachable[target] {
+                        reachable[target] = true;
+                        queue.push_back(target);
+                    }
+                }
+            }
+        }
+
+        reachable
+    }
+
+    /// Compute, for each node, whether one of the `targets` can be reached from it.
+    fn reaching(&self, targets: &[usize]) -> Vec<bool> {
+        let exit = self.exit();
+        let mut predecessors = vec![vec![]; exit + 1];
+        for (idx, succ) in self.successors.iter().enumerate() {
+            if let Successors::Static(nodes) = succ {
+                for &node in nodes {
+                    predecessors[node].push(idx);
+                }
+            }
+        }
+
+        let mut reaching = vec![false; exit + 1];
+        let mut queue: VecDeque<usize> = targets.iter().copied().collect();
+        for &target in targets {
+            reaching[target] = true;
+        }
+
+        while let Some(node) = queue.pop_front() {
+            for &pred in &predecessors[node] {
+                if !reaching[pred] {
+                    reaching[pred] = true;
+                    queue.push_back(pred);
+                }
+            }
+        }
+
+        reaching
+    }
+
+    /// Get cycles (strongly connected components) of the subgraph induced by `nodes`.
+    /// Each cycle is sorted, and cycles are sorted by first instruction.
+    fn cycles(&self, nodes: &[bool]) -> Vec<Vec<usize>> {
+        let static_successors = |node: usize| match self.successors.get(node) {
+            Some(Successors::Static(targets)) => targets.as_slice(),
+            _ => &[],
+        };
+
+        // Kosaraju, first pass: nodes in post-order
+        let mut visited = vec![false; nodes.len()];
+        let mut order = vec![];
+        for root in (0..nodes.len()).filter(|&n| nodes[n]) {
+            if visited[root] {
+                continue;
+            }
+
+            visited[root] = true;
+            let mut stack = vec![(root, 0)];
+            while let Some((node, child)) = stack.pop() {
+                match static_successors(node).get(child) {
+                    Some(&next) => {
+                        stack.push((node, child + 1));
+                        if nodes[next] && !visited[next] {
+                            visited[next] = true;
+                            stack.push((next, 0));
+                        }
+                    }
+                    None => order.push(node),
+                }
+            }
+        }
+
+        // Second pass, on the transposed graph
+        let mut predecessors = vec![vec![]; nodes.len()];
+        for node in (0..nodes.len()).filter(|&n| nodes[n]) {
+            for &next in static_successors(node) {
+                if nodes[next] {
+                    predecessors[next].push(node);
+                }
+            }
+        }
+
+        let mut assigned = vec![false; nodes.len()];
+        let mut cycles = vec![];
+        for &root in order.iter().rev() {
+            if assigned[root] {
+                continue;
+            }
+
+            assigned[root] = true;
+            let mut component = vec![];
+            let mut stack = vec![root];
+            while let Some(node) = stack.pop() {
+                component.push(node);
+                for &pred in &predecessors[node] {
+                    if !assigned[pred] {
+                        assigned[pred] = true;
+                        stack.push(pred);
+                    }
+                }
+            }
+
+            if component.len() > 1 || static_successors(root).contains(&root) {
+                component.sort_unstable();
+                cycles.push(component);
+            }
+        }
+
+        cycles.sort();
+        cycles
+    }
+
+    /// Get the target of a `Nop` / `Jmp` instruction once flipped, if it is
+    /// statically known and in range.
+    ///
+    /// # Arguments
+    ///
+    /// * `idx` - Instruction index
+    /// * `instr` - Instruction
+    pub fn flipped_target(&self, idx: usize, instr: &Instruction) -> Option<usize> {
+        match instr.opcode() {
+            OpCode::Nop => match instr.operands() {
+                [Operand::Immediate(offset)] => relative_target(idx, *offset, self.exit()),
+                _ => None,
+            },
+            OpCode::Jmp => Some(idx + 1),
+            _ => None,
+        }
+    }
+
+    fn instruction_successors(idx: usize, instr: &Instruction, len: usize) -> Successors {
+        let jump = |offset: &Operand| match offset {
+            Operand::Immediate(offset) => Some(relative_target(idx, *offset, len)),
+            Operand::Register(_) => None,
+        };
+
+        match (instr.opcode(), instr.operands()) {
+            (OpCode::Hlt, _) => Successors::Static(vec![len]),
+            (OpCode::Jmp, [offset]) => match jump(offset) {
+                Some(target) => Successors::Static(target.into_iter().collect()),
+                None => Successors::Dynamic,
+            },
+            (OpCode::Jz, [_, offset]) | (OpCode::Jnz, [_, offset]) => match jump(offset) {
+                Some(target) => Successors::Static(
+                    std::iter::once(idx + 1)
+                        .chain(target.filter(|&t| t != idx + 1))
+                        .collect(),
+                ),
+                None => Successors::Dynamic,
+            },
+            (OpCode::Custom(_), _) => Successors::Dynamic,
+            _ => Successors::Static(vec![idx + 1]),
+        }
+    }
+}
+
+/// Accumulator changes along the execution chain from an instruction to the
+/// end of the program.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ExitChain {
+    /// Executed instruction count
+    pub steps: usize,
+    /// Accumulator change at the end of the chain
+    pub delta: i128,
+    /// Lowest accumulator change along the chain
+    pub min: i128,
+    /// Highest accumulator change along the chain
+    pub max: i128,
+}
+
+impl ExitChain {
+    const EXIT: Self = Self {
+        steps: 0,
+        delta: 0,
+        min: 0,
+        max: 0,
+    };
+
+    fn prepend(self, value: isize) -> Self {
+        let value = value as i128;
+        Self {
+            steps: self.steps + 1,
+            delta: value + self.delta,
+            min: (value + self.min).min(0),
+            max: (value + self.max).max(0),
+        }
+    }
+
+    /// Get the accumulator at the end of the chain, or `None` if it overflows
+    /// along the way.
+    ///
+    /// # Arguments
+    ///
+    /// * `accumulator` - Accumulator when entering the chain
+    pub fn final_accumulator(&self, accumulator: isize) -> Option<isize> {
+        let accumulator = accumulator as i128;
+        if accumulator + self.min < isize::MIN as i128
+            || accumulator + self.max > isize::MAX as i128
+        {
+            None
+        } else {
+            isize::try_from(accumulator + self.delta).ok()
+        }
+    }
+}
+
+/// Compute, for each node, the execution chain from this node to the exit node,
+/// in a single pass. The returned vector includes the exit node.
+///
+/// Only `nop`, `acc` with an immediate value and `jmp` with an immediate offset
+/// have a statically known effect: chains going through other instructions,
+/// excluded instructions, cycles or out of range jumps have no summary.
+///
+/// # Arguments
+///
+/// * `instructions` - Instructions
+/// * `excluded` - Instructions to exclude from chains
+pub fn exit_chains(instructions: &[Instruction], excluded: &[bool]) -> Vec<Option<ExitChain>> {
+    let len = instructions.len();
+    let static_step = |idx: usize| {
+        if excluded.get(idx) == Some(&true) {
+            return None;
+        }
+
+        match (instructions[idx].opcode(), instructions[idx].operands()) {
+            (OpCode::Nop, _) => Some((0, idx + 1)),
+            (OpCode::Acc, [Operand::Immediate(value)]) => Some((*value, idx + 1)),
+            (OpCode::Jmp, [Operand::Immediate(offset)]) => {
+                relative_target(idx, *offset, len).map(|target| (0, target))
+            }
+            _ => None,
+        }
+    };
+
+    let mut chains: Vec<Option<Option<ExitChain>>> = vec![None; len + 1];
+    chains[len] = Some(Some(ExitChain::EXIT));
+    let mut on_stack = vec![false; len];
+
+    for start in 0..len {
+        // Walk forward until a known node, then resolve the walked nodes backwards
+        let mut stack = vec![];
+        let mut node = start;
+        let mut chain = loop {
+            if let Some(chain) = chains[node] {
+                break chain;
+            }
+            if on_stack[node] {
+                break None;
+            }
+
+            match static_step(node) {
+                Some((value, next)) => {
+                    on_stack[node] = true;
+                    stack.push((node, value));
+                    node = next;
+                }
+                None => {
+                    chains[node] = Some(None);
+                    break None;
+                }
+            }
+        };
+
+        while let Some((node, value)) = stack.pop() {
+            on_stack[node] = false;
+            chain = chain.map(|c| c.prepend(value));
+            chains[node] = Some(chain);
+        }
+    }
+
+    chains.into_iter().map(Option::flatten).collect()
+}
+
+/// Run static analysis on instructions.
+///
+/// Reported infinite loops are guaranteed: once entered, execution can neither
+/// reach the end of the program, an invalid jump nor a dynamic jump.
+/// Unreachable code is only reported when no dynamic jump can be executed.
+///
+/// # Arguments
+///
+/// * `instructions` - Instructions
+pub fn analyze(instructions: &[Instruction]) -> Vec<Diagnostic> {
+    let graph = ControlFlowGraph::new(instructions);
+    let exit = graph.exit();
+    let mut diagnostics = vec![];
+
+    // Out of range jumps
+    for (from, instr) in instructions.iter().enumerate() {
+        let offset = match (instr.opcode(), instr.operands()) {
+            (OpCode::Jmp, [Operand::Immediate(offset)]) => *offset,
+            (OpCode::Jz, [_, Operand::Immediate(offset)])
+            | (OpCode::Jnz, [_, Operand::Immediate(offset)]) => *offset,
+            _ => continue,
+        };
+
+        if relative_target(from, offset, exit).is_none() {
+            diagnostics.push(Diagnostic::JumpOutOfRange {
+                from,
+                target: from as isize + offset,
+            });
+        }
+    }
+
+    if instructions.is_empty() {
+        return diagnostics;
+    }
+
+    // Unreachable code
+    let reachable = graph.reachable_nodes();
+    let has_dynamic_jump =
+        (0..exit).any(|idx| reachable[idx] && *graph.successors(idx) == Successors::Dynamic);
+    if !has_dynamic_jump {
+        let mut idx = 0;
+        while idx < exit {
+            if reachable[idx] {
+                idx += 1;
+                continue;
+            }
+
+            let start = idx;
+            while idx < exit && !reachable[idx] {
+                idx += 1;
+            }
+            diagnostics.push(Diagnostic::UnreachableCode {
+                start,
+                end: idx - 1,
+            });
+        }
+    }
+
+    // Guaranteed infinite loops: cycles which cannot escape
+    let escapes: Vec<usize> = std::iter::once(exit)
+        .chain((0..exit).filter(|&idx| match graph.successors(idx) {
+            Successors::Static(targets) => targets.len() < expected_successors(&instructions[idx]),
+            Successors::Dynamic => true,
+        }))
+        .collect();
+    let escaping = graph.reaching(&escapes);
+    let trapped: Vec<bool> = (0..=exit).map(|idx| !escaping[idx]).collect();
+    diagnostics.extend(
+        graph
+            .cycles(&trapped)
+            .into_iter()
+            .map(Diagnostic::InfiniteLoop),
+    );
+
+    diagnostics
+}
+
+/// Number of successors of an instruction whose jumps are all in range.
+fn expected_successors(instr: &Instruction) -> usize {
+    match instr.opcode() {
+        OpCode::Jz | OpCode::Jnz => 2,
+        _ => 1,
+    }
+}
+
+/// Compute a relative jump target, or `None` if it is out of range.
+fn relative_target(idx: usize, offset: isize, len: usize) -> Option<usize> {
+    let target = idx as isize + offset;
+    if target < 0 || target as usize > len {
+        None
+    } else {
+        Some(target as usize)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::days::day08::Parser;
+
+    const CODE_SAMPLE: &str =
+        "nop +0\nacc +1\njmp +4\nacc +3\njmp -3\nacc -99\nacc +1\njmp -4\nacc +6";
+
+    #[test]
+    fn test_successors() {
+        let code = "jmp +2\njz a +2\njmp a\nhlt\njmp -9\nnop +0";
+        let graph = ControlFlowGraph::new(&Parser::parse_code(code).unwrap());
+
+        assert_eq!(graph.exit(), 6);
+        assert_eq!(graph.successors(0), &Successors::Static(vec![2]));
+        assert_eq!(graph.successors(1), &Successors::Static(vec![2, 3]));
+        assert_eq!(graph.successors(2), &Successors::Dynamic);
+        assert_eq!(graph.successors(3), &Successors::Static(vec![6]));
+        assert_eq!(graph.successors(4), &Successors::Static(vec![]));
+        assert_eq!(graph.successors(5), &Successors::Static(vec![6]));
+    }
+
+    #[test]
+    fn test_terminating_nodes() {
+        let instructions = Parser::parse_code(CODE_SAMPLE).unwrap();
+        let graph = ControlFlowGraph::new(&instructions);
+        let terminating: Vec<usize> = graph
+            .terminating_nodes()
+            .iter()
+            .enumerate()
+            .filter_map(|(idx, &t)| if t { Some(idx) } else { None })
+            .collect();
+
+        assert_eq!(terminating, vec![8, 9]);
+        assert_eq!(graph.flipped_target(0, &instructions[0]), Some(0));
+        assert_eq!(graph.flipped_target(7, &instructions[7]), Some(8));
+        assert_eq!(graph.flipped_target(1, &instructions[1]), None);
+    }
+
+    #[test]
+    fn test_exit_chains() {
+        let instructions =
+            Parser::parse_code("acc +2\njmp +2\nacc -9\nacc -5\nout a\njmp -1").unwrap();
+        let chains = exit_chains(&instructions, &[false; 6]);
+
+        assert_eq!(chains[6], Some(ExitChain::EXIT));
+        assert_eq!(chains[4], None);
+        assert_eq!(chains[5], None);
+        assert_eq!(chains[0], None);
+
+        let chains = exit_chains(&instructions[..4], &[false; 4]);
+        let chain = chains[0].unwrap();
+        assert_eq!(
+            chain,
+            ExitChain {
+                steps: 3,
+                delta: -3,
+                min: -3,
+                max: 2
+            }
+        );
+        assert_eq!(chain.final_accumulator(10), Some(7));
+        assert_eq!(chain.final_accumulator(isize::MAX), None);
+        assert_eq!(chains[2].unwrap().delta, -14);
+
+        let chains = exit_chains(&instructions[..4], &[false, false, false, true]);
+        assert_eq!(chains[0], None);
+        assert_eq!(chains[2], None);
+    }
+
+    #[test]
+    fn test_analyze() {
+        let instructions = Parser::parse_code(CODE_SAMPLE).unwrap();
+        assert_eq!(
+            analyze(&instructions),
+            vec![
+                Diagnostic::UnreachableCode { start: 5, end: 5 },
+                Diagnostic::UnreachableCode { start: 8, end: 8 },
+                Diagnostic::InfiniteLoop(vec![1, 2, 3, 4, 6, 7]),
+            ]
+        );
+
+        let code = "jz a +3\njmp -2\nacc +1\njmp +0\njmp +10\njnz b -1";
+        let diagnostics = analyze(&Parser::parse_code(code).unwrap());
+        assert_eq!(
+            diagnostics,
+            vec![
+                Diagnostic::JumpOutOfRange {
+                    from: 1,
+                    target: -1
+                },
+                Diagnostic::JumpOutOfRange {
+                    from: 4,
+                    target: 14
+                },
+                Diagnostic::UnreachableCode { start: 2, end: 2 },
+                Diagnostic::UnreachableCode { start: 4, end: 5 },
+                Diagnostic::InfiniteLoop(vec![3]),
+            ]
+        );
+        assert_eq!(diagnostics[0].to_string(), "Jump out of range from 1 to -1");
+        assert_eq!(diagnostics[2].to_string(), "Unreachable instruction 2");
+        assert_eq!(diagnostics[3].to_string(), "Unreachable instructions 4-5");
+        assert_eq!(
+            diagnostics[4].to_string(),
+            "Infinite loop on instructions 3"
+        );
+
+        // A conditional exit is not a guaranteed loop
+        let code = "acc +1\njnz a +2\njmp -2";
+        assert_eq!(analyze(&Parser::parse_code(code).unwrap()), vec![]);
+        // Dynamic jumps disable unreachable code detection
+        assert_eq!(analyze(&Parser::parse_code("jmp a\nhlt").unwrap()), vec![]);
+    }
+}
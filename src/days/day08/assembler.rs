@@ -0,0 +1,290 @@
+//! Assembler and disassembler, with label support
+
+use std::collections::{BTreeSet, HashMap};
+
+use super::vm::{Instruction, OpCode, Operand, ParseError};
+
+/// Program assembler.
+///
+/// On top of the instruction syntax, source code may contain:
+/// - comments, starting with `#`,
+/// - label definitions (`loop_start:`), alone or before an instruction,
+/// - label references as operands (`jmp loop_start`), resolved to offsets
+///   relative to the instruction.
+///
+/// Single lowercase letters are registers, so labels need at least two characters.
+pub struct Assembler;
+
+impl Assembler {
+    /// Assemble source code.
+    ///
+    /// # Arguments
+    ///
+    /// * `source` - Source code
+    pub fn assemble(source: &str) -> Result<Vec<Instruction>, ParseError> {
+        let mut labels = HashMap::new();
+        let mut lines = vec![];
+
+        for (idx, line) in source.lines().enumerate() {
+            let line_number = idx + 1;
+            let mut code = line.split('#').next().unwrap_or_default().trim();
+
+            while let Some((label, rest)) = Self::split_label(code) {
+                if labels.insert(label, lines.len()).is_some() {
+                    return Err(ParseError::DuplicateLabel {
+                        line: line_number,
+                        label: label.to_string(),
+                    });
+                }
+                code = rest;
+            }
+
+            if !code.is_empty() {
+                lines.push((line_number, code));
+            }
+        }
+
+        lines
+            .iter()
+            .enumerate()
+            .map(|(idx, &(line, code))| {
+                let mut tokens = code.split_whitespace();
+                let opcode = tokens
+                    .next()
+                    .map(OpCode::from_mnemonic)
+                    .ok_or(ParseError::MissingOpCode(line))?;
+                let operands = tokens
+                    .map(|t| Self::parse_operand(t, idx, line, &labels))
+                    .collect::<Result<_, _>>()?;
+
+                Ok(Instruction::with_operands(opcode, operands))
+            })
+            .collect()
+    }
+
+    /// Disassemble instructions, one per line.
+    ///
+    /// # Arguments
+    ///
+    /// * `instructions` - Instructions
+    pub fn disassemble(instructions: &[Instruction]) -> String {
+        instructions.iter().map(|i| format!("{}\n", i)).collect()
+    }
+
+    /// Disassemble instructions, replacing in-range jump offsets with labels.
+    ///
+    /// # Arguments
+    ///
+    /// * `instructions` - Instructions
+    pub fn disassemble_with_labels(instructions: &[Instruction]) -> String {
+        let targets: BTreeSet<usize> = instructions
+            .iter()
+            .enumerate()
+            .filter_map(|(idx, i)| Self::jump_target(idx, i, instructions.len()))
+            .map(|(_, target)| target)
+            .collect();
+
+        let mut output = String::new();
+        for (idx, instr) in instructions.iter().enumerate() {
+            if targets.contains(&idx) {
+                output.push_str(&format!("{}:\n", Self::label_name(idx)));
+            }
+
+            let mut line = instr.to_string();
+            if let Some((position, target)) = Self::jump_target(idx, instr, instructions.len()) {
+                let operands: Vec<String> = instr
+                    .operands()
+                    .iter()
+                    .enumerate()
+                    .map(|(i, op)| {
+                        if i == position {
+                            Self::label_name(target)
+                        } else {
+                            op.to_string()
+                        }
+                    })
+                    .collect();
+                line = format!("{} {}", instr.opcode(), operands.join(" "));
+            }
+            output.push_str(&format!("    {}\n", line));
+        }
+
+        if targets.contains(&instructions.len()) {
+            output.push_str(&format!("{}:\n", Self::label_name(instructions.len())));
+        }
+
+        output
+    }
+
+    fn label_name(idx: usize) -> String {
+        format!("addr_{}", idx)
+    }
+
+    /// Get the jump offset operand position and the jump target, if it is in range.
+    fn jump_target(idx: usize, instr: &Instruction, len: usize) -> Option<(usize, usize)> {
+        let position = match instr.opcode() {
+            OpCode::Jmp => 0,
+            OpCode::Jz | OpCode::Jnz => 1,
+            _ => return None,
+        };
+
+        match instr.operands().get(position) {
+            Some(Operand::Immediate(offset)) => {
+                let target = idx as isize + offset;
+                if target >= 0 && target as usize <= len {
+                    Some((position, target as usize))
+                } else {
+                    None
+                }
+            }
+            _ => None,
+        }
+    }
+
+    fn split_label(code: &str) -> Option<(&str, &str)> {
+        let (label, rest) = code.split_once(':')?;
+        if Self::is_label(label) {
+            Some((label, rest.trim_start()))
+        } else {
+            None
+        }
+    }
+
+    fn is_label(token: &str) -> bool {
+        let mut chars = token.chars();
+        chars
+            .next()
+            .is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+            && chars.all(|c| c.is_ascii_alphanumeric() || c == '_')
+    }
+
+    fn parse_operand(
+        token: &str,
+        idx: usize,
+        line: usize,
+        labels: &HashMap<&str, usize>,
+    ) -> Result<Operand, ParseError> {
+        if let Some(operand) = Operand::parse(token) {
+            return Ok(operand);
+        }
+
+        if !Self::is_label(token) {
+            return Err(ParseError::InvalidOperand {
+                line,
+                operand: token.to_string(),
+            });
+        }
+
+        labels
+            .get(token)
+            .map(|&target| Operand::Immediate(target as isize - idx as isize))
+            .ok_or_else(|| ParseError::UnknownLabel {
+                line,
+                label: token.to_string(),
+            })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::days::day08::{Interpreter, Parser, StepOutput, VmConfig};
+
+    const CODE_SAMPLE: &str =
+        "nop +0\nacc +1\njmp +4\nacc +3\njmp -3\nacc -99\nacc +1\njmp -4\nacc +6\n";
+
+    #[test]
+    fn test_display() {
+        let instructions = Parser::parse_code("jmp +4\nacc -3\njz a -2\nhlt\nfoo b +0").unwrap();
+        let lines: Vec<String> = instructions.iter().map(|i| i.to_string()).collect();
+
+        assert_eq!(
+            lines,
+            vec!["jmp +4", "acc -3", "jz a -2", "hlt", "foo b +0"]
+        );
+    }
+
+    #[test]
+    fn test_assemble() {
+        let source = "
+            set b 5       # counter
+        loop_start:
+            acc +2
+            sub b 1
+            jnz b loop_start
+            jmp end
+            acc -100
+        end:";
+
+        let instructions = Assembler::assemble(source).unwrap();
+        assert_eq!(
+            Assembler::disassemble(&instructions),
+            "set b +5\nacc +2\nsub b +1\njnz b -2\njmp +2\nacc -100\n"
+        );
+
+        let config = VmConfig {
+            detect_loops: false,
+            ..VmConfig::default()
+        };
+        assert_eq!(
+            Interpreter::with_config(instructions, config).run(),
+            StepOutput::Finished(10)
+        );
+    }
+
+    #[test]
+    fn test_assemble_errors() {
+        assert_eq!(
+            Assembler::assemble("jmp nowhere"),
+            Err(ParseError::UnknownLabel {
+                line: 1,
+                label: "nowhere".into()
+            })
+        );
+        assert_eq!(
+            Assembler::assemble("start: nop +0\nstart: jmp start"),
+            Err(ParseError::DuplicateLabel {
+                line: 2,
+                label: "start".into()
+            })
+        );
+        assert_eq!(
+            Assembler::assemble("acc 1.5"),
+            Err(ParseError::InvalidOperand {
+                line: 1,
+                operand: "1.5".into()
+            })
+        );
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let instructions = Parser::parse_code(CODE_SAMPLE).unwrap();
+
+        assert_eq!(Assembler::disassemble(&instructions), CODE_SAMPLE);
+        assert_eq!(
+            Assembler::assemble(&Assembler::disassemble(&instructions)).unwrap(),
+            instructions
+        );
+
+        let labelled = Assembler::disassemble_with_labels(&instructions);
+        assert_eq!(
+            labelled,
+            indoc::indoc! {"
+                    nop +0
+                addr_1:
+                    acc +1
+                    jmp addr_6
+                addr_3:
+                    acc +3
+                    jmp addr_1
+                    acc -99
+                addr_6:
+                    acc +1
+                    jmp addr_3
+                    acc +6
+            "}
+        );
+        assert_eq!(Assembler::assemble(&labelled).unwrap(), instructions);
+    }
+}
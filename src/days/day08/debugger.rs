@@ -0,0 +1,462 @@
+//! Interpreter debugger and execution tracer
+
+use std::io::{self, BufRead, Write};
+
+use super::vm::{Instruction, Interpreter, StepOutput, StepUndo};
+
+/// Executed instruction record.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TraceEntry {
+    /// Step number (starting at 1)
+    pub step: usize,
+    /// Instruction index
+    pub cursor: usize,
+    /// Executed instruction
+    pub instruction: Instruction,
+    /// Accumulator before execution
+    pub accumulator_before: isize,
+    /// Accumulator after execution
+    pub accumulator_after: isize,
+    /// Times this instruction was executed, including this one
+    pub visit_count: usize,
+}
+
+impl std::fmt::Display for TraceEntry {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "#{:<5} [{:>4}] {:<16} acc {} -> {} (visit {})",
+            self.step,
+            self.cursor,
+            self.instruction.to_string(),
+            self.accumulator_before,
+            self.accumulator_after,
+            self.visit_count
+        )
+    }
+}
+
+/// Breakpoint.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Breakpoint {
+    /// Break before executing instruction index
+    Instruction(usize),
+    /// Break when accumulator equals value
+    AccumulatorEquals(isize),
+    /// Break when accumulator is greater than value
+    AccumulatorAbove(isize),
+    /// Break when accumulator is lower than value
+    AccumulatorBelow(isize),
+}
+
+impl Breakpoint {
+    /// Check if breakpoint matches the interpreter state.
+    ///
+    /// # Arguments
+    ///
+    /// * `interpreter` - Interpreter
+    pub fn matches(&self, interpreter: &Interpreter) -> bool {
+        let acc = interpreter.accumulator();
+        match *self {
+            Self::Instruction(idx) => interpreter.cursor() == idx,
+            Self::AccumulatorEquals(v) => acc == v,
+            Self::AccumulatorAbove(v) => acc > v,
+            Self::AccumulatorBelow(v) => acc < v,
+        }
+    }
+}
+
+impl std::fmt::Display for Breakpoint {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Instruction(idx) => write!(f, "instruction {}", idx),
+            Self::AccumulatorEquals(v) => write!(f, "acc == {}", v),
+            Self::AccumulatorAbove(v) => write!(f, "acc > {}", v),
+            Self::AccumulatorBelow(v) => write!(f, "acc < {}", v),
+        }
+    }
+}
+
+/// Reason why execution stopped.
+#[derive(Debug, PartialEq, Eq)]
+pub enum DebugEvent {
+    /// Breakpoint hit, with its index
+    Breakpoint(usize),
+    /// Interpreter stopped by itself (loop, end of program or error)
+    Stopped(StepOutput),
+}
+
+/// Interpreter debugger, with breakpoints and reverse stepping.
+pub struct Debugger {
+    interpreter: Interpreter,
+    breakpoints: Vec<Breakpoint>,
+    history: Vec<StepUndo>,
+    trace: Vec<TraceEntry>,
+    visits: Vec<usize>,
+}
+
+impl Debugger {
+    /// Create debugger from interpreter.
+    ///
+    /// # Arguments
+    ///
+    /// * `interpreter` - Interpreter
+    pub fn new(interpreter: Interpreter) -> Self {
+        let visits = vec![0; interpreter.instructions().len()];
+
+        Self {
+            interpreter,
+            breakpoints: vec![],
+            history: vec![],
+            trace: vec![],
+            visits,
+        }
+    }
+
+    /// Get interpreter.
+    pub fn interpreter(&self) -> &Interpreter {
+        &self.interpreter
+    }
+
+    /// Get executed instructions, in order.
+    pub fn trace(&self) -> &[TraceEntry] {
+        &self.trace
+    }
+
+    /// Get breakpoints.
+    pub fn breakpoints(&self) -> &[Breakpoint] {
+        &self.breakpoints
+    }
+
+    /// Add breakpoint, returning its index.
+    ///
+    /// # Arguments
+    ///
+    /// * `breakpoint` - Breakpoint
+    pub fn add_breakpoint(&mut self, breakpoint: Breakpoint) -> usize {
+        self.breakpoints.push(breakpoint);
+        self.breakpoints.len() - 1
+    }
+
+    /// Remove breakpoint from index.
+    ///
+    /// # Arguments
+    ///
+    /// * `index` - Breakpoint index
+    pub fn remove_breakpoint(&mut self, index: usize) -> Option<Breakpoint> {
+        if index < self.breakpoints.len() {
+            Some(self.breakpoints.remove(index))
+        } else {
+            None
+        }
+    }
+
+    /// Execute one instruction and record it.
+    pub fn step(&mut self) -> StepOutput {
+        let cursor = self.interpreter.cursor();
+        let instruction = match self.interpreter.instructions().get(cursor) {
+            Some(i) => i.clone(),
+            None => return self.interpreter.step(),
+        };
+
+        let accumulator_before = self.interpreter.accumulator();
+        let (output, undo) = self.interpreter.step_with_undo();
+
+        if let StepOutput::Error(_) = output {
+            // Nothing was executed
+            self.interpreter.undo(undo);
+            return output;
+        }
+
+        self.history.push(undo);
+        self.visits[cursor] += 1;
+        self.trace.push(TraceEntry {
+            step: self.trace.len() + 1,
+            cursor,
+            instruction,
+            accumulator_before,
+            accumulator_after: self.interpreter.accumulator(),
+            visit_count: self.visits[cursor],
+        });
+
+        output
+    }
+
+    /// Undo last executed instruction.
+    /// Returns the undone trace entry, if any.
+    pub fn step_back(&mut self) -> Option<TraceEntry> {
+        let undo = self.history.pop()?;
+        let entry = self.trace.pop()?;
+
+        self.interpreter.undo(undo);
+        self.visits[entry.cursor] -= 1;
+        Some(entry)
+    }
+
+    /// Run until a breakpoint is hit or the interpreter stops.
+    /// Breakpoints are checked after each executed instruction.
+    pub fn continue_run(&mut self) -> DebugEvent {
+        loop {
+            match self.step() {
+                StepOutput::Normal => (),
+                output => return DebugEvent::Stopped(output),
+            }
+
+            if let Some(idx) = self.hit_breakpoint() {
+                return DebugEvent::Breakpoint(idx);
+            }
+        }
+    }
+
+    /// Get the first matching breakpoint index.
+    pub fn hit_breakpoint(&self) -> Option<usize> {
+        self.breakpoints
+            .iter()
+            .position(|b| b.matches(&self.interpreter))
+    }
+
+    /// Run an interactive session, reading commands from `input`.
+    ///
+    /// # Arguments
+    ///
+    /// * `input` - Command input
+    /// * `output` - Output
+    pub fn run_session<R: BufRead, W: Write>(&mut self, input: R, mut output: W) -> io::Result<()> {
+        writeln!(output, "Type 'help' for commands.")?;
+        self.write_location(&mut output)?;
+
+        for line in input.lines() {
+            let line = line?;
+            let tokens: Vec<&str> = line.split_whitespace().collect();
+            let count = || tokens.get(1).and_then(|t| t.parse().ok()).unwrap_or(1);
+
+            match tokens.as_slice() {
+                [] => continue,
+                ["q"] | ["quit"] => break,
+                ["h"] | ["help"] => writeln!(output, "{}", SESSION_HELP)?,
+                ["s"] | ["step"] | ["s", _] | ["step", _] => {
+                    for _ in 0..count() {
+                        let result = self.step();
+                        if let Some(entry) = self.trace.last() {
+                            writeln!(output, "{}", entry)?;
+                        }
+                        if result != StepOutput::Normal {
+                            writeln!(output, "{:?}", result)?;
+                            break;
+                        }
+                    }
+                }
+                ["b"] | ["back"] | ["b", _] | ["back", _] => {
+                    for _ in 0..count() {
+                        match self.step_back() {
+                            Some(entry) => writeln!(output, "undo {}", entry)?,
+                            None => {
+                                writeln!(output, "At start of history")?;
+                                break;
+                            }
+                        }
+                    }
+                }
+                ["c"] | ["continue"] => match self.continue_run() {
+                    DebugEvent::Breakpoint(idx) => {
+                        writeln!(output, "Breakpoint {} hit: {}", idx, self.breakpoints[idx])?
+                    }
+                    DebugEvent::Stopped(result) => writeln!(output, "{:?}", result)?,
+                },
+                ["break", args @ ..] => match parse_breakpoint(args) {
+                    Some(b) => {
+                        let idx = self.add_breakpoint(b);
+                        writeln!(output, "Breakpoint {}: {}", idx, b)?
+                    }
+                    None => writeln!(output, "Usage: break <idx> | break acc <==|>|<> <value>")?,
+                },
+                ["delete", idx] => match idx.parse().ok().and_then(|i| self.remove_breakpoint(i)) {
+                    Some(b) => writeln!(output, "Removed breakpoint: {}", b)?,
+                    None => writeln!(output, "Unknown breakpoint")?,
+                },
+                ["breakpoints"] => {
+                    for (idx, b) in self.breakpoints.iter().enumerate() {
+                        writeln!(output, "{}: {}", idx, b)?;
+                    }
+                }
+                ["trace"] | ["trace", _] => {
+                    let count = tokens.get(1).and_then(|t| t.parse().ok()).unwrap_or(10);
+                    for entry in &self.trace[self.trace.len().saturating_sub(count)..] {
+                        writeln!(output, "{}", entry)?;
+                    }
+                }
+                ["info"] => {
+                    writeln!(
+                        output,
+                        "steps {} | registers {:?} | output {:?}",
+                        self.interpreter.steps(),
+                        self.interpreter.machine().registers(),
+                        self.interpreter.machine().output()
+                    )?;
+                }
+                _ => writeln!(output, "Unknown command: {}", line.trim())?,
+            }
+
+            self.write_location(&mut output)?;
+        }
+
+        Ok(())
+    }
+
+    fn write_location<W: Write>(&self, output: &mut W) -> io::Result<()> {
+        let cursor = self.interpreter.cursor();
+        match self.interpreter.instructions().get(cursor) {
+            Some(instruction) => writeln!(
+                output,
+                "=> [{:>4}] {} (acc {})",
+                cursor,
+                instruction,
+                self.interpreter.accumulator()
+            ),
+            None => writeln!(
+                output,
+                "=> end of program (acc {})",
+                self.interpreter.accumulator()
+            ),
+        }
+    }
+}
+
+const SESSION_HELP: &str = "\
+step [n]      execute n instructions (s)
+back [n]      undo n instructions (b)
+continue      run until a breakpoint or the end (c)
+break <idx>   break before instruction <idx>
+break acc <op> <value>
+              break on accumulator condition (op: ==, >, <)
+delete <n>    remove breakpoint <n>
+breakpoints   list breakpoints
+trace [n]     show last n executed instructions
+info          show registers and output
+quit          exit (q)";
+
+fn parse_breakpoint(args: &[&str]) -> Option<Breakpoint> {
+    match *args {
+        [idx] => idx.parse().ok().map(Breakpoint::Instruction),
+        ["acc", op, value] => {
+            let value = value.parse().ok()?;
+            match op {
+                "==" => Some(Breakpoint::AccumulatorEquals(value)),
+                ">" => Some(Breakpoint::AccumulatorAbove(value)),
+                "<" => Some(Breakpoint::AccumulatorBelow(value)),
+                _ => None,
+            }
+        }
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CODE_SAMPLE: &str =
+        "nop +0\nacc +1\njmp +4\nacc +3\njmp -3\nacc -99\nacc +1\njmp -4\nacc +6";
+
+    fn debugger() -> Debugger {
+        Debugger::new(Interpreter::new_from_code(CODE_SAMPLE).unwrap())
+    }
+
+    #[test]
+    fn test_trace() {
+        let mut debugger = debugger();
+        assert_eq!(
+            debugger.continue_run(),
+            DebugEvent::Stopped(StepOutput::LoopFound(5))
+        );
+
+        let cursors: Vec<usize> = debugger.trace().iter().map(|e| e.cursor).collect();
+        assert_eq!(cursors, vec![0, 1, 2, 6, 7, 3, 4]);
+
+        let last = debugger.trace().last().unwrap();
+        assert_eq!(last.accumulator_before, 5);
+        assert_eq!(last.accumulator_after, 5);
+        assert_eq!(
+            last.to_string(),
+            "#7     [   4] jmp -3           acc 5 -> 5 (visit 1)"
+        );
+
+        // Keep running past the loop detection
+        debugger.step();
+        assert_eq!(debugger.trace().last().unwrap().visit_count, 2);
+    }
+
+    #[test]
+    fn test_breakpoints() {
+        let mut debugger = debugger();
+        debugger.add_breakpoint(Breakpoint::Instruction(6));
+        debugger.add_breakpoint(Breakpoint::AccumulatorAbove(3));
+
+        assert_eq!(debugger.continue_run(), DebugEvent::Breakpoint(0));
+        assert_eq!(debugger.interpreter().cursor(), 6);
+        assert_eq!(debugger.continue_run(), DebugEvent::Breakpoint(1));
+        assert_eq!(debugger.interpreter().accumulator(), 5);
+
+        assert_eq!(
+            debugger.remove_breakpoint(0),
+            Some(Breakpoint::Instruction(6))
+        );
+        assert_eq!(debugger.remove_breakpoint(5), None);
+    }
+
+    #[test]
+    fn test_step_back() {
+        let mut debugger = debugger();
+        for _ in 0..5 {
+            debugger.step();
+        }
+        assert_eq!(debugger.interpreter().accumulator(), 2);
+        assert_eq!(debugger.interpreter().cursor(), 3);
+
+        let entry = debugger.step_back().unwrap();
+        assert_eq!(entry.cursor, 7);
+        assert_eq!(debugger.interpreter().cursor(), 7);
+        assert_eq!(debugger.interpreter().accumulator(), 2);
+
+        debugger.step_back();
+        assert_eq!(debugger.interpreter().accumulator(), 1);
+        assert_eq!(debugger.trace().len(), 3);
+
+        while debugger.step_back().is_some() {}
+        assert_eq!(debugger.interpreter().cursor(), 0);
+        assert_eq!(debugger.interpreter().steps(), 0);
+    }
+
+    #[test]
+    fn test_session() {
+        let mut debugger = debugger();
+        let commands = "step 2\nbreak acc == 2\nc\nback\ninfo\nfoo\nq\nstep";
+        let mut output = vec![];
+        debugger
+            .run_session(commands.as_bytes(), &mut output)
+            .unwrap();
+
+        let output = String::from_utf8(output).unwrap();
+        let lines: Vec<&str> = output.lines().collect();
+        assert_eq!(
+            lines,
+            vec![
+                "Type 'help' for commands.",
+                "=> [   0] nop +0 (acc 0)",
+                "#1     [   0] nop +0           acc 0 -> 0 (visit 1)",
+                "#2     [   1] acc +1           acc 0 -> 1 (visit 1)",
+                "=> [   2] jmp +4 (acc 1)",
+                "Breakpoint 0: acc == 2",
+                "=> [   2] jmp +4 (acc 1)",
+                "Breakpoint 0 hit: acc == 2",
+                "=> [   7] jmp -4 (acc 2)",
+                "undo #4     [   6] acc +1           acc 1 -> 2 (visit 1)",
+                "=> [   6] acc +1 (acc 1)",
+                "steps 3 | registers [1, 0, 0, 0] | output []",
+                "=> [   6] acc +1 (acc 1)",
+                "Unknown command: foo",
+                "=> [   6] acc +1 (acc 1)",
+            ]
+        );
+    }
+}
@@ -0,0 +1,1152 @@
+//! Handheld console virtual machine
+
+use std::{
+    collections::{HashMap, HashSet},
+    convert::TryFrom,
+};
+
+use serde::Deserialize;
+use thiserror::Error;
+
+use super::analysis::{exit_chains, ControlFlowGraph, Repair};
+
+/// Parse error.
+#[derive(Debug, Error, PartialEq, Eq)]
+pub enum ParseError {
+    /// Missing opcode
+    #[error("Missing opcode on line {0}")]
+    MissingOpCode(usize),
+    /// Invalid operand
+    #[error("Invalid operand '{operand}' on line {line}")]
+    InvalidOperand {
+        /// Line number (starting at 1)
+        line: usize,
+        /// Operand
+        operand: String,
+    },
+    /// Unknown label
+    #[error("Unknown label '{label}' on line {line}")]
+    UnknownLabel {
+        /// Line number (starting at 1)
+        line: usize,
+        /// Label
+        label: String,
+    },
+    /// Label defined twice
+    #[error("Duplicate label '{label}' on line {line}")]
+    DuplicateLabel {
+        /// Line number (starting at 1)
+        line: usize,
+        /// Label
+        label: String,
+    },
+}
+
+/// Execution error.
+#[derive(Debug, Error, PartialEq, Eq, Clone)]
+pub enum VmError {
+    /// Jump target is outside of the program
+    #[error("Jump from {from} to {target} is out of range")]
+    JumpOutOfRange {
+        /// Source instruction
+        from: usize,
+        /// Target instruction
+        target: isize,
+    },
+    /// Arithmetic overflow or underflow
+    #[error("Arithmetic overflow on instruction {0}")]
+    Overflow(usize),
+    /// Division by zero
+    #[error("Division by zero on instruction {0}")]
+    DivisionByZero(usize),
+    /// Register does not exist
+    #[error("Unknown register {0}")]
+    UnknownRegister(usize),
+    /// Memory address does not exist
+    #[error("Memory address {0} is out of range")]
+    MemoryOutOfRange(isize),
+    /// Opcode has no handler
+    #[error("Unknown opcode '{0}'")]
+    UnknownOpCode(String),
+    /// Wrong operand count or kind
+    #[error("Invalid operands for '{0}'")]
+    InvalidOperands(String),
+    /// Step budget exhausted
+    #[error("Step budget of {0} exhausted")]
+    BudgetExceeded(usize),
+    /// No instruction flip makes the program terminate
+    #[error("No repair found")]
+    RepairNotFound,
+}
+
+/// Operation code
+#[derive(Debug, Deserialize, Clone, Eq, PartialEq, Hash)]
+#[serde(rename_all = "snake_case")]
+pub enum OpCode {
+    /// No operation
+    Nop,
+    /// Increases or decreases the accumulator
+    Acc,
+    /// Jump to relative instruction number
+    Jmp,
+    /// Set register: `set r x`
+    Set,
+    /// Add to register: `add r x`
+    Add,
+    /// Subtract from register: `sub r x`
+    Sub,
+    /// Multiply register: `mul r x`
+    Mul,
+    /// Divide register: `div r x`
+    Div,
+    /// Remainder of register: `mod r x`
+    Mod,
+    /// Jump to relative instruction if value is zero: `jz x offset`
+    Jz,
+    /// Jump to relative instruction if value is not zero: `jnz x offset`
+    Jnz,
+    /// Load memory to register: `ld r address`
+    Ld,
+    /// Store value to memory: `st x address`
+    St,
+    /// Push value to output: `out x`
+    Out,
+    /// Halt program
+    Hlt,
+    /// Opcode handled by the interpreter opcode table
+    #[serde(skip_deserializing)]
+    Custom(String),
+}
+
+impl OpCode {
+    /// Parse opcode from mnemonic. Unknown mnemonics are custom opcodes.
+    ///
+    /// # Arguments
+    ///
+    /// * `mnemonic` - Mnemonic
+    pub fn from_mnemonic(mnemonic: &str) -> Self {
+        serde_plain::from_str(mnemonic).unwrap_or_else(|_| Self::Custom(mnemonic.to_string()))
+    }
+}
+
+impl std::fmt::Display for OpCode {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Custom(name) => write!(f, "{}", name),
+            other => write!(f, "{}", format!("{:?}", other).to_lowercase()),
+        }
+    }
+}
+
+/// Instruction operand
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Hash)]
+pub enum Operand {
+    /// Immediate value
+    Immediate(isize),
+    /// Register index, written as a letter from `a`
+    Register(usize),
+}
+
+impl Operand {
+    /// Parse operand: signed numbers are immediate values, letters are registers.
+    ///
+    /// # Arguments
+    ///
+    /// * `input` - Input string
+    pub fn parse(input: &str) -> Option<Self> {
+        let mut chars = input.chars();
+        match (chars.next(), chars.next()) {
+            (Some(c), None) if c.is_ascii_lowercase() => {
+                Some(Self::Register((c as u8 - b'a') as usize))
+            }
+            _ => serde_plain::from_str(input).ok().map(Self::Immediate),
+        }
+    }
+}
+
+impl std::fmt::Display for Operand {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Immediate(v) => write!(f, "{:+}", v),
+            Self::Register(r) => write!(f, "{}", (b'a' + *r as u8) as char),
+        }
+    }
+}
+
+/// Instruction
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct Instruction {
+    opcode: OpCode,
+    operands: Vec<Operand>,
+}
+
+impl Instruction {
+    /// Creates a new instruction with one immediate value.
+    ///
+    /// # Arguments
+    ///
+    /// * `opcode` - Instruction `OpCode`
+    /// * `value` - Instruction value
+    pub fn new(opcode: OpCode, value: isize) -> Self {
+        Self::with_operands(opcode, vec![Operand::Immediate(value)])
+    }
+
+    /// Creates a new instruction with operands.
+    ///
+    /// # Arguments
+    ///
+    /// * `opcode` - Instruction `OpCode`
+    /// * `operands` - Operands
+    pub fn with_operands(opcode: OpCode, operands: Vec<Operand>) -> Self {
+        Self { opcode, operands }
+    }
+
+    /// Get opcode.
+    pub fn opcode(&self) -> &OpCode {
+        &self.opcode
+    }
+
+    /// Get operands.
+    pub fn operands(&self) -> &[Operand] {
+        &self.operands
+    }
+
+    /// Get first immediate value, or 0.
+    pub fn value(&self) -> isize {
+        match self.operands.first() {
+            Some(Operand::Immediate(v)) => *v,
+            _ => 0,
+        }
+    }
+
+    /// Convert instruction to fixed instruction by swapping `Nop` and `Jmp` `OpCode`s.
+    pub fn to_fixed_instruction(&self) -> Self {
+        match self.opcode {
+            OpCode::Nop => Self::with_operands(OpCode::Jmp, self.operands.clone()),
+            OpCode::Jmp => Self::with_operands(OpCode::Nop, self.operands.clone()),
+            _ => self.clone(),
+        }
+    }
+}
+
+impl std::fmt::Display for Instruction {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.opcode)?;
+        for operand in &self.operands {
+            write!(f, " {}", operand)?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Instruction parser
+pub struct Parser;
+
+impl Parser {
+    /// Parse instruction from input string.
+    ///
+    /// # Arguments
+    ///
+    /// * `input` - Input string
+    pub fn parse_instruction(input: &str) -> Result<Instruction, ParseError> {
+        Self::parse_instruction_at_line(input, 1)
+    }
+
+    /// Parse code.
+    ///
+    /// # Arguments
+    ///
+    /// * `code` - Source code
+    pub fn parse_code(code: &str) -> Result<Vec<Instruction>, ParseError> {
+        code.lines()
+            .enumerate()
+            .filter(|(_, l)| !l.trim().is_empty())
+            .map(|(idx, l)| Self::parse_instruction_at_line(l, idx + 1))
+            .collect()
+    }
+
+    fn parse_instruction_at_line(input: &str, line: usize) -> Result<Instruction, ParseError> {
+        let mut tokens = input.split_whitespace();
+        let opcode = tokens
+            .next()
+            .map(OpCode::from_mnemonic)
+            .ok_or(ParseError::MissingOpCode(line))?;
+        let operands = tokens
+            .map(|t| {
+                Operand::parse(t).ok_or_else(|| ParseError::InvalidOperand {
+                    line,
+                    operand: t.to_string(),
+                })
+            })
+            .collect::<Result<_, _>>()?;
+
+        Ok(Instruction::with_operands(opcode, operands))
+    }
+}
+
+/// Step output
+#[derive(Debug, Eq, PartialEq)]
+pub enum StepOutput {
+    /// Normal execution
+    Normal,
+    /// Loop found, with current accumulator value
+    LoopFound(isize),
+    /// Finished, with current accumulator value
+    Finished(isize),
+    /// Error
+    Error(VmError),
+}
+
+/// Control flow after an instruction.
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum Flow {
+    /// Go to next instruction
+    Next,
+    /// Jump to relative instruction
+    Jump(isize),
+    /// Halt program
+    Halt,
+}
+
+/// Custom opcode handler.
+pub type OpCodeHandler = fn(&mut Machine, &[Operand]) -> Result<Flow, VmError>;
+
+/// Interpreter configuration.
+#[derive(Debug, Clone)]
+pub struct VmConfig {
+    /// Register count (register `a` is the accumulator)
+    pub registers: usize,
+    /// Memory size
+    pub memory_size: usize,
+    /// Maximum executed instructions
+    pub step_budget: Option<usize>,
+    /// Stop when an instruction is about to run a second time
+    pub detect_loops: bool,
+}
+
+impl Default for VmConfig {
+    fn default() -> Self {
+        Self {
+            registers: 4,
+            memory_size: 0,
+            step_budget: None,
+            detect_loops: true,
+        }
+    }
+}
+
+/// Machine state: registers, memory and output.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Machine {
+    registers: Vec<isize>,
+    memory: Vec<isize>,
+    output: Vec<isize>,
+    cursor: usize,
+}
+
+impl Machine {
+    fn new(config: &VmConfig) -> Self {
+        Self {
+            registers: vec![0; config.registers.max(1)],
+            memory: vec![0; config.memory_size],
+            output: vec![],
+            cursor: 0,
+        }
+    }
+
+    /// Get accumulator value (register `a`).
+    pub fn accumulator(&self) -> isize {
+        self.registers[0]
+    }
+
+    /// Get registers.
+    pub fn registers(&self) -> &[isize] {
+        &self.registers
+    }
+
+    /// Get memory.
+    pub fn memory(&self) -> &[isize] {
+        &self.memory
+    }
+
+    /// Get output values.
+    pub fn output(&self) -> &[isize] {
+        &self.output
+    }
+
+    /// Get current instruction index.
+    pub fn cursor(&self) -> usize {
+        self.cursor
+    }
+
+    /// Read operand value.
+    ///
+    /// # Arguments
+    ///
+    /// * `operand` - Operand
+    pub fn read(&self, operand: Operand) -> Result<isize, VmError> {
+        match operand {
+            Operand::Immediate(v) => Ok(v),
+            Operand::Register(r) => self
+                .registers
+                .get(r)
+                .copied()
+                .ok_or(VmError::UnknownRegister(r)),
+        }
+    }
+
+    /// Write value to register operand.
+    ///
+    /// # Arguments
+    ///
+    /// * `operand` - Register operand
+    /// * `value` - Value
+    pub fn write(&mut self, operand: Operand, value: isize) -> Result<(), VmError> {
+        match operand {
+            Operand::Register(r) => {
+                let register = self
+                    .registers
+                    .get_mut(r)
+                    .ok_or(VmError::UnknownRegister(r))?;
+                *register = value;
+                Ok(())
+            }
+            Operand::Immediate(_) => Err(VmError::InvalidOperands("write".into())),
+        }
+    }
+
+    /// Push value to output.
+    ///
+    /// # Arguments
+    ///
+    /// * `value` - Value
+    pub fn push_output(&mut self, value: isize) {
+        self.output.push(value);
+    }
+
+    fn memory_slot(&mut self, address: isize) -> Result<&mut isize, VmError> {
+        usize::try_from(address)
+            .ok()
+            .and_then(move |a| self.memory.get_mut(a))
+            .ok_or(VmError::MemoryOutOfRange(address))
+    }
+
+    fn execute(&mut self, instr: &Instruction) -> Result<Flow, VmError> {
+        let cursor = self.cursor;
+        let name = || instr.opcode.to_string();
+        let ops = instr.operands.as_slice();
+        let arith = |this: &mut Self, f: fn(isize, isize) -> Option<isize>| match *ops {
+            [target, value] => {
+                let result = f(this.read(target)?, this.read(value)?);
+                this.write(target, result.ok_or(VmError::Overflow(cursor))?)?;
+                Ok(Flow::Next)
+            }
+            _ => Err(VmError::InvalidOperands(name())),
+        };
+
+        match (&instr.opcode, ops) {
+            (OpCode::Nop, _) => Ok(Flow::Next),
+            (OpCode::Acc, &[value]) => {
+                let result = self.registers[0].checked_add(self.read(value)?);
+                self.registers[0] = result.ok_or(VmError::Overflow(cursor))?;
+                Ok(Flow::Next)
+            }
+            (OpCode::Jmp, &[offset]) => Ok(Flow::Jump(self.read(offset)?)),
+            (OpCode::Set, &[target, value]) => {
+                let value = self.read(value)?;
+                self.write(target, value)?;
+                Ok(Flow::Next)
+            }
+            (OpCode::Add, _) => arith(self, isize::checked_add),
+            (OpCode::Sub, _) => arith(self, isize::checked_sub),
+            (OpCode::Mul, _) => arith(self, isize::checked_mul),
+            (OpCode::Div, &[_, divisor]) | (OpCode::Mod, &[_, divisor])
+                if self.read(divisor)? == 0 =>
+            {
+                Err(VmError::DivisionByZero(cursor))
+            }
+            (OpCode::Div, _) => arith(self, isize::checked_div),
+            (OpCode::Mod, _) => arith(self, isize::checked_rem_euclid),
+            (OpCode::Jz, &[value, offset]) | (OpCode::Jnz, &[value, offset]) => {
+                let is_zero = self.read(value)? == 0;
+                if is_zero == (instr.opcode == OpCode::Jz) {
+                    Ok(Flow::Jump(self.read(offset)?))
+                } else {
+                    Ok(Flow::Next)
+                }
+            }
+            (OpCode::Ld, &[target, address]) => {
+                let address = self.read(address)?;
+                let value = *self.memory_slot(address)?;
+                self.write(target, value)?;
+                Ok(Flow::Next)
+            }
+            (OpCode::St, &[value, address]) => {
+                let value = self.read(value)?;
+                let address = self.read(address)?;
+                *self.memory_slot(address)? = value;
+                Ok(Flow::Next)
+            }
+            (OpCode::Out, &[value]) => {
+                let value = self.read(value)?;
+                self.push_output(value);
+                Ok(Flow::Next)
+            }
+            (OpCode::Hlt, &[]) => Ok(Flow::Halt),
+            (OpCode::Custom(name), _) => Err(VmError::UnknownOpCode(name.clone())),
+            _ => Err(VmError::InvalidOperands(name())),
+        }
+    }
+}
+
+/// Value overwritten by an instruction.
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Overwritten {
+    /// Register index and previous value
+    Register(usize, isize),
+    /// Memory address and previous value
+    Memory(usize, isize),
+    /// Every register, for custom opcodes which may write any of them
+    Registers(Vec<isize>),
+}
+
+/// Changes made by one executed instruction, used to undo it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StepUndo {
+    cursor: usize,
+    steps: usize,
+    overwritten: Option<Overwritten>,
+    output_len: usize,
+    newly_seen: bool,
+}
+
+/// Interpreter
+pub struct Interpreter {
+    instructions: Vec<Instruction>,
+    config: VmConfig,
+    opcodes: HashMap<String, OpCodeHandler>,
+    machine: Machine,
+    steps: usize,
+    seen_instructions: HashSet<usize>,
+}
+
+impl Interpreter {
+    /// Creates interpreter from instructions, with default configuration.
+    ///
+    /// # Arguments
+    ///
+    /// * `instructions` - Instructions
+    pub fn new(instructions: Vec<Instruction>) -> Self {
+        Self::with_config(instructions, VmConfig::default())
+    }
+
+    /// Creates interpreter from instructions and configuration.
+    ///
+    /// # Arguments
+    ///
+    /// * `instructions` - Instructions
+    /// * `config` - Configuration
+    pub fn with_config(instructions: Vec<Instruction>, config: VmConfig) -> Self {
+        Self {
+            instructions,
+            machine: Machine::new(&config),
+            config,
+            opcodes: HashMap::new(),
+            steps: 0,
+            seen_instructions: HashSet::new(),
+        }
+    }
+
+    /// Creates interpreter from code.
+    ///
+    /// # Arguments
+    ///
+    /// * `code` - Source code
+    pub fn new_from_code(code: &str) -> Result<Self, ParseError> {
+        Parser::parse_code(code).map(Self::new)
+    }
+
+    /// Register a custom opcode handler.
+    ///
+    /// # Arguments
+    ///
+    /// * `mnemonic` - Opcode mnemonic
+    /// * `handler` - Handler
+    pub fn register_opcode(&mut self, mnemonic: &str, handler: OpCodeHandler) {
+        self.opcodes.insert(mnemonic.to_string(), handler);
+    }
+
+    /// Get instructions.
+    pub fn instructions(&self) -> &[Instruction] {
+        &self.instructions
+    }
+
+    /// Get machine state.
+    pub fn machine(&self) -> &Machine {
+        &self.machine
+    }
+
+    /// Get accumulator value.
+    pub fn accumulator(&self) -> isize {
+        self.machine.accumulator()
+    }
+
+    /// Get current instruction index.
+    pub fn cursor(&self) -> usize {
+        self.machine.cursor
+    }
+
+    /// Get executed instruction count.
+    pub fn steps(&self) -> usize {
+        self.steps
+    }
+
+    /// Reset state, conserving current instructions.
+    pub fn reset_state(&mut self) {
+        self.machine = Machine::new(&self.config);
+        self.steps = 0;
+        self.seen_instructions = HashSet::new();
+    }
+
+    /// Step on next instruction, also returning what is needed to undo it.
+    pub fn step_with_undo(&mut self) -> (StepOutput, StepUndo) {
+        let cursor = self.machine.cursor;
+        let undo = StepUndo {
+            cursor,
+            steps: self.steps,
+            overwritten: self
+                .instructions
+                .get(cursor)
+                .and_then(|instr| self.overwritten_by(instr)),
+            output_len: self.machine.output.len(),
+            newly_seen: !self.seen_instructions.contains(&cursor),
+        };
+
+        (self.step(), undo)
+    }
+
+    /// Undo a step. Steps must be undone from the last one.
+    ///
+    /// # Arguments
+    ///
+    /// * `undo` - Step undo record
+    pub fn undo(&mut self, undo: StepUndo) {
+        match undo.overwritten {
+            Some(Overwritten::Register(r, value)) => self.machine.registers[r] = value,
+            Some(Overwritten::Memory(address, value)) => self.machine.memory[address] = value,
+            Some(Overwritten::Registers(registers)) => self.machine.registers = registers,
+            None => (),
+        }
+
+        if undo.newly_seen {
+            self.seen_instructions.remove(&undo.cursor);
+        }
+        self.machine.output.truncate(undo.output_len);
+        self.machine.cursor = undo.cursor;
+        self.steps = undo.steps;
+    }
+
+    /// Get the value an instruction may overwrite, before executing it.
+    fn overwritten_by(&self, instr: &Instruction) -> Option<Overwritten> {
+        let machine = &self.machine;
+        let register = |operand: &Operand| match *operand {
+            Operand::Register(r) => machine
+                .registers
+                .get(r)
+                .map(|&value| Overwritten::Register(r, value)),
+            Operand::Immediate(_) => None,
+        };
+
+        match (&instr.opcode, instr.operands.as_slice()) {
+            (OpCode::Acc, _) => Some(Overwritten::Register(0, machine.registers[0])),
+            (
+                OpCode::Set
+                | OpCode::Add
+                | OpCode::Sub
+                | OpCode::Mul
+                | OpCode::Div
+                | OpCode::Mod
+                | OpCode::Ld,
+                [target, _],
+            ) => register(target),
+            (OpCode::St, [_, address]) => {
+                let address = usize::try_from(machine.read(*address).ok()?).ok()?;
+                machine
+                    .memory
+                    .get(address)
+                    .map(|&value| Overwritten::Memory(address, value))
+            }
+            (OpCode::Custom(_), _) => Some(Overwritten::Registers(machine.registers.clone())),
+            _ => None,
+        }
+    }
+
+    /// Step on next instruction.
+    pub fn step(&mut self) -> StepOutput {
+        let cursor = self.machine.cursor;
+        if cursor >= self.instructions.len() {
+            // End !
+            return StepOutput::Finished(self.accumulator());
+        }
+
+        if let Some(budget) = self.config.step_budget {
+            if self.steps >= budget {
+                return StepOutput::Error(VmError::BudgetExceeded(budget));
+            }
+        }
+
+        let instr = &self.instructions[cursor];
+        let flow = match &instr.opcode {
+            OpCode::Custom(name) if self.opcodes.contains_key(name) => {
+                self.opcodes[name](&mut self.machine, &instr.operands)
+            }
+            _ => self.machine.execute(instr),
+        };
+        self.steps += 1;
+
+        let next_cursor = match flow {
+            Ok(Flow::Next) => cursor + 1,
+            Ok(Flow::Jump(offset)) => {
+                let target = cursor as isize + offset;
+                if target < 0 || target as usize > self.instructions.len() {
+                    return StepOutput::Error(VmError::JumpOutOfRange {
+                        from: cursor,
+                        target,
+                    });
+                }
+                target as usize
+            }
+            Ok(Flow::Halt) => {
+                self.machine.cursor = self.instructions.len();
+                return StepOutput::Finished(self.accumulator());
+            }
+            Err(e) => return StepOutput::Error(e),
+        };
+
+        // Update cursor and seen instructions
+        self.seen_instructions.insert(cursor);
+        self.machine.cursor = next_cursor;
+
+        if self.config.detect_loops && self.seen_instructions.contains(&next_cursor) {
+            StepOutput::LoopFound(self.accumulator())
+        } else {
+            StepOutput::Normal
+        }
+    }
+
+    /// Run interpreter.
+    /// Breaks on Finished, LoopFound or Error.
+    pub fn run(&mut self) -> StepOutput {
+        loop {
+            match self.step() {
+                StepOutput::Normal => (),
+                step => {
+                    return step;
+                }
+            }
+        }
+    }
+
+    /// Find every single `Nop` / `Jmp` flip which makes the program terminate.
+    ///
+    /// Only flips on the executed path can change the program behavior, and a
+    /// flip repairs the program if its new target reaches the end of the
+    /// control-flow graph, which is computed once with a reverse traversal.
+    ///
+    /// The final accumulator of a repair is the accumulator at the flip point
+    /// plus the accumulator change along the chain from the new target to the
+    /// end of the program, both computed once. Only candidates whose chain is
+    /// not statically known (e.g. using registers or memory) are run, for at
+    /// most the instruction count times the executed path length steps:
+    /// repairs needing longer runs are not reported.
+    ///
+    /// Repairs are sorted by instruction index, and the interpreter state is
+    /// reset on return.
+    pub fn find_repairs(&mut self) -> Vec<Repair> {
+        let graph = ControlFlowGraph::new(&self.instructions);
+        let terminating = graph.terminating_nodes();
+
+        // Record executed path until the first repeated instruction, with the
+        // accumulator and step count before each instruction
+        self.reset_state();
+        let mut visited = vec![false; self.instructions.len()];
+        let mut path = vec![];
+        loop {
+            let cursor = self.machine.cursor;
+            if cursor >= self.instructions.len() || visited[cursor] {
+                break;
+            }
+
+            visited[cursor] = true;
+            path.push((cursor, self.accumulator(), self.steps));
+            match self.step() {
+                StepOutput::Normal | StepOutput::LoopFound(_) => (),
+                _ => break,
+            }
+        }
+
+        // Chains avoid the executed path, so that the flipped instruction is
+        // executed once at most
+        let chains = exit_chains(&self.instructions, &visited);
+
+        // Dynamic chains may loop forever, even with loop detection off, so
+        // their runs get a bounded step budget
+        let fallback_budget = self.instructions.len().saturating_mul(path.len());
+
+        let mut candidates: Vec<(usize, usize, isize, usize)> = path
+            .into_iter()
+            .filter_map(|(idx, accumulator, steps)| {
+                graph
+                    .flipped_target(idx, &self.instructions[idx])
+                    .filter(|&target| terminating[target])
+                    .map(|target| (idx, target, accumulator, steps))
+            })
+            .collect();
+        candidates.sort_unstable();
+
+        let mut repairs = vec![];
+        for (index, target, accumulator, steps) in candidates {
+            let instruction = self.instructions[index].to_fixed_instruction();
+            let accumulator = match chains[target] {
+                Some(chain) => {
+                    let total_steps = steps + 1 + chain.steps;
+                    if self.config.step_budget.is_some_and(|b| total_steps > b) {
+                        None
+                    } else {
+                        chain.final_accumulator(accumulator)
+                    }
+                }
+                None => self.run_with_repair(index, &instruction, fallback_budget),
+            };
+
+            if let Some(accumulator) = accumulator {
+                repairs.push(Repair {
+                    index,
+                    instruction,
+                    accumulator,
+                });
+            }
+        }
+
+        self.reset_state();
+        repairs
+    }
+
+    /// Run program from start with a replaced instruction, returning the final
+    /// accumulator if it terminates within `budget` steps (or the configured
+    /// step budget if lower).
+    fn run_with_repair(
+        &mut self,
+        index: usize,
+        instruction: &Instruction,
+        budget: usize,
+    ) -> Option<isize> {
+        let original = std::mem::replace(&mut self.instructions[index], instruction.clone());
+        let step_budget = self.config.step_budget;
+        self.config.step_budget = Some(step_budget.map_or(budget, |b| b.min(budget)));
+        self.reset_state();
+        let output = self.run();
+        self.config.step_budget = step_budget;
+        self.instructions[index] = original;
+
+        match output {
+            StepOutput::Finished(accumulator) => Some(accumulator),
+            _ => None,
+        }
+    }
+
+    /// Run on repair mode, applying the first repair found.
+    /// Returns Finished with the repaired program final accumulator, or Error.
+    ///
+    /// The repaired program is not run again, so the interpreter state is reset.
+    pub fn run_repair_mode(&mut self) -> StepOutput {
+        match self.find_repairs().into_iter().next() {
+            Some(repair) => {
+                self.instructions[repair.index] = repair.instruction;
+                StepOutput::Finished(repair.accumulator)
+            }
+            None => StepOutput::Error(VmError::RepairNotFound),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn run_with_config(code: &str, config: VmConfig) -> (StepOutput, Machine) {
+        let mut interpreter = Interpreter::with_config(Parser::parse_code(code).unwrap(), config);
+        let output = interpreter.run();
+        (output, interpreter.machine().clone())
+    }
+
+    fn run(code: &str) -> (StepOutput, Machine) {
+        run_with_config(code, VmConfig::default())
+    }
+
+    #[test]
+    fn test_parse_operands() {
+        assert_eq!(
+            Parser::parse_instruction("add b -3"),
+            Ok(Instruction::with_operands(
+                OpCode::Add,
+                vec![Operand::Register(1), Operand::Immediate(-3)]
+            ))
+        );
+        assert_eq!(
+            Parser::parse_instruction("foo 2"),
+            Ok(Instruction::with_operands(
+                OpCode::Custom("foo".into()),
+                vec![Operand::Immediate(2)]
+            ))
+        );
+        assert_eq!(
+            Parser::parse_code("nop +0\nadd b ?"),
+            Err(ParseError::InvalidOperand {
+                line: 2,
+                operand: "?".into()
+            })
+        );
+    }
+
+    #[test]
+    fn test_arithmetic_and_output() {
+        let code = "set b +6\nmul b +7\nout b\nsub b +2\ndiv b +8\nout b\nmod b +3\nadd a b\nout a\nhlt\nout +1";
+        let (output, machine) = run(code);
+
+        assert_eq!(output, StepOutput::Finished(2));
+        assert_eq!(machine.output(), &[42, 5, 2]);
+        assert_eq!(machine.registers(), &[2, 2, 0, 0]);
+    }
+
+    #[test]
+    fn test_conditional_jumps() {
+        // Compute 5! with a countdown loop
+        let code = "set a +1\nset b +5\nmul a b\nsub b +1\njnz b -2\nout a";
+        let config = VmConfig {
+            detect_loops: false,
+            ..VmConfig::default()
+        };
+        let (output, machine) = run_with_config(code, config);
+
+        assert_eq!(output, StepOutput::Finished(120));
+        assert_eq!(machine.output(), &[120]);
+
+        let (output, _) = run("jz +0 +2\nacc +1\nacc +2");
+        assert_eq!(output, StepOutput::Finished(2));
+    }
+
+    #[test]
+    fn test_memory() {
+        let config = VmConfig {
+            memory_size: 2,
+            ..VmConfig::default()
+        };
+        let (output, machine) = run_with_config("st +9 +1\nld c +1\nout c", config.clone());
+        assert_eq!(output, StepOutput::Finished(0));
+        assert_eq!(machine.memory(), &[0, 9]);
+        assert_eq!(machine.output(), &[9]);
+
+        let (output, _) = run_with_config("ld c +2", config);
+        assert_eq!(output, StepOutput::Error(VmError::MemoryOutOfRange(2)));
+    }
+
+    #[test]
+    fn test_errors() {
+        assert_eq!(
+            run("nop +0\njmp -2").0,
+            StepOutput::Error(VmError::JumpOutOfRange {
+                from: 1,
+                target: -1
+            })
+        );
+        assert_eq!(
+            run("jmp +3").0,
+            StepOutput::Error(VmError::JumpOutOfRange { from: 0, target: 3 })
+        );
+        assert_eq!(run("jmp +1").0, StepOutput::Finished(0));
+        assert_eq!(
+            run("div a +0").0,
+            StepOutput::Error(VmError::DivisionByZero(0))
+        );
+        assert_eq!(
+            run(&format!("acc +{}\nacc +1", isize::MAX)).0,
+            StepOutput::Error(VmError::Overflow(1))
+        );
+        assert_eq!(
+            run("set z +1").0,
+            StepOutput::Error(VmError::UnknownRegister(25))
+        );
+        assert_eq!(
+            run("set +1 +1").0,
+            StepOutput::Error(VmError::InvalidOperands("write".into()))
+        );
+        assert_eq!(
+            run("add a").0,
+            StepOutput::Error(VmError::InvalidOperands("add".into()))
+        );
+        assert_eq!(
+            run("foo").0,
+            StepOutput::Error(VmError::UnknownOpCode("foo".into()))
+        );
+    }
+
+    #[test]
+    fn test_step_budget() {
+        let config = VmConfig {
+            step_budget: Some(10),
+            detect_loops: false,
+            ..VmConfig::default()
+        };
+        let (output, machine) = run_with_config("acc +1\njmp -1", config);
+
+        assert_eq!(output, StepOutput::Error(VmError::BudgetExceeded(10)));
+        assert_eq!(machine.accumulator(), 5);
+    }
+
+    #[test]
+    fn test_custom_opcode() {
+        fn double(machine: &mut Machine, operands: &[Operand]) -> Result<Flow, VmError> {
+            match *operands {
+                [target] => {
+                    let value = machine.read(target)?;
+                    machine.write(target, value * 2)?;
+                    Ok(Flow::Next)
+                }
+                _ => Err(VmError::InvalidOperands("dbl".into())),
+            }
+        }
+
+        let mut interpreter = Interpreter::new(Parser::parse_code("acc +3\ndbl a\ndbl a").unwrap());
+        interpreter.register_opcode("dbl", double);
+
+        assert_eq!(interpreter.run(), StepOutput::Finished(12));
+    }
+
+    #[test]
+    fn test_undo() {
+        let config = VmConfig {
+            memory_size: 2,
+            ..VmConfig::default()
+        };
+        let code = "set b +6\nst b +1\nout b\nacc +3\nld c +1\nmul c +7\nhlt";
+        let mut interpreter = Interpreter::with_config(Parser::parse_code(code).unwrap(), config);
+
+        let mut undos = vec![];
+        let mut machines = vec![interpreter.machine().clone()];
+        loop {
+            let (output, undo) = interpreter.step_with_undo();
+            undos.push(undo);
+            machines.push(interpreter.machine().clone());
+            if output != StepOutput::Normal {
+                assert_eq!(output, StepOutput::Finished(3));
+                break;
+            }
+        }
+        assert_eq!(interpreter.machine().registers(), &[3, 6, 42, 0]);
+
+        while let Some(undo) = undos.pop() {
+            machines.pop();
+            interpreter.undo(undo);
+            assert_eq!(interpreter.machine(), machines.last().unwrap());
+            assert_eq!(interpreter.steps(), undos.len());
+        }
+
+        // Loop detection state is restored too
+        let mut interpreter = Interpreter::new_from_code("jmp +0").unwrap();
+        let (output, undo) = interpreter.step_with_undo();
+        assert_eq!(output, StepOutput::LoopFound(0));
+        interpreter.undo(undo);
+        assert_eq!(interpreter.step(), StepOutput::LoopFound(0));
+    }
+
+    #[test]
+    fn test_find_repairs() {
+        let code = "nop +0\nacc +1\njmp +4\nacc +3\njmp -3\nacc -99\nacc +1\njmp -4\nacc +6";
+        let mut interpreter = Interpreter::new_from_code(code).unwrap();
+
+        assert_eq!(
+            interpreter.find_repairs(),
+            vec![Repair {
+                index: 7,
+                instruction: Instruction::new(OpCode::Nop, -4),
+                accumulator: 8
+            }]
+        );
+        assert_eq!(
+            interpreter.instructions()[7],
+            Instruction::new(OpCode::Jmp, -4)
+        );
+        assert_eq!(interpreter.steps(), 0);
+    }
+
+    #[test]
+    fn test_find_repairs_large_program() {
+        let size = 50_000;
+        let mut instructions = vec![Instruction::new(OpCode::Acc, 1); size];
+        instructions[10] = Instruction::new(OpCode::Nop, (size - 10) as isize);
+        instructions[size - 1] = Instruction::new(OpCode::Jmp, -(size as isize - 1));
+
+        let repairs = Interpreter::new(instructions).find_repairs();
+        let found: Vec<(usize, isize)> = repairs.iter().map(|r| (r.index, r.accumulator)).collect();
+        assert_eq!(found, vec![(10, 10), (size - 1, size as isize - 2)]);
+    }
+
+    #[test]
+    fn test_find_repairs_fallback() {
+        let repairs = |code: &str, config: VmConfig| {
+            let mut interpreter =
+                Interpreter::with_config(Parser::parse_code(code).unwrap(), config);
+            let found: Vec<(usize, isize)> = interpreter
+                .find_repairs()
+                .iter()
+                .map(|r| (r.index, r.accumulator))
+                .collect();
+            found
+        };
+
+        // Chain through registers, run to get the accumulator
+        assert_eq!(
+            repairs(
+                "nop +0\njmp +0\nacc +2\nset b +3\nadd a b",
+                VmConfig::default()
+            ),
+            vec![(1, 5)]
+        );
+        // Chain through the flipped instruction
+        assert_eq!(repairs("nop +0", VmConfig::default()), vec![]);
+        // Accumulator overflow on the chain
+        assert_eq!(
+            repairs(
+                &format!("acc +{}\njmp +0\nacc +1", isize::MAX),
+                VmConfig::default()
+            ),
+            vec![]
+        );
+
+        // Repaired sample runs 6 instructions
+        let code = "nop +0\nacc +1\njmp +4\nacc +3\njmp -3\nacc -99\nacc +1\njmp -4\nacc +6";
+        let budget = |b| VmConfig {
+            step_budget: Some(b),
+            ..VmConfig::default()
+        };
+        assert_eq!(repairs(code, budget(5)), vec![]);
+        assert_eq!(repairs(code, budget(6)), vec![(7, 8)]);
+
+        // Repaired program loops on a register jump, which only the step
+        // budget of the fallback run can stop
+        let no_loop_detection = VmConfig {
+            detect_loops: false,
+            ..VmConfig::default()
+        };
+        assert_eq!(
+            repairs("set a +1\njmp +0\njnz a +0", no_loop_detection.clone()),
+            vec![]
+        );
+        assert_eq!(
+            repairs("set a +1\njmp +0\njnz b +0\nacc +4", no_loop_detection),
+            vec![(1, 5)]
+        );
+    }
+}
@@ -85,9 +85,11 @@
 //!
 //! What is the encryption weakness in your XMAS-encrypted list of numbers?
 
-use std::cmp::Ordering;
+use std::{collections::HashMap, io::BufRead};
 
-use itertools::Itertools;
+use thiserror::Error;
+
+use crate::common::ksum::KSum;
 
 const EX1_RING_SIZE: usize = 25;
 const INPUT_VALUES: &str = include_str!("input.txt");
@@ -99,12 +101,175 @@ pub fn run_ex1() -> usize {
 
 /// Part two answer.
 pub fn run_ex2() -> usize {
-    let target = run_ex1();
-    XmasScanner::find_weakness(INPUT_VALUES, target)
+    let numbers = XmasScanner::parse_numbers(INPUT_VALUES);
+    let target = XmasValidator::new(EX1_RING_SIZE)
+        .invalid_numbers(numbers.iter().copied())
+        .next()
+        .unwrap()
+        .value;
+
+    XmasScanner::find_weakness_in(numbers, target)
         .map(|x| x.get_sum())
         .unwrap()
 }
 
+/// Day error.
+#[derive(Debug, Error)]
+pub enum DayError {
+    /// Number parse error
+    #[error("Invalid number on line {line}: '{value}'")]
+    NumberParseError {
+        /// Line number (starting at 1)
+        line: usize,
+        /// Line value
+        value: String,
+    },
+    /// Read error
+    #[error("Read error: {0}")]
+    ReadError(#[from] std::io::Error),
+}
+
+/// Number which is not the sum of two numbers of the preceding window.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct InvalidNumber {
+    /// Index in the stream
+    pub index: usize,
+    /// Value
+    pub value: usize,
+}
+
+/// Streaming XMAS validator.
+///
+/// The last `preamble_size` numbers are kept in a ring buffer, along with the
+/// multiset of the sums of each pair of them, so checking a number is a single
+/// lookup and pushing a number costs `O(preamble_size)`.
+pub struct XmasValidator {
+    preamble_size: usize,
+    ring: Vec<usize>,
+    head: usize,
+    pair_sums: HashMap<usize, usize>,
+    index: usize,
+}
+
+impl XmasValidator {
+    /// Create validator.
+    ///
+    /// # Arguments
+    ///
+    /// * `preamble_size` - Preamble size
+    pub fn new(preamble_size: usize) -> Self {
+        Self {
+            preamble_size,
+            ring: Vec::with_capacity(preamble_size),
+            head: 0,
+            pair_sums: HashMap::new(),
+            index: 0,
+        }
+    }
+
+    /// Get the number of values pushed.
+    pub fn len(&self) -> usize {
+        self.index
+    }
+
+    /// Check if no value was pushed.
+    pub fn is_empty(&self) -> bool {
+        self.index == 0
+    }
+
+    /// Check if a value is the sum of two numbers from the current window.
+    /// Always valid while the preamble is not complete.
+    ///
+    /// # Arguments
+    ///
+    /// * `value` - Value
+    pub fn is_valid(&self, value: usize) -> bool {
+        self.ring.len() < self.preamble_size || self.pair_sums.contains_key(&value)
+    }
+
+    /// Validate and push value to the window.
+    /// Invalid values are pushed too.
+    ///
+    /// # Arguments
+    ///
+    /// * `value` - Value
+    pub fn push(&mut self, value: usize) -> Result<(), InvalidNumber> {
+        let valid = self.is_valid(value);
+        let index = self.index;
+        self.index += 1;
+
+        if self.ring.len() < self.preamble_size {
+            for i in 0..self.ring.len() {
+                *self.pair_sums.entry(value + self.ring[i]).or_default() += 1;
+            }
+            self.ring.push(value);
+        } else if self.preamble_size > 0 {
+            let (head, oldest) = (self.head, self.ring[self.head]);
+            for i in (0..self.preamble_size).filter(|&i| i != head) {
+                let other = self.ring[i];
+                self.remove_pair_sum(oldest + other);
+                *self.pair_sums.entry(value + other).or_default() += 1;
+            }
+
+            self.ring[self.head] = value;
+            self.head = (self.head + 1) % self.preamble_size;
+        }
+
+        if valid {
+            Ok(())
+        } else {
+            Err(InvalidNumber { index, value })
+        }
+    }
+
+    /// Lazily validate values, yielding invalid numbers.
+    ///
+    /// # Arguments
+    ///
+    /// * `values` - Values
+    pub fn invalid_numbers<I>(self, values: I) -> InvalidNumbers<I::IntoIter>
+    where
+        I: IntoIterator<Item = usize>,
+    {
+        InvalidNumbers {
+            validator: self,
+            values: values.into_iter(),
+        }
+    }
+
+    fn remove_pair_sum(&mut self, sum: usize) {
+        if let Some(count) = self.pair_sums.get_mut(&sum) {
+            *count -= 1;
+            if *count == 0 {
+                self.pair_sums.remove(&sum);
+            }
+        }
+    }
+}
+
+/// Lazy iterator over invalid numbers.
+pub struct InvalidNumbers<I> {
+    validator: XmasValidator,
+    values: I,
+}
+
+impl<I> Iterator for InvalidNumbers<I>
+where
+    I: Iterator<Item = usize>,
+{
+    type Item = InvalidNumber;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        for value in &mut self.values {
+            if let Err(invalid) = self.validator.push(value) {
+                return Some(invalid);
+            }
+        }
+
+        None
+    }
+}
+
 /// Xmas Weakness output
 pub struct XmasWeaknessOutput {
     data: Vec<usize>,
@@ -113,7 +278,7 @@ pub struct XmasWeaknessOutput {
 }
 
 impl XmasWeaknessOutput {
-    /// Create output from data, start and end cursor.
+    /// Create output from data, start and end cursor (inclusive).
     ///
     /// # Arguments
     ///
@@ -130,7 +295,7 @@ impl XmasWeaknessOutput {
 
     /// Find smallest and largest value in data.
     pub fn get_min_max(&self) -> (usize, usize) {
-        let range = &self.data[self.start_cursor..self.end_cursor];
+        let range = &self.data[self.start_cursor..=self.end_cursor];
         (*range.iter().min().unwrap(), *range.iter().max().unwrap())
     }
 
@@ -145,38 +310,80 @@ impl XmasWeaknessOutput {
 pub struct XmasScanner;
 
 impl XmasScanner {
-    /// Parse input string and find error if any.
+    /// Parse numbers from input string, skipping invalid lines.
     ///
     /// # Arguments
     ///
     /// * `input` - Input string
-    pub fn parse_and_find_error(input: &str, ring_size: usize) -> Option<usize> {
-        let mut data = Vec::with_capacity(ring_size);
-        // Parse initial data
-        let mut initial_data = input
+    pub fn parse_numbers(input: &str) -> Vec<usize> {
+        input
             .lines()
-            .take(ring_size)
-            .filter_map(|x| x.trim().parse::<usize>().ok())
-            .collect();
-        data.append(&mut initial_data);
+            .filter_map(|x| x.trim().parse().ok())
+            .collect()
+    }
 
-        // Now parse remaining items
-        let remaining_items: Vec<usize> = input
+    /// Read numbers from a reader, one per line.
+    /// Blank lines are skipped.
+    ///
+    /// # Arguments
+    ///
+    /// * `reader` - Reader
+    pub fn read_numbers<R: BufRead>(reader: R) -> impl Iterator<Item = Result<usize, DayError>> {
+        reader
             .lines()
-            .skip(ring_size)
-            .filter_map(|x| x.trim().parse::<usize>().ok())
-            .collect();
+            .enumerate()
+            .filter_map(|(idx, line)| match line {
+                Ok(line) if line.trim().is_empty() => None,
+                Ok(line) => Some(line.trim().parse().map_err(|_| DayError::NumberParseError {
+                    line: idx + 1,
+                    value: line,
+                })),
+                Err(e) => Some(Err(e.into())),
+            })
+    }
 
-        for x in remaining_items {
-            if Self::find_sum(&data, x).is_some() {
-                data.remove(0);
-                data.push(x);
-            } else {
-                return Some(x);
+    /// Validate numbers from a reader, returning every invalid number.
+    ///
+    /// # Arguments
+    ///
+    /// * `reader` - Reader
+    /// * `ring_size` - Preamble size
+    pub fn validate_reader<R: BufRead>(
+        reader: R,
+        ring_size: usize,
+    ) -> Result<Vec<InvalidNumber>, DayError> {
+        let mut validator = XmasValidator::new(ring_size);
+        let mut errors = vec![];
+        for value in Self::read_numbers(reader) {
+            if let Err(invalid) = validator.push(value?) {
+                errors.push(invalid);
             }
         }
 
-        None
+        Ok(errors)
+    }
+
+    /// Parse input string and find error if any.
+    ///
+    /// # Arguments
+    ///
+    /// * `input` - Input string
+    /// * `ring_size` - Preamble size
+    pub fn parse_and_find_error(input: &str, ring_size: usize) -> Option<usize> {
+        Self::find_errors(input, ring_size)
+            .next()
+            .map(|invalid| invalid.value)
+    }
+
+    /// Parse input string and lazily find every error.
+    ///
+    /// # Arguments
+    ///
+    /// * `input` - Input string
+    /// * `ring_size` - Preamble size
+    pub fn find_errors(input: &str, ring_size: usize) -> impl Iterator<Item = InvalidNumber> + '_ {
+        let values = input.lines().filter_map(|x| x.trim().parse::<usize>().ok());
+        XmasValidator::new(ring_size).invalid_numbers(values)
     }
 
     /// Find a sum of number from the ring equals to the target number.
@@ -186,13 +393,8 @@ impl XmasScanner {
     /// * `data` - Data
     /// * `target` - Target number
     pub fn find_sum(data: &[usize], target: usize) -> Option<(usize, usize)> {
-        data.iter().combinations(2).find_map(|v| {
-            if v[0] + v[1] == target {
-                Some((*v[0], *v[1]))
-            } else {
-                None
-            }
-        })
+        let ksum = KSum::new(data);
+        ksum.find(2, target).map(|v| (data[v[0]], data[v[1]]))
     }
 
     /// Find weakness.
@@ -202,26 +404,39 @@ impl XmasScanner {
     /// * `input` - Input string
     /// * `invalid_number` - Invalid number
     pub fn find_weakness(input: &str, invalid_number: usize) -> Option<XmasWeaknessOutput> {
-        let numbers: Vec<usize> = input
-            .lines()
-            .filter_map(|x| x.trim().parse().ok())
-            .collect();
+        Self::find_weakness_in(Self::parse_numbers(input), invalid_number)
+    }
+
+    /// Find weakness in parsed numbers: a contiguous range of at least two
+    /// numbers summing to the invalid number.
+    ///
+    /// Uses a sliding window over prefix sums, which is linear as numbers are
+    /// non-negative.
+    ///
+    /// # Arguments
+    ///
+    /// * `numbers` - Numbers
+    /// * `invalid_number` - Invalid number
+    pub fn find_weakness_in(
+        numbers: Vec<usize>,
+        invalid_number: usize,
+    ) -> Option<XmasWeaknessOutput> {
+        let mut prefix = Vec::with_capacity(numbers.len() + 1);
+        prefix.push(0);
+        for n in &numbers {
+            prefix.push(prefix.last().unwrap() + n);
+        }
 
-        for start_i in 0..numbers.len() {
-            let mut acc = numbers[start_i];
-
-            for end_i in start_i + 1..numbers.len() {
-                acc += numbers[end_i];
-
-                match acc.cmp(&invalid_number) {
-                    Ordering::Equal => {
-                        return Some(XmasWeaknessOutput::new(numbers, start_i, end_i))
-                    }
-                    Ordering::Greater => {
-                        break;
-                    }
-                    Ordering::Less => (),
-                }
+        // Window is numbers[start..end]
+        let (mut start, mut end) = (0, 2);
+        while end <= numbers.len() {
+            let sum = prefix[end] - prefix[start];
+            if end - start < 2 || sum < invalid_number {
+                end += 1;
+            } else if sum > invalid_number {
+                start += 1;
+            } else {
+                return Some(XmasWeaknessOutput::new(numbers, start, end - 1));
             }
         }
 
@@ -262,6 +477,59 @@ mod tests {
         assert_eq!(XmasScanner::parse_and_find_error(SAMPLE, 5), Some(127));
     }
 
+    #[test]
+    fn test_find_errors() {
+        let errors: Vec<InvalidNumber> = XmasScanner::find_errors(SAMPLE, 5).collect();
+        assert_eq!(
+            errors,
+            vec![InvalidNumber {
+                index: 14,
+                value: 127
+            }]
+        );
+
+        let values: Vec<usize> = XmasScanner::find_errors("1\n2\n3\n100\n5\n105\n200", 2)
+            .map(|e| e.index)
+            .collect();
+        assert_eq!(values, vec![3, 4, 6]);
+        assert_eq!(XmasScanner::find_errors(SAMPLE, 0).count(), 20);
+    }
+
+    #[test]
+    fn test_validator() {
+        let mut validator = XmasValidator::new(3);
+        assert_eq!(validator.push(1), Ok(()));
+        assert_eq!(validator.push(2), Ok(()));
+        assert_eq!(validator.push(3), Ok(()));
+        // Pairs must use two distinct entries
+        assert_eq!(validator.push(2), Err(InvalidNumber { index: 3, value: 2 }));
+        assert_eq!(validator.push(5), Ok(()));
+        // 1 left the window
+        assert!(!validator.is_valid(3));
+        assert!(validator.is_valid(7));
+        assert_eq!(validator.len(), 5);
+
+        // Brute-force comparison with equal values in the window
+        let values: Vec<usize> = (0..200).map(|x| (x * 7 + 3) % 13).collect();
+        let mut validator = XmasValidator::new(4);
+        for (idx, &v) in values.iter().enumerate() {
+            let expected = idx < 4 || XmasScanner::find_sum(&values[idx - 4..idx], v).is_some();
+            assert_eq!(validator.push(v).is_ok(), expected, "index {}", idx);
+        }
+    }
+
+    #[test]
+    fn test_validate_reader() {
+        assert_eq!(
+            XmasScanner::validate_reader(SAMPLE.as_bytes(), 5).unwrap(),
+            XmasScanner::find_errors(SAMPLE, 5).collect::<Vec<_>>()
+        );
+        assert!(matches!(
+            XmasScanner::validate_reader("1\n2\nfoo".as_bytes(), 5),
+            Err(DayError::NumberParseError { line: 3, .. })
+        ));
+    }
+
     #[test]
     fn test_find_weakness() {
         let weakness = XmasScanner::find_weakness(SAMPLE, 127).unwrap();
@@ -269,6 +537,10 @@ mod tests {
         assert_eq!(weakness.end_cursor, 5);
         assert_eq!(weakness.get_min_max(), (15, 47));
         assert_eq!(weakness.get_sum(), 62);
+
+        assert!(XmasScanner::find_weakness(SAMPLE, 1).is_none());
+        assert!(XmasScanner::find_weakness_in(vec![1, 2, 3, 10], 5).is_some());
+        assert!(XmasScanner::find_weakness_in(vec![1, 2, 3, 10], 4).is_none());
     }
 
     #[test]
@@ -146,8 +146,9 @@
 //!
 //! What is the total number of distinct ways you can arrange the adapters to connect the charging outlet to your device?
 
+use std::collections::HashSet;
+
 use itertools::Itertools;
-use std::collections::{HashMap, HashSet, VecDeque};
 
 const INPUT_VALUES: &str = include_str!("input.txt");
 
@@ -165,9 +166,60 @@ pub fn run_ex2() -> usize {
     JoltAnalyzer::from_input(INPUT_VALUES).count_adapter_permutations()
 }
 
+/// Allowed joltage differences between two chained adapters.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct GapRules {
+    /// Minimum difference
+    pub min_gap: usize,
+    /// Maximum difference
+    pub max_gap: usize,
+}
+
+impl Default for GapRules {
+    fn default() -> Self {
+        Self {
+            min_gap: 1,
+            max_gap: 3,
+        }
+    }
+}
+
+impl GapRules {
+    /// Check if a difference is allowed.
+    ///
+    /// # Arguments
+    ///
+    /// * `diff` - Joltage difference
+    pub fn allows(&self, diff: usize) -> bool {
+        diff >= self.min_gap && diff <= self.max_gap
+    }
+}
+
+/// Extra constraints on adapter arrangements.
+#[derive(Debug, Clone, Default)]
+pub struct ChainConstraints {
+    /// Adapters which cannot be used, by joltage
+    pub banned: HashSet<usize>,
+    /// Minimum number of adapters used
+    pub min_length: usize,
+    /// Maximum number of adapters used
+    pub max_length: Option<usize>,
+}
+
+impl ChainConstraints {
+    fn has_length_constraint(&self) -> bool {
+        self.min_length > 0 || self.max_length.is_some()
+    }
+
+    fn allows_length(&self, length: usize) -> bool {
+        length >= self.min_length && self.max_length.is_none_or(|max| length <= max)
+    }
+}
+
 /// Jolt analyzer
 pub struct JoltAnalyzer {
     data: Vec<usize>,
+    rules: GapRules,
 }
 
 impl JoltAnalyzer {
@@ -177,6 +229,16 @@ impl JoltAnalyzer {
     ///
     /// * `input` - Input string
     pub fn from_input(input: &str) -> Self {
+        Self::from_input_with_rules(input, GapRules::default())
+    }
+
+    /// Creates analyzer from input string and gap rules.
+    ///
+    /// # Arguments
+    ///
+    /// * `input` - Input string
+    /// * `rules` - Gap rules
+    pub fn from_input_with_rules(input: &str, rules: GapRules) -> Self {
         let mut data: Vec<usize> = input
             .lines()
             .filter_map(|x| x.trim().parse().ok())
@@ -188,7 +250,7 @@ impl JoltAnalyzer {
         // Add device builtin adapter
         data.push(Self::get_builtin_adapter_jolts(&data));
 
-        Self { data }
+        Self { data, rules }
     }
 
     /// Get device builtin adapter jolts.
@@ -196,69 +258,121 @@ impl JoltAnalyzer {
         data.iter().max().unwrap() + 3
     }
 
+    /// Get sorted joltages, including the charging outlet and the device.
+    pub fn adapters(&self) -> &[usize] {
+        &self.data
+    }
+
     /// Determine jolt chain.
+    /// Adapters which cannot follow the previous one are skipped.
     pub fn determine_jolt_chain(&self) -> Vec<(usize, usize)> {
-        let mut remaining: VecDeque<usize> = self.data.clone().into();
-        let mut available = self.data.clone();
-        let mut chain = vec![];
-
-        while let Some(num) = remaining.pop_front() {
-            if let Some((diff, adapter, idx)) =
-                Self::find_smallest_compatible_adapter(&available, num)
-            {
-                chain.push((diff, adapter));
-                available.remove(idx);
+        self.data
+            .iter()
+            .tuple_windows()
+            .filter_map(|(a, b)| {
+                let diff = b - a;
+                if self.rules.allows(diff) {
+                    Some((diff, *b))
+                } else {
+                    None
+                }
+            })
+            .collect()
+    }
+
+    /// Count adapter permutations.
+    pub fn count_adapter_permutations(&self) -> usize {
+        self.count_arrangements(&ChainConstraints::default())
+    }
+
+    /// Count arrangements from the charging outlet to the device under constraints.
+    ///
+    /// Without length constraints, this is a linear dynamic programming pass
+    /// using prefix sums over the window of compatible predecessors.
+    /// Length constraints add a dimension for the number of adapters used.
+    ///
+    /// # Arguments
+    ///
+    /// * `constraints` - Constraints
+    pub fn count_arrangements(&self, constraints: &ChainConstraints) -> usize {
+        let windows = self.predecessor_windows();
+        let last = self.data.len() - 1;
+
+        if !constraints.has_length_constraint() {
+            let mut ways = vec![0; self.data.len()];
+            let mut prefix = vec![0; self.data.len() + 1];
+            ways[0] = 1;
+            prefix[1] = 1;
+
+            for i in 1..self.data.len() {
+                let (lo, hi) = windows[i];
+                if self.is_usable(i, constraints) && lo < hi {
+                    ways[i] = prefix[hi] - prefix[lo];
+                }
+                prefix[i + 1] = prefix[i] + ways[i];
+            }
+
+            return ways[last];
+        }
+
+        // ways[l][i]: chains of `l` adapters from the outlet to `i`
+        let max_length = last.saturating_sub(1);
+        let mut ways = vec![vec![0; self.data.len()]; max_length + 2];
+        let mut prefix = vec![vec![0; self.data.len() + 1]; max_length + 2];
+        ways[0][0] = 1;
+
+        for i in 0..self.data.len() {
+            for l in 0..=max_length + 1 {
+                let (lo, hi) = windows[i];
+                if i > 0 && l > 0 && self.is_usable(i, constraints) && lo < hi {
+                    ways[l][i] = prefix[l - 1][hi] - prefix[l - 1][lo];
+                }
+                prefix[l][i + 1] = prefix[l][i] + ways[l][i];
             }
         }
 
-        chain
+        // The device counts as one step, but not as an adapter
+        (1..=max_length + 1)
+            .filter(|&l| constraints.allows_length(l - 1))
+            .map(|l| ways[l][last])
+            .sum()
     }
 
-    /// Count adapter permutations.
-    pub fn count_adapter_permutations(&self) -> usize {
-        let max_value = self.data.last().unwrap();
-        let data_set: HashSet<usize> = self.data.iter().copied().collect();
-        let mut memory: HashMap<usize, usize> = HashMap::new();
-        Self::count_inner_adapter_permutations(&data_set, 0, *max_value, &mut memory)
+    /// Lazily enumerate arrangements, from the charging outlet to the device.
+    pub fn arrangements(&self) -> Arrangements<'_> {
+        self.arrangements_with(ChainConstraints::default())
     }
 
-    /// Recursively count inner adapter permutations.
+    /// Lazily enumerate arrangements under constraints, from the charging
+    /// outlet to the device.
     ///
     /// # Arguments
     ///
-    /// * `data` - Adapter data set
-    /// * `number` - Base number to search
-    /// * `max_value` - Maximum value to found
-    /// * `memory` - Memory to write permutations for better performance
-    pub fn count_inner_adapter_permutations(
-        data: &HashSet<usize>,
-        number: usize,
-        max_value: usize,
-        memory: &mut HashMap<usize, usize>,
-    ) -> usize {
-        memory.get(&number).cloned().map_or_else(
-            || {
-                let memory_value = {
-                    if number == max_value {
-                        1
-                    } else {
-                        (number + 1..=number + 3)
-                            .filter_map(|i| {
-                                data.get(&i).map(|i| {
-                                    Self::count_inner_adapter_permutations(
-                                        data, *i, max_value, memory,
-                                    )
-                                })
-                            })
-                            .sum()
-                    }
-                };
-
-                memory.insert(number, memory_value);
-                memory_value
-            },
-            |v| v,
-        )
+    /// * `constraints` - Constraints
+    pub fn arrangements_with(&self, constraints: ChainConstraints) -> Arrangements<'_> {
+        let windows = self.successor_windows();
+        let last = self.data.len() - 1;
+
+        // Prune adapters which cannot reach the device
+        let mut reachable = vec![false; self.data.len()];
+        let mut suffix = vec![0; self.data.len() + 1];
+        reachable[last] = true;
+        suffix[last] = 1;
+        for i in (0..last).rev() {
+            let (lo, hi) = windows[i];
+            reachable[i] = self.is_usable(i, &constraints) && lo < hi && suffix[lo] > suffix[hi];
+            suffix[i] = suffix[i + 1] + usize::from(reachable[i]);
+        }
+
+        Arrangements {
+            analyzer: self,
+            constraints,
+            windows,
+            reachable,
+            path: vec![],
+            candidates: vec![],
+            started: false,
+        }
     }
 
     /// Get 1-jolt differences and 3-jolt differences from chain.
@@ -281,31 +395,110 @@ impl JoltAnalyzer {
         (diff1, diff3)
     }
 
-    /// Find smallest compatible adapter.
-    ///
-    /// # Arguments
-    ///
-    /// * `remaining` - Remaining adapters
-    /// * `target` - Jolt target
-    pub fn find_smallest_compatible_adapter(
-        remaining: &[usize],
-        target: usize,
-    ) -> Option<(usize, usize, usize)> {
-        let mut compatible: Vec<(usize, usize, usize)> = remaining
-            .iter()
-            .enumerate()
-            .filter_map(|(idx, x)| {
-                let diff = x.wrapping_sub(target);
-                if diff > 0 && diff <= 3 {
-                    Some((diff, *x, idx))
-                } else {
-                    None
+    fn is_usable(&self, idx: usize, constraints: &ChainConstraints) -> bool {
+        idx == 0 || idx == self.data.len() - 1 || !constraints.banned.contains(&self.data[idx])
+    }
+
+    /// For each adapter, get the `[lo, hi)` index range of compatible predecessors.
+    fn predecessor_windows(&self) -> Vec<(usize, usize)> {
+        let (mut lo, mut hi) = (0, 0);
+        (0..self.data.len())
+            .map(|i| {
+                let value = self.data[i];
+                while lo < i && self.data[lo] + self.rules.max_gap < value {
+                    lo += 1;
                 }
+                while hi < i && self.data[hi] + self.rules.min_gap <= value {
+                    hi += 1;
+                }
+                (lo, hi)
             })
-            .collect();
+            .collect()
+    }
+
+    /// For each adapter, get the `[lo, hi)` index range of compatible successors.
+    fn successor_windows(&self) -> Vec<(usize, usize)> {
+        let (mut lo, mut hi) = (0, 0);
+        (0..self.data.len())
+            .map(|i| {
+                let value = self.data[i];
+                lo = lo.max(i + 1);
+                while lo < self.data.len() && self.data[lo] < value + self.rules.min_gap {
+                    lo += 1;
+                }
+                hi = hi.max(lo);
+                while hi < self.data.len() && self.data[hi] <= value + self.rules.max_gap {
+                    hi += 1;
+                }
+                (lo, hi)
+            })
+            .collect()
+    }
+}
+
+/// Lazy iterator over adapter arrangements.
+///
+/// Arrangements are joltage lists starting with the charging outlet and ending
+/// with the device, yielded in lexicographic order.
+pub struct Arrangements<'a> {
+    analyzer: &'a JoltAnalyzer,
+    constraints: ChainConstraints,
+    windows: Vec<(usize, usize)>,
+    reachable: Vec<bool>,
+    path: Vec<usize>,
+    candidates: Vec<usize>,
+    started: bool,
+}
+
+impl<'a> Iterator for Arrangements<'a> {
+    type Item = Vec<usize>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let last = self.analyzer.data.len() - 1;
+
+        if !self.started {
+            self.started = true;
+            if !self.reachable[0] {
+                return None;
+            }
+            self.path.push(0);
+            self.candidates.push(self.windows[0].0);
+        }
+
+        while let Some(&top) = self.path.last() {
+            // Adapters in path, without the outlet
+            let length = self.path.len() - 1;
+
+            if top == last {
+                let arrangement = self.path.iter().map(|&i| self.analyzer.data[i]).collect();
+                self.path.pop();
+                self.candidates.pop();
+
+                if self.constraints.allows_length(length - 1) {
+                    return Some(arrangement);
+                }
+                continue;
+            }
 
-        compatible.sort_unstable();
-        compatible.first().copied()
+            let too_long = self.constraints.max_length.is_some_and(|max| length > max);
+            let hi = self.windows[top].1;
+            let reachable = &self.reachable;
+            let candidate = self.candidates.last_mut().unwrap();
+
+            match (*candidate..hi).find(|&c| reachable[c]) {
+                Some(next) if !too_long => {
+                    *candidate = next + 1;
+                    self.path.push(next);
+                    self.candidates.push(self.windows[next].0);
+                }
+                _ => {
+                    self.path.pop();
+                    self.candidates.pop();
+                }
+            }
+        }
+
+        None
     }
 }
 
@@ -418,6 +611,84 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_arrangements() {
+        let analyzer = JoltAnalyzer::from_input(SAMPLE1);
+        let arrangements: Vec<Vec<usize>> = analyzer.arrangements().collect();
+
+        assert_eq!(arrangements.len(), 8);
+        assert_eq!(
+            arrangements[0],
+            vec![0, 1, 4, 5, 6, 7, 10, 11, 12, 15, 16, 19, 22]
+        );
+        assert_eq!(arrangements[7], vec![0, 1, 4, 7, 10, 12, 15, 16, 19, 22]);
+        assert_eq!(
+            JoltAnalyzer::from_input(SAMPLE2).arrangements().count(),
+            19208
+        );
+    }
+
+    #[test]
+    fn test_gap_rules() {
+        let rules = GapRules {
+            min_gap: 1,
+            max_gap: 4,
+        };
+        let analyzer = JoltAnalyzer::from_input_with_rules(SAMPLE1, rules);
+        assert_eq!(
+            analyzer.count_adapter_permutations(),
+            analyzer.arrangements().count()
+        );
+        assert!(analyzer.count_adapter_permutations() > 8);
+
+        let rules = GapRules {
+            min_gap: 1,
+            max_gap: 2,
+        };
+        let analyzer = JoltAnalyzer::from_input_with_rules(SAMPLE1, rules);
+        assert_eq!(analyzer.count_adapter_permutations(), 0);
+        assert_eq!(analyzer.arrangements().next(), None);
+        assert_eq!(analyzer.determine_jolt_chain().len(), 7);
+    }
+
+    #[test]
+    fn test_count_arrangements_with_constraints() {
+        let analyzer = JoltAnalyzer::from_input(SAMPLE2);
+        let all: Vec<Vec<usize>> = analyzer.arrangements().collect();
+
+        let mut constraints = ChainConstraints::default();
+        constraints.banned.insert(11);
+        constraints.banned.insert(33);
+        let expected = all
+            .iter()
+            .filter(|a| !a.contains(&11) && !a.contains(&33))
+            .count();
+        assert_eq!(analyzer.count_arrangements(&constraints), expected);
+        assert_eq!(analyzer.arrangements_with(constraints).count(), expected);
+
+        for (min_length, max_length) in &[(20, None), (25, Some(27)), (0, Some(20))] {
+            let constraints = ChainConstraints {
+                min_length: *min_length,
+                max_length: *max_length,
+                ..ChainConstraints::default()
+            };
+            let expected = all
+                .iter()
+                .filter(|a| constraints.allows_length(a.len() - 2))
+                .count();
+            assert_eq!(analyzer.count_arrangements(&constraints), expected);
+            assert_eq!(analyzer.arrangements_with(constraints).count(), expected);
+        }
+
+        // Banning a mandatory adapter
+        let mut constraints = ChainConstraints::default();
+        constraints.banned.insert(4);
+        assert_eq!(
+            JoltAnalyzer::from_input(SAMPLE1).count_arrangements(&constraints),
+            0
+        );
+    }
+
     #[test]
     fn test_run_ex1() {
         assert_eq!(run_ex1(), EX1_OUTPUT);
@@ -0,0 +1,214 @@
+//! Precomputed seat neighbourhood graph
+
+#[cfg(feature = "parallel")]
+use rayon::prelude::*;
+
+use super::{Neighbourhood, SeatLayout, SeatState, SeatingRules, DIRECTIONS};
+
+/// Minimum seat count before the parallel step is used.
+#[cfg(feature = "parallel")]
+pub const PARALLEL_MIN_SEATS: usize = 16_384;
+
+/// Seat neighbourhood graph.
+///
+/// Seats are indexed in reading order, and the neighbours of each seat are
+/// stored in a single compact adjacency list, so stepping never looks at
+/// floor positions again.
+#[derive(Debug, Clone)]
+pub struct SeatGraph {
+    rules: SeatingRules,
+    positions: Vec<(usize, usize)>,
+    offsets: Vec<usize>,
+    neighbours: Vec<u32>,
+}
+
+impl SeatGraph {
+    /// Build graph from layout and rules.
+    ///
+    /// # Arguments
+    ///
+    /// * `layout` - Seat layout
+    /// * `rules` - Seating rules
+    pub fn new(layout: &SeatLayout, rules: &SeatingRules) -> Self {
+        let (w, h) = layout.get_size();
+        let mut index = vec![None; w * h];
+        let mut positions = vec![];
+        for y in 0..h {
+            for x in 0..w {
+                if layout.get_seat_state_at_position(x, y) != Some(SeatState::Floor) {
+                    index[y * w + x] = Some(positions.len() as u32);
+                    positions.push((x, y));
+                }
+            }
+        }
+
+        let seat_at = |pos: Option<(usize, usize)>| {
+            pos.filter(|&(x, y)| x < w && y < h)
+                .and_then(|(x, y)| index[y * w + x])
+        };
+
+        let mut offsets = Vec::with_capacity(positions.len() + 1);
+        let mut neighbours = vec![];
+        offsets.push(0);
+        for &(x, y) in &positions {
+            match rules.neighbourhood {
+                Neighbourhood::Adjacent => neighbours.extend(
+                    DIRECTIONS
+                        .iter()
+                        .filter_map(|&(dx, dy)| seat_at(SeatLayout::offset_position(x, y, dx, dy))),
+                ),
+                Neighbourhood::LineOfSight => {
+                    neighbours.extend(DIRECTIONS.iter().filter_map(|&(dx, dy)| {
+                        seat_at(layout.find_visible_seat(x, y, dx, dy, rules.see_through_floor))
+                    }))
+                }
+                Neighbourhood::Radius(r) => {
+                    let r = r as isize;
+                    for dy in -r..=r {
+                        for dx in -r..=r {
+                            if (dx, dy) != (0, 0) {
+                                neighbours
+                                    .extend(seat_at(SeatLayout::offset_position(x, y, dx, dy)));
+                            }
+                        }
+                    }
+                }
+            }
+            offsets.push(neighbours.len());
+        }
+
+        Self {
+            rules: *rules,
+            positions,
+            offsets,
+            neighbours,
+        }
+    }
+
+    /// Get rules used to build the graph.
+    pub fn rules(&self) -> &SeatingRules {
+        &self.rules
+    }
+
+    /// Get seat count.
+    pub fn len(&self) -> usize {
+        self.positions.len()
+    }
+
+    /// Check if there is no seat.
+    pub fn is_empty(&self) -> bool {
+        self.positions.is_empty()
+    }
+
+    /// Get seat positions, by seat index.
+    pub fn positions(&self) -> &[(usize, usize)] {
+        &self.positions
+    }
+
+    /// Get neighbour seat indices of a seat.
+    ///
+    /// # Arguments
+    ///
+    /// * `seat` - Seat index
+    pub fn neighbours(&self, seat: usize) -> &[u32] {
+        &self.neighbours[self.offsets[seat]..self.offsets[seat + 1]]
+    }
+
+    /// Compute next occupation state of every seat.
+    ///
+    /// # Arguments
+    ///
+    /// * `current` - Current occupation, by seat index
+    /// * `next` - Next occupation, by seat index
+    pub fn step(&self, current: &[bool], next: &mut [bool]) {
+        for (seat, state) in next.iter_mut().enumerate() {
+            *state = self.next_state(seat, current);
+        }
+    }
+
+    /// Compute next occupation state of every seat, using multiple threads.
+    ///
+    /// # Arguments
+    ///
+    /// * `current` - Current occupation, by seat index
+    /// * `next` - Next occupation, by seat index
+    #[cfg(feature = "parallel")]
+    pub fn par_step(&self, current: &[bool], next: &mut [bool]) {
+        next.par_iter_mut()
+            .enumerate()
+            .for_each(|(seat, state)| *state = self.next_state(seat, current));
+    }
+
+    fn next_state(&self, seat: usize, current: &[bool]) -> bool {
+        let occupied = self
+            .neighbours(seat)
+            .iter()
+            .filter(|&&n| current[n as usize])
+            .count();
+
+        if current[seat] {
+            occupied < self.rules.tolerance
+        } else {
+            occupied == 0
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_graph_matches_ray_casting() {
+        let layout = SeatLayout::generate(60, 40, 0.6, 42);
+        let rules = [
+            SeatingRules::adjacent(),
+            SeatingRules::line_of_sight(),
+            SeatingRules {
+                neighbourhood: Neighbourhood::Radius(2),
+                tolerance: 8,
+                see_through_floor: false,
+            },
+        ];
+
+        for rule in &rules {
+            let graph = SeatGraph::new(&layout, rule);
+            assert_eq!(graph.len(), layout.stats().total_seats);
+
+            let mut current = vec![false; graph.len()];
+            let mut next = vec![false; graph.len()];
+            graph.step(&current, &mut next);
+            assert!(next.iter().all(|&s| s));
+
+            // Compare neighbour counts on a half-occupied layout
+            for (seat, state) in current.iter_mut().enumerate() {
+                *state = seat % 2 == 0;
+            }
+            let mut expected = SeatLayout::generate(60, 40, 0.6, 42);
+            expected.set_occupation(&graph, &current);
+
+            for (seat, &(x, y)) in graph.positions().iter().enumerate() {
+                let count = graph
+                    .neighbours(seat)
+                    .iter()
+                    .filter(|&&n| current[n as usize])
+                    .count();
+                assert_eq!(count, expected.count_occupied_seats(x, y, rule));
+            }
+        }
+    }
+
+    #[cfg(feature = "parallel")]
+    #[test]
+    fn test_par_step() {
+        let layout = SeatLayout::generate(200, 200, 0.7, 7);
+        let graph = SeatGraph::new(&layout, &SeatingRules::line_of_sight());
+        let current: Vec<bool> = (0..graph.len()).map(|i| i % 3 == 0).collect();
+
+        let mut sequential = vec![false; graph.len()];
+        let mut parallel = vec![false; graph.len()];
+        graph.step(&current, &mut sequential);
+        graph.par_step(&current, &mut parallel);
+        assert_eq!(sequential, parallel);
+    }
+}
@@ -242,6 +242,16 @@
 #![allow(clippy::cast_sign_loss)]
 #![allow(clippy::cast_possible_wrap)]
 
+use std::collections::HashMap;
+
+use thiserror::Error;
+
+mod graph;
+mod recorder;
+
+pub use self::graph::SeatGraph;
+pub use self::recorder::{Frame, FrameRecorder};
+
 const INPUT_VALUES: &str = include_str!("input.txt");
 
 /// Part one answer.
@@ -258,8 +268,28 @@ pub fn run_ex2() -> usize {
         .occupied_seats
 }
 
+/// Day error.
+#[derive(Debug, Error)]
+pub enum DayError {
+    /// Frame is too large for the output format
+    #[error("Frame is too large: {0}x{1}")]
+    FrameTooLarge(usize, usize),
+    /// Recording has no frame
+    #[error("Recording has no frame")]
+    EmptyRecording,
+    /// I/O error
+    #[error("I/O error: {0}")]
+    IoError(#[from] std::io::Error),
+    /// PNG encoding error
+    #[error("PNG encoding error: {0}")]
+    PngError(#[from] png::EncodingError),
+    /// GIF encoding error
+    #[error("GIF encoding error: {0}")]
+    GifError(#[from] gif::EncodingError),
+}
+
 /// Seat state
-#[derive(Debug, Copy, Clone)]
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
 pub enum SeatState {
     /// No seat
     Floor,
@@ -294,6 +324,49 @@ impl SeatState {
     }
 }
 
+/// Which seats a seat takes into account
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Neighbourhood {
+    /// The 8 adjacent positions
+    Adjacent,
+    /// First seat in each of the 8 directions
+    LineOfSight,
+    /// Every position at a Chebyshev distance of at most `r`
+    Radius(usize),
+}
+
+/// Seating rules
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct SeatingRules {
+    /// Neighbourhood kind
+    pub neighbourhood: Neighbourhood,
+    /// Occupied seats are freed when at least this many neighbours are occupied
+    pub tolerance: usize,
+    /// Line of sight goes through floor; when disabled, sight stops at the
+    /// first position in each direction
+    pub see_through_floor: bool,
+}
+
+impl SeatingRules {
+    /// Part one rules: adjacent seats, tolerance of 4.
+    pub const fn adjacent() -> Self {
+        Self {
+            neighbourhood: Neighbourhood::Adjacent,
+            tolerance: 4,
+            see_through_floor: true,
+        }
+    }
+
+    /// Part two rules: visible seats, tolerance of 5.
+    pub const fn line_of_sight() -> Self {
+        Self {
+            neighbourhood: Neighbourhood::LineOfSight,
+            tolerance: 5,
+            see_through_floor: true,
+        }
+    }
+}
+
 /// Seat layout stats
 #[derive(Debug, Clone, Eq, PartialEq)]
 pub struct SeatLayoutStats {
@@ -316,11 +389,52 @@ impl SeatLayoutStats {
     }
 }
 
+/// Simulation outcome
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub enum SimulationOutcome {
+    /// Layout reached a fixed point
+    Stable {
+        /// Steps until the fixed point was first reached
+        steps: usize,
+        /// Final stats
+        stats: SeatLayoutStats,
+    },
+    /// Layout oscillates between several states
+    Oscillating {
+        /// Step where the cycle starts
+        start: usize,
+        /// Cycle length
+        period: usize,
+        /// Stats when the cycle was detected
+        stats: SeatLayoutStats,
+    },
+}
+
+impl SimulationOutcome {
+    /// Get final stats.
+    pub fn stats(&self) -> &SeatLayoutStats {
+        match self {
+            Self::Stable { stats, .. } | Self::Oscillating { stats, .. } => stats,
+        }
+    }
+}
+
+const DIRECTIONS: [(isize, isize); 8] = [
+    (-1, -1),
+    (0, -1),
+    (1, -1),
+    (-1, 0),
+    (1, 0),
+    (-1, 1),
+    (0, 1),
+    (1, 1),
+];
+
 /// Seat layout
 #[derive(Debug)]
 pub struct SeatLayout {
-    frontbuffer: Vec<Vec<SeatState>>,
-    backbuffer: Vec<Vec<SeatState>>,
+    grid: Vec<Vec<SeatState>>,
+    graph: Option<SeatGraph>,
 }
 
 impl SeatLayout {
@@ -341,35 +455,80 @@ impl SeatLayout {
             .collect::<Vec<Vec<SeatState>>>();
 
         Self {
-            frontbuffer: data.clone(),
-            backbuffer: data,
+            grid: data,
+            graph: None,
+        }
+    }
+
+    /// Generate a random layout of free seats.
+    ///
+    /// # Arguments
+    ///
+    /// * `width` - Width
+    /// * `height` - Height
+    /// * `seat_ratio` - Probability for a position to be a seat
+    /// * `seed` - Random seed
+    pub fn generate(width: usize, height: usize, seat_ratio: f64, seed: u64) -> Self {
+        // Xorshift, good enough for layouts
+        let mut state = seed.max(1);
+        let mut next_random = move || {
+            state ^= state << 13;
+            state ^= state >> 7;
+            state ^= state << 17;
+            (state >> 11) as f64 / (1_u64 << 53) as f64
+        };
+
+        let data: Vec<Vec<SeatState>> = (0..height)
+            .map(|_| {
+                (0..width)
+                    .map(|_| {
+                        if next_random() < seat_ratio {
+                            SeatState::Free
+                        } else {
+                            SeatState::Floor
+                        }
+                    })
+                    .collect()
+            })
+            .collect();
+
+        Self {
+            grid: data,
+            graph: None,
         }
     }
 
     /// Get layout size
     pub fn get_size(&self) -> (usize, usize) {
-        (self.frontbuffer[0].len(), self.frontbuffer.len())
+        (self.grid[0].len(), self.grid.len())
     }
 
-    /// Update state using neighbors.
+    /// Update state using rules.
     ///
     /// # Arguments
     ///
     /// * `x` - X position
     /// * `y` - Y position
     /// * `old_state` - Old seat state
-    fn update_state_from_neighbors(&self, x: usize, y: usize, old_state: SeatState) -> SeatState {
+    /// * `rules` - Seating rules
+    pub fn update_state(
+        &self,
+        x: usize,
+        y: usize,
+        old_state: SeatState,
+        rules: &SeatingRules,
+    ) -> SeatState {
         match old_state {
             SeatState::Floor => old_state,
             SeatState::Free => {
-                if self.count_neighbors_occupied_seats(x, y) == 0 {
+                if self.count_occupied_seats(x, y, rules) == 0 {
                     SeatState::Occupied
                 } else {
                     old_state
                 }
             }
             SeatState::Occupied => {
-                if self.count_neighbors_occupied_seats(x, y) >= 4 {
+                if self.count_occupied_seats(x, y, rules) >= rules.tolerance {
                     SeatState::Free
                 } else {
                     old_state
@@ -391,157 +550,215 @@ impl SeatLayout {
         y: usize,
         old_state: SeatState,
     ) -> SeatState {
-        match old_state {
-            SeatState::Floor => old_state,
-            SeatState::Free => {
-                if self.count_visible_occupied_seats(x, y) == 0 {
-                    SeatState::Occupied
-                } else {
-                    old_state
-                }
-            }
-            SeatState::Occupied => {
-                if self.count_visible_occupied_seats(x, y) >= 5 {
-                    SeatState::Free
-                } else {
-                    old_state
-                }
-            }
-        }
+        self.update_state(x, y, old_state, &SeatingRules::line_of_sight())
     }
 
-    /// Swap front and back buffers.
-    pub fn swap_buffers(&mut self) {
-        let (w, h) = self.get_size();
+    /// Step simulation.
+    pub fn step(&mut self) -> SeatLayoutStats {
+        self.step_with_rules(&SeatingRules::adjacent())
+    }
 
-        for j in 0..h {
-            for i in 0..w {
-                self.frontbuffer[j][i] = self.backbuffer[j][i];
-            }
-        }
+    /// Step simulation with visibility check.
+    pub fn step_with_visibility(&mut self) -> SeatLayoutStats {
+        self.step_with_rules(&SeatingRules::line_of_sight())
     }
 
-    /// Step simulation.
-    pub fn step(&mut self) -> SeatLayoutStats {
-        let mut total_seats = 0;
-        let mut free_seats = 0;
-        let mut occupied_seats = 0;
+    /// Step simulation with custom rules.
+    ///
+    /// # Arguments
+    ///
+    /// * `rules` - Seating rules
+    pub fn step_with_rules(&mut self, rules: &SeatingRules) -> SeatLayoutStats {
+        if self.graph.as_ref().is_none_or(|g| g.rules() != rules) {
+            self.graph = Some(SeatGraph::new(self, rules));
+        }
 
-        let (w, h) = self.get_size();
+        let graph = self.graph.take().unwrap();
+        let current = self.occupation(&graph);
+        let mut next = vec![false; graph.len()];
 
-        for j in 0..h {
-            for i in 0..w {
-                let old_state = self.frontbuffer[j][i];
-                let new_state = self.update_state_from_neighbors(i, j, old_state);
-                self.backbuffer[j][i] = new_state;
-
-                match new_state {
-                    SeatState::Floor => (),
-                    SeatState::Free => {
-                        total_seats += 1;
-                        free_seats += 1;
-                    }
-                    SeatState::Occupied => {
-                        total_seats += 1;
-                        occupied_seats += 1;
-                    }
-                }
+        #[cfg(feature = "parallel")]
+        {
+            if graph.len() >= graph::PARALLEL_MIN_SEATS {
+                graph.par_step(&current, &mut next);
+            } else {
+                graph.step(&current, &mut next);
             }
         }
+        #[cfg(not(feature = "parallel"))]
+        graph.step(&current, &mut next);
 
-        self.swap_buffers();
+        self.set_occupation(&graph, &next);
+        self.graph = Some(graph);
+        self.stats()
+    }
 
-        SeatLayoutStats {
-            total_seats,
-            free_seats,
-            occupied_seats,
-        }
+    /// Get seat occupation, indexed like the graph seats.
+    ///
+    /// # Arguments
+    ///
+    /// * `graph` - Seat graph built from this layout
+    pub fn occupation(&self, graph: &SeatGraph) -> Vec<bool> {
+        graph
+            .positions()
+            .iter()
+            .map(|&(x, y)| self.grid[y][x] == SeatState::Occupied)
+            .collect()
     }
 
-    /// Step simulation with visibility check.
-    pub fn step_with_visibility(&mut self) -> SeatLayoutStats {
-        let mut total_seats = 0;
-        let mut free_seats = 0;
-        let mut occupied_seats = 0;
+    /// Set seat occupation, indexed like the graph seats.
+    ///
+    /// # Arguments
+    ///
+    /// * `graph` - Seat graph built from this layout
+    /// * `occupation` - Occupation, by seat index
+    pub fn set_occupation(&mut self, graph: &SeatGraph, occupation: &[bool]) {
+        for (&(x, y), &occupied) in graph.positions().iter().zip(occupation) {
+            self.grid[y][x] = if occupied {
+                SeatState::Occupied
+            } else {
+                SeatState::Free
+            };
+        }
+    }
 
-        let (w, h) = self.get_size();
+    /// Compute current stats.
+    pub fn stats(&self) -> SeatLayoutStats {
+        let mut stats = SeatLayoutStats::new_empty();
 
-        for j in 0..h {
-            for i in 0..w {
-                let old_state = self.frontbuffer[j][i];
-                let new_state = self.update_state_from_visibility(i, j, old_state);
-                self.backbuffer[j][i] = new_state;
-
-                match new_state {
-                    SeatState::Floor => (),
-                    SeatState::Free => {
-                        total_seats += 1;
-                        free_seats += 1;
-                    }
-                    SeatState::Occupied => {
-                        total_seats += 1;
-                        occupied_seats += 1;
-                    }
+        for state in self.grid.iter().flatten() {
+            match state {
+                SeatState::Floor => (),
+                SeatState::Free => {
+                    stats.total_seats += 1;
+                    stats.free_seats += 1;
+                }
+                SeatState::Occupied => {
+                    stats.total_seats += 1;
+                    stats.occupied_seats += 1;
                 }
             }
         }
 
-        self.swap_buffers();
-
-        SeatLayoutStats {
-            total_seats,
-            free_seats,
-            occupied_seats,
-        }
+        stats
     }
 
     /// Run steps until the simulation is stable.
+    /// Also stops if the simulation oscillates.
     pub fn run_until_stable(&mut self) -> SeatLayoutStats {
-        let mut last_stats = SeatLayoutStats::new_empty();
-
-        loop {
-            let new_stats = self.step();
-            if new_stats == last_stats {
-                return new_stats;
-            }
-            last_stats = new_stats;
-        }
+        self.run_with_rules(&SeatingRules::adjacent())
+            .stats()
+            .clone()
     }
 
     /// Run steps with visibility until the simulation is stable.
+    /// Also stops if the simulation oscillates.
     pub fn run_with_visibility_until_stable(&mut self) -> SeatLayoutStats {
-        let mut last_stats = SeatLayoutStats::new_empty();
+        self.run_with_rules(&SeatingRules::line_of_sight())
+            .stats()
+            .clone()
+    }
 
-        loop {
-            let new_stats = self.step_with_visibility();
-            if new_stats == last_stats {
-                return new_stats;
+    /// Run steps with custom rules until the simulation reaches a fixed point
+    /// or a cycle.
+    ///
+    /// Each visited state is hashed, so a cycle is detected as soon as a state
+    /// is seen twice.
+    ///
+    /// # Arguments
+    ///
+    /// * `rules` - Seating rules
+    pub fn run_with_rules(&mut self, rules: &SeatingRules) -> SimulationOutcome {
+        self.run_with_observer(rules, |_| ())
+    }
+
+    /// Run steps with custom rules until the simulation reaches a fixed point
+    /// or a cycle, calling `observer` on the initial layout and after each step
+    /// leading to a new state.
+    ///
+    /// # Arguments
+    ///
+    /// * `rules` - Seating rules
+    /// * `observer` - Observer
+    pub fn run_with_observer<F>(
+        &mut self,
+        rules: &SeatingRules,
+        mut observer: F,
+    ) -> SimulationOutcome
+    where
+        F: FnMut(&Self),
+    {
+        let mut seen = HashMap::new();
+        seen.insert(self.occupied_key(), 0);
+        observer(self);
+
+        for step in 1.. {
+            let stats = self.step_with_rules(rules);
+            if let Some(start) = seen.insert(self.occupied_key(), step) {
+                let period = step - start;
+                return if period == 1 {
+                    SimulationOutcome::Stable {
+                        steps: start,
+                        stats,
+                    }
+                } else {
+                    SimulationOutcome::Oscillating {
+                        start,
+                        period,
+                        stats,
+                    }
+                };
             }
-            last_stats = new_stats;
+            observer(self);
         }
+
+        unreachable!()
     }
 
-    /// Count neighbors occupied seats for position.
+    /// Count occupied seats around position, using rules.
     ///
     /// # Arguments
     ///
     /// * `x` - X position
     /// * `y` - Y position
-    pub fn count_neighbors_occupied_seats(&self, x: usize, y: usize) -> usize {
-        let mut count = 0;
-
-        for i in -1..=1 {
-            for j in -1..=1 {
-                if i != 0 || j != 0 {
-                    let (nx, ny) = ((x as isize + i) as usize, (y as isize + j) as usize);
-                    if let Some(SeatState::Occupied) = self.get_seat_state_at_position(nx, ny) {
-                        count += 1;
-                    }
-                }
+    /// * `rules` - Seating rules
+    pub fn count_occupied_seats(&self, x: usize, y: usize, rules: &SeatingRules) -> usize {
+        let is_occupied = |pos: Option<(usize, usize)>| {
+            pos.and_then(|(nx, ny)| self.get_seat_state_at_position(nx, ny))
+                == Some(SeatState::Occupied)
+        };
+
+        match rules.neighbourhood {
+            Neighbourhood::Adjacent => DIRECTIONS
+                .iter()
+                .filter(|&&(dx, dy)| is_occupied(Self::offset_position(x, y, dx, dy)))
+                .count(),
+            Neighbourhood::LineOfSight => DIRECTIONS
+                .iter()
+                .filter(|&&(dx, dy)| {
+                    is_occupied(self.find_visible_seat(x, y, dx, dy, rules.see_through_floor))
+                })
+                .count(),
+            Neighbourhood::Radius(r) => {
+                let r = r as isize;
+                (-r..=r)
+                    .flat_map(|dy| (-r..=r).map(move |dx| (dx, dy)))
+                    .filter(|&(dx, dy)| {
+                        (dx, dy) != (0, 0) && is_occupied(Self::offset_position(x, y, dx, dy))
+                    })
+                    .count()
             }
         }
+    }
 
-        count
+    /// Count neighbors occupied seats for position.
+    ///
+    /// # Arguments
+    ///
+    /// * `x` - X position
+    /// * `y` - Y position
+    pub fn count_neighbors_occupied_seats(&self, x: usize, y: usize) -> usize {
+        self.count_occupied_seats(x, y, &SeatingRules::adjacent())
     }
 
     /// Count visible occupied seats for position.
@@ -551,36 +768,38 @@ impl SeatLayout {
     /// * `x` - X position
     /// * `y` - Y position
     pub fn count_visible_occupied_seats(&self, x: usize, y: usize) -> usize {
-        let mut count = 0;
-
-        for i in -1..=1 {
-            for j in -1..=1 {
-                if i != 0 || j != 0 {
-                    let (mut nx, mut ny) = ((x as isize + i) as usize, (y as isize + j) as usize);
-                    loop {
-                        match self.get_seat_state_at_position(nx, ny) {
-                            Some(SeatState::Occupied) => {
-                                // Stop scan and count
-                                count += 1;
-                                break;
-                            }
-                            Some(SeatState::Floor) => {
-                                // Continue to scan
-                            }
-                            None | Some(SeatState::Free) => {
-                                // Stop scan
-                                break;
-                            }
-                        }
+        self.count_occupied_seats(x, y, &SeatingRules::line_of_sight())
+    }
 
-                        nx = (nx as isize + i) as usize;
-                        ny = (ny as isize + j) as usize;
-                    }
+    /// Find the first seat visible from position in a direction.
+    ///
+    /// # Arguments
+    ///
+    /// * `x` - X position
+    /// * `y` - Y position
+    /// * `dx` - X direction
+    /// * `dy` - Y direction
+    /// * `see_through_floor` - Continue through floor positions
+    pub fn find_visible_seat(
+        &self,
+        x: usize,
+        y: usize,
+        dx: isize,
+        dy: isize,
+        see_through_floor: bool,
+    ) -> Option<(usize, usize)> {
+        let (mut nx, mut ny) = Self::offset_position(x, y, dx, dy)?;
+        loop {
+            match self.get_seat_state_at_position(nx, ny)? {
+                SeatState::Floor if see_through_floor => {
+                    let next = Self::offset_position(nx, ny, dx, dy)?;
+                    nx = next.0;
+                    ny = next.1;
                 }
+                SeatState::Floor => return None,
+                _ => return Some((nx, ny)),
             }
         }
-
-        count
     }
 
     /// Get seat state at position.
@@ -592,7 +811,7 @@ impl SeatLayout {
     pub fn get_seat_state_at_position(&self, x: usize, y: usize) -> Option<SeatState> {
         let (w, h) = self.get_size();
         if x < w && y < h {
-            Some(self.frontbuffer[y][x])
+            Some(self.grid[y][x])
         } else {
             None
         }
@@ -606,7 +825,7 @@ impl SeatLayout {
 
         for j in 0..h {
             for i in 0..w {
-                let c = self.frontbuffer[j][i];
+                let c = self.grid[j][i];
                 output.push(c.to_char());
             }
             output.push('\n');
@@ -619,6 +838,30 @@ impl SeatLayout {
     pub fn show(&self) {
         println!("{}", self.write_to_string());
     }
+
+    /// Compact occupied seats bitset, used as state key.
+    fn occupied_key(&self) -> Vec<u64> {
+        let mut key = vec![];
+        for (idx, state) in self.grid.iter().flatten().enumerate() {
+            if idx % 64 == 0 {
+                key.push(0);
+            }
+            if *state == SeatState::Occupied {
+                *key.last_mut().unwrap() |= 1 << (idx % 64);
+            }
+        }
+
+        key
+    }
+
+    fn offset_position(x: usize, y: usize, dx: isize, dy: isize) -> Option<(usize, usize)> {
+        let (nx, ny) = (x as isize + dx, y as isize + dy);
+        if nx < 0 || ny < 0 {
+            None
+        } else {
+            Some((nx as usize, ny as usize))
+        }
+    }
 }
 
 #[cfg(test)]
@@ -711,6 +954,56 @@ mod tests {
         assert_eq!(layout.count_visible_occupied_seats(3, 3), 0);
     }
 
+    #[test]
+    fn test_custom_rules() {
+        let mut layout = SeatLayout::from_input(SAMPLE_LAYOUT);
+        assert_eq!(
+            layout.run_with_rules(&SeatingRules::adjacent()),
+            SimulationOutcome::Stable {
+                steps: 5,
+                stats: SeatLayoutStats {
+                    total_seats: 71,
+                    free_seats: 34,
+                    occupied_seats: 37
+                }
+            }
+        );
+
+        let layout = SeatLayout::from_input(SAMPLE_SCAN_2);
+        let blind = SeatingRules {
+            see_through_floor: false,
+            ..SeatingRules::line_of_sight()
+        };
+        assert_eq!(layout.count_occupied_seats(3, 1, &blind), 0);
+        assert_eq!(layout.find_visible_seat(3, 1, 1, 0, true), Some((5, 1)));
+        assert_eq!(layout.find_visible_seat(3, 1, 1, 0, false), None);
+
+        let layout = SeatLayout::from_input(SAMPLE_SCAN_3);
+        let radius = |r| SeatingRules {
+            neighbourhood: Neighbourhood::Radius(r),
+            ..SeatingRules::adjacent()
+        };
+        assert_eq!(layout.count_occupied_seats(3, 3, &radius(1)), 0);
+        assert_eq!(layout.count_occupied_seats(3, 3, &radius(2)), 8);
+        assert_eq!(layout.count_occupied_seats(3, 3, &radius(3)), 24);
+    }
+
+    #[test]
+    fn test_oscillation() {
+        let rules = SeatingRules {
+            tolerance: 1,
+            ..SeatingRules::adjacent()
+        };
+        let mut layout = SeatLayout::from_input("LL.\n...");
+
+        match layout.run_with_rules(&rules) {
+            SimulationOutcome::Oscillating { start, period, .. } => {
+                assert_eq!((start, period), (0, 2));
+            }
+            outcome => panic!("Unexpected outcome: {:?}", outcome),
+        }
+    }
+
     #[test]
     fn test_run_ex1() {
         assert_eq!(run_ex1(), EX1_OUTPUT);
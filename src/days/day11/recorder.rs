@@ -0,0 +1,358 @@
+//! Seat layout frame recording and export
+
+use std::{
+    borrow::Cow,
+    fs::File,
+    io::{BufWriter, Write},
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+use super::{DayError, SeatLayout, SeatState, SeatingRules, SimulationOutcome};
+
+/// Colors used for image output, as RGB.
+const FLOOR_COLOR: [u8; 3] = [32, 32, 40];
+const FREE_COLOR: [u8; 3] = [70, 190, 110];
+const OCCUPIED_COLOR: [u8; 3] = [220, 60, 60];
+
+/// Image palette, indexed with `palette_index`.
+const PALETTE: [[u8; 3]; 3] = [FLOOR_COLOR, FREE_COLOR, OCCUPIED_COLOR];
+
+/// Recorded layout state.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Frame {
+    width: usize,
+    height: usize,
+    cells: Vec<SeatState>,
+}
+
+impl Frame {
+    /// Capture frame from layout.
+    ///
+    /// # Arguments
+    ///
+    /// * `layout` - Seat layout
+    pub fn capture(layout: &SeatLayout) -> Self {
+        let (width, height) = layout.get_size();
+        let cells = (0..height)
+            .flat_map(|y| (0..width).map(move |x| (x, y)))
+            .filter_map(|(x, y)| layout.get_seat_state_at_position(x, y))
+            .collect();
+
+        Self {
+            width,
+            height,
+            cells,
+        }
+    }
+
+    /// Get frame size.
+    pub fn size(&self) -> (usize, usize) {
+        (self.width, self.height)
+    }
+
+    /// Render frame with ANSI colors.
+    pub fn to_ansi(&self) -> String {
+        let mut output = String::new();
+        for row in self.cells.chunks(self.width) {
+            for cell in row {
+                let color = match cell {
+                    SeatState::Floor => "90",
+                    SeatState::Free => "32",
+                    SeatState::Occupied => "31",
+                };
+                output.push_str(&format!("\x1b[{}m{}", color, cell.to_char()));
+            }
+            output.push_str("\x1b[0m\n");
+        }
+
+        output
+    }
+
+    /// Render frame to RGB pixels, each cell being a `scale` pixels square.
+    ///
+    /// # Arguments
+    ///
+    /// * `scale` - Cell size in pixels
+    pub fn to_rgb(&self, scale: usize) -> Vec<u8> {
+        self.to_indexed(scale)
+            .into_iter()
+            .flat_map(|idx| PALETTE[usize::from(idx)])
+            .collect()
+    }
+
+    /// Render frame to palette indices, each cell being a `scale` pixels square.
+    fn to_indexed(&self, scale: usize) -> Vec<u8> {
+        let mut pixels = Vec::with_capacity(self.cells.len() * scale * scale);
+        for row in self.cells.chunks(self.width) {
+            let line: Vec<u8> = row
+                .iter()
+                .flat_map(|&cell| std::iter::repeat_n(palette_index(cell), scale))
+                .collect();
+            for _ in 0..scale {
+                pixels.extend_from_slice(&line);
+            }
+        }
+
+        pixels
+    }
+}
+
+fn palette_index(state: SeatState) -> u8 {
+    match state {
+        SeatState::Floor => 0,
+        SeatState::Free => 1,
+        SeatState::Occupied => 2,
+    }
+}
+
+/// Simulation frame recorder.
+pub struct FrameRecorder {
+    frames: Vec<Frame>,
+    outcome: SimulationOutcome,
+}
+
+impl FrameRecorder {
+    /// Run simulation until stability or oscillation, recording every distinct state.
+    ///
+    /// # Arguments
+    ///
+    /// * `layout` - Seat layout
+    /// * `rules` - Seating rules
+    pub fn record(layout: &mut SeatLayout, rules: &SeatingRules) -> Self {
+        let mut frames = vec![];
+        let outcome = layout.run_with_observer(rules, |l| frames.push(Frame::capture(l)));
+
+        Self { frames, outcome }
+    }
+
+    /// Get recorded frames.
+    pub fn frames(&self) -> &[Frame] {
+        &self.frames
+    }
+
+    /// Get simulation outcome.
+    pub fn outcome(&self) -> &SimulationOutcome {
+        &self.outcome
+    }
+
+    /// Play frames as a terminal animation.
+    ///
+    /// # Arguments
+    ///
+    /// * `output` - Output
+    /// * `delay` - Delay between frames
+    pub fn play_ansi<W: Write>(&self, mut output: W, delay: Duration) -> Result<(), DayError> {
+        for (idx, frame) in self.frames.iter().enumerate() {
+            if idx > 0 {
+                std::thread::sleep(delay);
+            }
+
+            // Clear screen and move cursor home
+            write!(output, "\x1b[2J\x1b[H{}", frame.to_ansi())?;
+            writeln!(output, "Step {}/{}", idx, self.frames.len() - 1)?;
+            output.flush()?;
+        }
+
+        Ok(())
+    }
+
+    /// Write frames as binary PPM images in a directory.
+    /// Returns written paths.
+    ///
+    /// # Arguments
+    ///
+    /// * `directory` - Output directory
+    /// * `scale` - Cell size in pixels
+    pub fn write_ppm_frames(
+        &self,
+        directory: &Path,
+        scale: usize,
+    ) -> Result<Vec<PathBuf>, DayError> {
+        self.write_frames(directory, "ppm", |frame, writer| {
+            let (w, h) = frame.size();
+            write!(writer, "P6\n{} {}\n255\n", w * scale, h * scale)?;
+            writer.write_all(&frame.to_rgb(scale))?;
+            Ok(())
+        })
+    }
+
+    /// Write frames as PNG images in a directory.
+    /// Returns written paths.
+    ///
+    /// # Arguments
+    ///
+    /// * `directory` - Output directory
+    /// * `scale` - Cell size in pixels
+    pub fn write_png_frames(
+        &self,
+        directory: &Path,
+        scale: usize,
+    ) -> Result<Vec<PathBuf>, DayError> {
+        self.write_frames(directory, "png", |frame, writer| {
+            let (w, h) = frame.size();
+            let mut encoder = png::Encoder::new(writer, (w * scale) as u32, (h * scale) as u32);
+            encoder.set_color(png::ColorType::Rgb);
+            encoder.set_depth(png::BitDepth::Eight);
+            encoder
+                .write_header()?
+                .write_image_data(&frame.to_rgb(scale))?;
+            Ok(())
+        })
+    }
+
+    /// Write frames as a looping animated GIF.
+    /// Fails if no frame was recorded.
+    ///
+    /// # Arguments
+    ///
+    /// * `output` - Output
+    /// * `scale` - Cell size in pixels
+    /// * `delay` - Delay between frames
+    pub fn write_gif<W: Write>(
+        &self,
+        output: W,
+        scale: usize,
+        delay: Duration,
+    ) -> Result<(), DayError> {
+        let (w, h) = self
+            .frames
+            .first()
+            .map(Frame::size)
+            .ok_or(DayError::EmptyRecording)?;
+        let (width, height) = match (gif_dimension(w * scale), gif_dimension(h * scale)) {
+            (Some(width), Some(height)) => (width, height),
+            _ => return Err(DayError::FrameTooLarge(w * scale, h * scale)),
+        };
+
+        let mut encoder = gif::Encoder::new(output, width, height, &PALETTE.concat())?;
+        encoder.set_repeat(gif::Repeat::Infinite)?;
+
+        // GIF delays are in hundredths of a second
+        let delay = (delay.as_millis() / 10).min(u128::from(u16::MAX)) as u16;
+        for frame in &self.frames {
+            let pixels = frame.to_indexed(scale);
+            let gif_frame = gif::Frame {
+                width,
+                height,
+                delay,
+                buffer: Cow::Borrowed(&pixels),
+                ..gif::Frame::default()
+            };
+            encoder.write_frame(&gif_frame)?;
+        }
+
+        Ok(())
+    }
+
+    fn write_frames<F>(
+        &self,
+        directory: &Path,
+        extension: &str,
+        write: F,
+    ) -> Result<Vec<PathBuf>, DayError>
+    where
+        F: Fn(&Frame, &mut BufWriter<File>) -> Result<(), DayError>,
+    {
+        std::fs::create_dir_all(directory)?;
+
+        self.frames
+            .iter()
+            .enumerate()
+            .map(|(idx, frame)| {
+                let path = directory.join(format!("frame_{:04}.{}", idx, extension));
+                let mut writer = BufWriter::new(File::create(&path)?);
+                write(frame, &mut writer)?;
+                writer.flush()?;
+                Ok(path)
+            })
+            .collect()
+    }
+}
+
+fn gif_dimension(value: usize) -> Option<u16> {
+    if value > 0 && value <= usize::from(u16::MAX) {
+        Some(value as u16)
+    } else {
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SAMPLE_LAYOUT: &str = "L.LL.LL.LL\nLLLLLLL.LL\nL.L.L..L..\nLLLL.LL.LL\nL.LL.LL.LL\nL.LLLLL.LL\n..L.L.....\nLLLLLLLLLL\nL.LLLLLL.L\nL.LLLLL.LL";
+
+    fn recorder() -> FrameRecorder {
+        let mut layout = SeatLayout::from_input(SAMPLE_LAYOUT);
+        FrameRecorder::record(&mut layout, &SeatingRules::adjacent())
+    }
+
+    fn temp_directory(name: &str) -> PathBuf {
+        std::env::temp_dir().join(format!("aoc2020-day11-{}-{}", name, std::process::id()))
+    }
+
+    #[test]
+    fn test_record() {
+        let recorder = recorder();
+        assert_eq!(recorder.frames().len(), 6);
+        assert_eq!(recorder.outcome().stats().occupied_seats, 37);
+        assert_eq!(recorder.frames()[0].size(), (10, 10));
+    }
+
+    #[test]
+    fn test_play_ansi() {
+        let mut output = vec![];
+        recorder()
+            .play_ansi(&mut output, Duration::from_millis(0))
+            .unwrap();
+
+        let output = String::from_utf8(output).unwrap();
+        assert_eq!(output.matches("\x1b[2J\x1b[H").count(), 6);
+        assert!(output.starts_with("\x1b[2J\x1b[H\x1b[32mL\x1b[90m.\x1b[32mL"));
+        assert!(output.ends_with("Step 5/5\n"));
+    }
+
+    #[test]
+    fn test_write_frames() {
+        let recorder = recorder();
+        let directory = temp_directory("frames");
+
+        let paths = recorder.write_ppm_frames(&directory, 2).unwrap();
+        assert_eq!(paths.len(), 6);
+        let ppm = std::fs::read(&paths[0]).unwrap();
+        assert!(ppm.starts_with(b"P6\n20 20\n255\n"));
+        assert_eq!(ppm.len(), 13 + 20 * 20 * 3);
+        assert_eq!(&ppm[13..16], &FREE_COLOR);
+
+        let paths = recorder.write_png_frames(&directory, 2).unwrap();
+        assert_eq!(paths[5].file_name().unwrap(), "frame_0005.png");
+        let png = std::fs::read(&paths[5]).unwrap();
+        assert!(png.starts_with(b"\x89PNG\r\n\x1a\n"));
+
+        std::fs::remove_dir_all(&directory).unwrap();
+    }
+
+    #[test]
+    fn test_write_gif() {
+        let mut output = vec![];
+        recorder()
+            .write_gif(&mut output, 4, Duration::from_millis(200))
+            .unwrap();
+        assert!(output.starts_with(b"GIF89a"));
+        assert_eq!(&output[6..10], &[40, 0, 40, 0]);
+
+        assert!(matches!(
+            recorder().write_gif(&mut vec![], 10_000, Duration::from_millis(200)),
+            Err(DayError::FrameTooLarge(100_000, 100_000))
+        ));
+
+        let mut empty = recorder();
+        empty.frames.clear();
+        assert!(matches!(
+            empty.write_gif(&mut vec![], 4, Duration::from_millis(200)),
+            Err(DayError::EmptyRecording)
+        ));
+    }
+}
@@ -68,6 +68,16 @@
 
 #![allow(clippy::cast_precision_loss, clippy::cast_possible_truncation)]
 
+use std::fmt;
+
+mod optimizer;
+mod planner;
+mod route;
+
+pub use self::optimizer::Optimizer;
+pub use self::planner::Planner;
+pub use self::route::{Route, RoutePoint};
+
 const INPUT_VALUES: &str = include_str!("input.txt");
 
 /// Part one answer.
@@ -92,21 +102,104 @@ pub fn compute_manhattan_distance((x, y): (isize, isize)) -> usize {
     (x.abs() + y.abs()) as usize
 }
 
+/// Rounding policy, used when a rotation does not land on integer coordinates.
+#[derive(Debug, Default, Copy, Clone, Eq, PartialEq)]
+pub enum RoundingPolicy {
+    /// Round to the nearest integer, half away from zero
+    #[default]
+    Nearest,
+    /// Round towards zero
+    TowardZero,
+    /// Round towards negative infinity
+    Floor,
+}
+
+impl RoundingPolicy {
+    /// Round value.
+    ///
+    /// # Arguments
+    ///
+    /// * `value` - Value
+    pub fn apply(self, value: f64) -> isize {
+        match self {
+            Self::Nearest => value.round() as isize,
+            Self::TowardZero => value.trunc() as isize,
+            Self::Floor => value.floor() as isize,
+        }
+    }
+}
+
+/// Navigation semantics
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum NavigationMode {
+    /// Commands move and rotate the ship (part one)
+    Direct,
+    /// Commands move and rotate the waypoint (part two)
+    Waypoint,
+}
+
 /// Rotate position from `amount`.
+/// Uses the `Nearest` rounding policy for angles which are not multiples of 90 degrees.
 ///
 /// # Arguments
 ///
 /// * `(x, y)`: `isize` position tuple
 /// * `amount`: Rotation amount
 pub fn rotate_position((x, y): (isize, isize), amount: isize) -> (isize, isize) {
-    let amount: f64 = amount as f64;
-    let amount = amount.to_radians();
-    let (cs, sn) = (amount.cos(), amount.sin());
+    rotate_position_with_policy((x, y), amount, RoundingPolicy::Nearest)
+}
+
+/// Rotate position from `amount` degrees, counter-clockwise.
+/// Multiples of 90 degrees are exact, other angles are rounded using `policy`.
+///
+/// # Arguments
+///
+/// * `(x, y)`: `isize` position tuple
+/// * `amount`: Rotation amount
+/// * `policy`: Rounding policy
+pub fn rotate_position_with_policy(
+    (x, y): (isize, isize),
+    amount: isize,
+    policy: RoundingPolicy,
+) -> (isize, isize) {
+    match amount.rem_euclid(360) {
+        0 => (x, y),
+        90 => (-y, x),
+        180 => (-x, -y),
+        270 => (y, -x),
+        amount => {
+            let amount = (amount as f64).to_radians();
+            let (cs, sn) = (amount.cos(), amount.sin());
+
+            let (fx, fy) = (x as f64, y as f64);
+            let (nx, ny) = (fx * cs - fy * sn, fx.mul_add(sn, fy * cs));
+
+            (policy.apply(nx), policy.apply(ny))
+        }
+    }
+}
 
-    let (fx, fy) = (x as f64, y as f64);
-    let (nx, ny) = (fx * cs - fy * sn, fx.mul_add(sn, fy * cs));
+/// Parse commands from input string.
+///
+/// # Arguments
+///
+/// * `input` - Input string
+pub fn parse_commands(input: &str) -> Vec<CommandDirection> {
+    input
+        .lines()
+        .map(str::trim)
+        .filter(|l| !l.is_empty())
+        .map(CommandDirection::from_input)
+        .collect()
+}
 
-    (nx.round() as isize, ny.round() as isize)
+/// Format commands as a script, one command per line.
+///
+/// # Arguments
+///
+/// * `commands` - Commands
+pub fn format_commands(commands: &[CommandDirection]) -> String {
+    commands.iter().map(|c| format!("{}\n", c)).collect()
 }
 
 /// Command direction
@@ -150,6 +243,55 @@ impl CommandDirection {
             l => panic!("Unknown direction: {}", l),
         }
     }
+
+    /// Get shortest turn command for a counter-clockwise rotation, if any.
+    ///
+    /// # Arguments
+    ///
+    /// * `amount` - Rotation amount, in degrees
+    pub fn turn(amount: isize) -> Option<Self> {
+        match amount.rem_euclid(360) {
+            0 => None,
+            a if a <= 180 => Some(Self::Left(a)),
+            a => Some(Self::Right(360 - a)),
+        }
+    }
+
+    /// Get cardinal commands for a translation, east/west first.
+    ///
+    /// # Arguments
+    ///
+    /// * `(dx, dy)` - Translation
+    pub fn shift((dx, dy): (isize, isize)) -> impl Iterator<Item = Self> {
+        let horizontal = match dx.signum() {
+            1 => Some(Self::East(dx)),
+            -1 => Some(Self::West(-dx)),
+            _ => None,
+        };
+        let vertical = match dy.signum() {
+            1 => Some(Self::North(dy)),
+            -1 => Some(Self::South(-dy)),
+            _ => None,
+        };
+
+        horizontal.into_iter().chain(vertical)
+    }
+}
+
+impl fmt::Display for CommandDirection {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let (letter, value) = match self {
+            Self::North(v) => ('N', v),
+            Self::South(v) => ('S', v),
+            Self::East(v) => ('E', v),
+            Self::West(v) => ('W', v),
+            Self::Forward(v) => ('F', v),
+            Self::Left(v) => ('L', v),
+            Self::Right(v) => ('R', v),
+        };
+
+        write!(f, "{}{}", letter, value)
+    }
 }
 
 /// Ship
@@ -162,24 +304,71 @@ impl CommandDirection {
 /// ```
 ///
 /// Initial waypoint is at `(10, 1)`
-#[derive(Debug, Default)]
+///
+/// Rotations of any angle are supported: headings and waypoints are rotated
+/// exactly for multiples of 90 degrees, and rounded to integer coordinates
+/// using the ship rounding policy otherwise.
+#[derive(Debug, Default, Clone)]
 pub struct Ship {
     rotation: isize,
     x: isize,
     y: isize,
     waypoint_rel_x: isize,
     waypoint_rel_y: isize,
+    rounding: RoundingPolicy,
 }
 
 impl Ship {
     /// Creates a new ship.
     pub const fn new() -> Self {
+        Self::with_rounding(RoundingPolicy::Nearest)
+    }
+
+    /// Creates a new ship with a rounding policy.
+    ///
+    /// # Arguments
+    ///
+    /// * `rounding` - Rounding policy
+    pub const fn with_rounding(rounding: RoundingPolicy) -> Self {
         Self {
             rotation: 0,
             x: 0,
             y: 0,
             waypoint_rel_x: 10,
             waypoint_rel_y: 1,
+            rounding,
+        }
+    }
+
+    /// Get ship position.
+    pub const fn position(&self) -> (isize, isize) {
+        (self.x, self.y)
+    }
+
+    /// Get waypoint position, relative to the ship.
+    pub const fn waypoint(&self) -> (isize, isize) {
+        (self.waypoint_rel_x, self.waypoint_rel_y)
+    }
+
+    /// Get ship heading in degrees, counter-clockwise from east.
+    pub const fn rotation(&self) -> isize {
+        self.rotation
+    }
+
+    /// Execute command using navigation semantics.
+    ///
+    /// # Arguments
+    ///
+    /// * `mode` - Navigation mode
+    /// * `command` - Command
+    pub fn execute_command(
+        &mut self,
+        mode: NavigationMode,
+        command: CommandDirection,
+    ) -> (isize, isize) {
+        match mode {
+            NavigationMode::Direct => self.execute_command_no_waypoint(command),
+            NavigationMode::Waypoint => self.execute_command_waypoint(command),
         }
     }
 
@@ -227,7 +416,11 @@ impl Ship {
     ///
     /// * `amount` - Rotation amount
     pub fn rotate_waypoint(&mut self, amount: isize) {
-        let (x, y) = rotate_position((self.waypoint_rel_x, self.waypoint_rel_y), amount);
+        let (x, y) = rotate_position_with_policy(
+            (self.waypoint_rel_x, self.waypoint_rel_y),
+            amount,
+            self.rounding,
+        );
         self.waypoint_rel_x = x;
         self.waypoint_rel_y = y;
     }
@@ -247,18 +440,9 @@ impl Ship {
     ///
     /// * `amount` - Movement factor
     pub fn move_forward(&mut self, amount: isize) {
-        match self.rotation {
-            // East
-            0 => self.x += amount,
-            // North
-            90 => self.y += amount,
-            // West
-            180 => self.x -= amount,
-            // South
-            270 => self.y -= amount,
-            // Unsupported
-            u => panic!("Unsupported rotation: {}", u),
-        }
+        let (dx, dy) = rotate_position_with_policy((amount, 0), self.rotation, self.rounding);
+        self.x += dx;
+        self.y += dy;
     }
 
     /// Move ship towards waypoint.
@@ -278,11 +462,7 @@ impl Ship {
     ///
     /// * `input` - Input string
     pub fn parse_and_execute_input_commands_no_waypoint(&mut self, input: &str) -> (isize, isize) {
-        let commands: Vec<_> = input
-            .lines()
-            .map(|l| CommandDirection::from_input(l.trim()))
-            .collect();
-        for command in commands {
+        for command in parse_commands(input) {
             self.execute_command_no_waypoint(command);
         }
 
@@ -296,11 +476,7 @@ impl Ship {
     ///
     /// * `input` - Input string
     pub fn parse_and_execute_input_commands_waypoint(&mut self, input: &str) -> (isize, isize) {
-        let commands: Vec<_> = input
-            .lines()
-            .map(|l| CommandDirection::from_input(l.trim()))
-            .collect();
-        for command in commands {
+        for command in parse_commands(input) {
             self.execute_command_waypoint(command);
         }
 
@@ -377,6 +553,44 @@ mod tests {
         assert_eq!(rotate_position((10, 1), 360), (10, 1));
     }
 
+    #[test]
+    fn test_rotate_position_with_policy() {
+        assert_eq!(
+            rotate_position_with_policy((10, 1), -270, RoundingPolicy::Floor),
+            (-1, 10)
+        );
+        assert_eq!(rotate_position((10, 1), 45), (6, 8));
+        assert_eq!(
+            rotate_position_with_policy((10, 1), 45, RoundingPolicy::TowardZero),
+            (6, 7)
+        );
+        assert_eq!(
+            rotate_position_with_policy((10, 1), 135, RoundingPolicy::TowardZero),
+            (-7, 6)
+        );
+        assert_eq!(
+            rotate_position_with_policy((10, 1), 135, RoundingPolicy::Floor),
+            (-8, 6)
+        );
+    }
+
+    #[test]
+    fn test_arbitrary_angles() {
+        let mut ship = Ship::new();
+        let commands = parse_commands("L45\nF10\nR90\nF10\nR135\nF1");
+        for command in commands.iter().copied() {
+            ship.execute_command(NavigationMode::Direct, command);
+        }
+        assert_eq!(ship.position(), (13, 0));
+        assert_eq!(ship.rotation(), 180);
+
+        let mut ship = Ship::with_rounding(RoundingPolicy::TowardZero);
+        ship.execute_command(NavigationMode::Waypoint, CommandDirection::Right(45));
+        assert_eq!(ship.waypoint(), (7, -6));
+        ship.execute_command(NavigationMode::Waypoint, CommandDirection::Forward(2));
+        assert_eq!(ship.position(), (14, -12));
+    }
+
     #[test]
     fn test_sample_execution_steps_waypoint() {
         let mut ship = Ship::new();
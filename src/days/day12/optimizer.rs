@@ -0,0 +1,211 @@
+//! Navigation program optimizer
+
+use super::{rotate_position, CommandDirection, NavigationMode};
+
+/// Navigation program optimizer.
+///
+/// Optimized programs are equivalent to their source when executed on a new
+/// ship: they lead to the same ship position, heading and waypoint.
+pub struct Optimizer;
+
+impl Optimizer {
+    /// Optimize commands for a navigation mode.
+    ///
+    /// # Arguments
+    ///
+    /// * `commands` - Commands
+    /// * `mode` - Navigation mode
+    pub fn optimize(commands: &[CommandDirection], mode: NavigationMode) -> Vec<CommandDirection> {
+        match mode {
+            NavigationMode::Direct => Self::optimize_direct(commands),
+            NavigationMode::Waypoint => Self::optimize_waypoint(commands),
+        }
+    }
+
+    /// Without waypoint, cardinal moves commute with every other command, so
+    /// they are gathered in at most two commands at the end of the program.
+    /// Turns between forward moves are merged into one, and forward moves are
+    /// merged when the heading is a right angle, as rounding may apply otherwise.
+    fn optimize_direct(commands: &[CommandDirection]) -> Vec<CommandDirection> {
+        let mut output = vec![];
+        let mut shift = (0, 0);
+        let mut heading = 0;
+        let mut turn = 0;
+
+        for &command in commands {
+            match command {
+                CommandDirection::North(v) => shift.1 += v,
+                CommandDirection::South(v) => shift.1 -= v,
+                CommandDirection::East(v) => shift.0 += v,
+                CommandDirection::West(v) => shift.0 -= v,
+                CommandDirection::Left(v) => turn += v,
+                CommandDirection::Right(v) => turn -= v,
+                CommandDirection::Forward(0) => (),
+                CommandDirection::Forward(v) => {
+                    output.extend(CommandDirection::turn(turn));
+                    heading = (heading + turn).rem_euclid(360);
+                    turn = 0;
+
+                    match output.last_mut() {
+                        Some(CommandDirection::Forward(previous)) if heading % 90 == 0 => {
+                            *previous += v
+                        }
+                        _ => output.push(CommandDirection::Forward(v)),
+                    }
+                }
+            }
+        }
+
+        output.extend(CommandDirection::turn(turn));
+        output.extend(CommandDirection::shift(shift));
+        output
+    }
+
+    /// With waypoint, each run of waypoint commands between forward moves is an
+    /// affine map of the waypoint, emitted as one turn followed by at most two
+    /// cardinal moves. Turns which are not right angles are rounded, so they
+    /// are kept as is and split runs.
+    fn optimize_waypoint(commands: &[CommandDirection]) -> Vec<CommandDirection> {
+        let mut output = vec![];
+        // Pending map: waypoint -> rotate(waypoint, turn) + shift
+        let mut turn = 0;
+        let mut shift = (0, 0);
+
+        let flush =
+            |output: &mut Vec<CommandDirection>, turn: &mut isize, shift: &mut (isize, isize)| {
+                output.extend(CommandDirection::turn(*turn));
+                output.extend(CommandDirection::shift(*shift));
+                *turn = 0;
+                *shift = (0, 0);
+            };
+
+        for &command in commands {
+            match command {
+                CommandDirection::North(v) => shift.1 += v,
+                CommandDirection::South(v) => shift.1 -= v,
+                CommandDirection::East(v) => shift.0 += v,
+                CommandDirection::West(v) => shift.0 -= v,
+                CommandDirection::Left(v) | CommandDirection::Right(v) => {
+                    let amount = if let CommandDirection::Left(_) = command {
+                        v
+                    } else {
+                        -v
+                    };
+
+                    if amount % 90 == 0 {
+                        turn += amount;
+                        shift = rotate_position(shift, amount);
+                    } else {
+                        flush(&mut output, &mut turn, &mut shift);
+                        output.extend(CommandDirection::turn(amount));
+                    }
+                }
+                CommandDirection::Forward(0) => (),
+                CommandDirection::Forward(v) => {
+                    flush(&mut output, &mut turn, &mut shift);
+                    match output.last_mut() {
+                        Some(CommandDirection::Forward(previous)) => *previous += v,
+                        _ => output.push(CommandDirection::Forward(v)),
+                    }
+                }
+            }
+        }
+
+        flush(&mut output, &mut turn, &mut shift);
+        output
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::days::day12::{format_commands, parse_commands, Ship};
+
+    const SAMPLE: &str = "F10\nN3\nF7\nR90\nF11";
+
+    fn final_state(commands: &[CommandDirection], mode: NavigationMode) -> [(isize, isize); 3] {
+        let mut ship = Ship::new();
+        for &command in commands {
+            ship.execute_command(mode, command);
+        }
+
+        [ship.position(), ship.waypoint(), (ship.rotation(), 0)]
+    }
+
+    fn random_program(seed: u64, length: usize) -> Vec<CommandDirection> {
+        let mut state = seed;
+        let mut next = move |modulo: u64| {
+            state ^= state << 13;
+            state ^= state >> 7;
+            state ^= state << 17;
+            (state % modulo) as isize
+        };
+
+        (0..length)
+            .map(|_| {
+                let value = next(12) - 2;
+                match next(8) {
+                    0 => CommandDirection::North(value),
+                    1 => CommandDirection::South(value),
+                    2 => CommandDirection::East(value),
+                    3 => CommandDirection::West(value),
+                    4 => CommandDirection::Left(next(8) * 45),
+                    5 => CommandDirection::Right(next(4) * 90),
+                    _ => CommandDirection::Forward(value),
+                }
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_optimize_sample() {
+        let commands = parse_commands(SAMPLE);
+
+        let direct = Optimizer::optimize(&commands, NavigationMode::Direct);
+        assert_eq!(format_commands(&direct), "F17\nR90\nF11\nN3\n");
+
+        let waypoint = Optimizer::optimize(&commands, NavigationMode::Waypoint);
+        assert_eq!(waypoint, commands);
+    }
+
+    #[test]
+    fn test_optimize_runs() {
+        let commands = parse_commands("N1\nS1\nL90\nR90\nE5\nW5\nF10\nF5\nR90\nN3\nL90");
+        assert_eq!(
+            Optimizer::optimize(&commands, NavigationMode::Waypoint),
+            vec![CommandDirection::Forward(15), CommandDirection::West(3)]
+        );
+        assert_eq!(
+            Optimizer::optimize(&commands, NavigationMode::Direct),
+            vec![CommandDirection::Forward(15), CommandDirection::North(3)]
+        );
+
+        let commands = parse_commands("L270\nR45\nL45\nF2\nL45\nF1\nF1");
+        assert_eq!(
+            format_commands(&Optimizer::optimize(&commands, NavigationMode::Direct)),
+            "R90\nF2\nL45\nF1\nF1\n"
+        );
+        assert_eq!(
+            format_commands(&Optimizer::optimize(&commands, NavigationMode::Waypoint)),
+            "R90\nR45\nL45\nF2\nL45\nF2\n"
+        );
+    }
+
+    #[test]
+    fn test_optimize_equivalence() {
+        for seed in 1..200 {
+            let commands = random_program(seed * 7919, 40);
+            for &mode in &[NavigationMode::Direct, NavigationMode::Waypoint] {
+                let optimized = Optimizer::optimize(&commands, mode);
+                assert!(optimized.len() <= commands.len());
+                assert_eq!(
+                    final_state(&optimized, mode),
+                    final_state(&commands, mode),
+                    "{:?} / {:?}",
+                    mode,
+                    commands
+                );
+            }
+        }
+    }
+}
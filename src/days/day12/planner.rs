@@ -0,0 +1,169 @@
+//! Inverse navigation planner
+
+use super::{rotate_position, CommandDirection, NavigationMode, Ship};
+
+/// Navigation planner, finding shortest programs to reach a position.
+///
+/// Plans only use cardinal moves, right-angle turns and forward moves.
+pub struct Planner;
+
+impl Planner {
+    /// Get a shortest command sequence moving the ship to `target`.
+    ///
+    /// Without waypoint, one cardinal move per axis is enough.
+    /// With waypoint, only forward moves displace the ship, by a positive
+    /// multiple of the waypoint. The target is reached either with the current
+    /// waypoint, with a waypoint changed by a single command, or by setting the
+    /// waypoint to the target with two cardinal moves and moving forward once.
+    ///
+    /// # Arguments
+    ///
+    /// * `ship` - Ship
+    /// * `mode` - Navigation mode
+    /// * `target` - Target position
+    pub fn plan(
+        ship: &Ship,
+        mode: NavigationMode,
+        target: (isize, isize),
+    ) -> Vec<CommandDirection> {
+        let (x, y) = ship.position();
+        let delta = (target.0 - x, target.1 - y);
+
+        match mode {
+            NavigationMode::Direct => CommandDirection::shift(delta).collect(),
+            NavigationMode::Waypoint => Self::plan_waypoint(ship.waypoint(), delta),
+        }
+    }
+
+    fn plan_waypoint(waypoint: (isize, isize), delta: (isize, isize)) -> Vec<CommandDirection> {
+        if delta == (0, 0) {
+            return vec![];
+        }
+
+        if let Some(factor) = Self::factor(waypoint, delta) {
+            return vec![CommandDirection::Forward(factor)];
+        }
+
+        for &amount in &[90, 180, 270] {
+            if let Some(factor) = Self::factor(rotate_position(waypoint, amount), delta) {
+                return CommandDirection::turn(amount)
+                    .into_iter()
+                    .chain(std::iter::once(CommandDirection::Forward(factor)))
+                    .collect();
+            }
+        }
+
+        // Change one waypoint coordinate, keeping the other
+        if let Some((factor, x)) = Self::scale(waypoint.1, delta.1, delta.0) {
+            return CommandDirection::shift((x - waypoint.0, 0))
+                .chain(std::iter::once(CommandDirection::Forward(factor)))
+                .collect();
+        }
+        if let Some((factor, y)) = Self::scale(waypoint.0, delta.0, delta.1) {
+            return CommandDirection::shift((0, y - waypoint.1))
+                .chain(std::iter::once(CommandDirection::Forward(factor)))
+                .collect();
+        }
+
+        CommandDirection::shift((delta.0 - waypoint.0, delta.1 - waypoint.1))
+            .chain(std::iter::once(CommandDirection::Forward(1)))
+            .collect()
+    }
+
+    /// Get positive factor `n` so that `delta` is `n * waypoint`.
+    fn factor(waypoint: (isize, isize), delta: (isize, isize)) -> Option<isize> {
+        let factor = match waypoint {
+            (0, 0) => return None,
+            (0, wy) if delta.1 % wy == 0 => delta.1 / wy,
+            (wx, _) if wx != 0 && delta.0 % wx == 0 => delta.0 / wx,
+            _ => return None,
+        };
+
+        if factor > 0 && (waypoint.0 * factor, waypoint.1 * factor) == delta {
+            Some(factor)
+        } else {
+            None
+        }
+    }
+
+    /// Get positive factor `n` and free coordinate `c` so that `n * fixed` is
+    /// `target_fixed` and `n * c` is `target_free`.
+    fn scale(fixed: isize, target_fixed: isize, target_free: isize) -> Option<(isize, isize)> {
+        let factor = match fixed {
+            0 if target_fixed == 0 => 1,
+            0 => return None,
+            f if target_fixed % f == 0 => target_fixed / f,
+            _ => return None,
+        };
+
+        if factor > 0 && target_free % factor == 0 {
+            Some((factor, target_free / factor))
+        } else {
+            None
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::days::day12::format_commands;
+
+    fn execute(
+        mut ship: Ship,
+        mode: NavigationMode,
+        commands: &[CommandDirection],
+    ) -> (isize, isize) {
+        for &command in commands {
+            ship.execute_command(mode, command);
+        }
+
+        ship.position()
+    }
+
+    #[test]
+    fn test_plan_direct() {
+        let ship = Ship::new();
+        assert_eq!(Planner::plan(&ship, NavigationMode::Direct, (0, 0)), vec![]);
+        assert_eq!(
+            format_commands(&Planner::plan(&ship, NavigationMode::Direct, (-4, 7))),
+            "W4\nN7\n"
+        );
+        assert_eq!(
+            Planner::plan(&ship, NavigationMode::Direct, (0, -3)),
+            vec![CommandDirection::South(3)]
+        );
+    }
+
+    #[test]
+    fn test_plan_waypoint() {
+        let ship = Ship::new();
+        let plan =
+            |target| format_commands(&Planner::plan(&ship, NavigationMode::Waypoint, target));
+
+        assert_eq!(plan((0, 0)), "");
+        assert_eq!(plan((100, 10)), "F10\n");
+        assert_eq!(plan((-10, 100)), "L90\nF10\n");
+        assert_eq!(plan((-20, -2)), "L180\nF2\n");
+        assert_eq!(plan((35, 7)), "W5\nF7\n");
+        assert_eq!(plan((20, 6)), "N2\nF2\n");
+        assert_eq!(plan((7, -3)), "W3\nS4\nF1\n");
+    }
+
+    #[test]
+    fn test_plan_round_trip() {
+        let mut start = Ship::new();
+        start.execute_command(NavigationMode::Waypoint, CommandDirection::Forward(3));
+        start.execute_command(NavigationMode::Waypoint, CommandDirection::North(-1));
+
+        for x in -25..=25 {
+            for y in -25..=25 {
+                for &mode in &[NavigationMode::Direct, NavigationMode::Waypoint] {
+                    let commands = Planner::plan(&start, mode, (x, y));
+                    assert!(commands.len() <= 3);
+                    assert_eq!(execute(start.clone(), mode, &commands), (x, y));
+                }
+            }
+        }
+    }
+}
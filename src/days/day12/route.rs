@@ -0,0 +1,284 @@
+//! Ship route recording and plotting
+
+use super::{CommandDirection, NavigationMode, Ship};
+
+/// Ship and waypoint state after a command.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct RoutePoint {
+    /// Executed command, `None` for the starting point
+    pub command: Option<CommandDirection>,
+    /// Ship position
+    pub ship: (isize, isize),
+    /// Waypoint position, relative to the ship, only recorded in waypoint mode
+    pub waypoint: Option<(isize, isize)>,
+}
+
+impl RoutePoint {
+    fn capture(command: Option<CommandDirection>, ship: &Ship, mode: NavigationMode) -> Self {
+        Self {
+            command,
+            ship: ship.position(),
+            waypoint: match mode {
+                NavigationMode::Direct => None,
+                NavigationMode::Waypoint => Some(ship.waypoint()),
+            },
+        }
+    }
+
+    /// Get absolute waypoint position, if recorded.
+    pub fn waypoint_position(&self) -> Option<(isize, isize)> {
+        self.waypoint
+            .map(|(wx, wy)| (self.ship.0 + wx, self.ship.1 + wy))
+    }
+}
+
+/// Recorded ship route.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Route {
+    points: Vec<RoutePoint>,
+}
+
+impl Route {
+    /// Execute commands on ship, recording every intermediate state.
+    /// The waypoint is only recorded in waypoint mode.
+    ///
+    /// # Arguments
+    ///
+    /// * `ship` - Ship
+    /// * `mode` - Navigation mode
+    /// * `commands` - Commands
+    pub fn record<I>(ship: &mut Ship, mode: NavigationMode, commands: I) -> Self
+    where
+        I: IntoIterator<Item = CommandDirection>,
+    {
+        let mut points = vec![RoutePoint::capture(None, ship, mode)];
+        for command in commands {
+            ship.execute_command(mode, command);
+            points.push(RoutePoint::capture(Some(command), ship, mode));
+        }
+
+        Self { points }
+    }
+
+    /// Get recorded points, starting with the initial state.
+    pub fn points(&self) -> &[RoutePoint] {
+        &self.points
+    }
+
+    /// Get bounds of ship and recorded waypoint positions, as `(min_x, min_y, max_x, max_y)`.
+    pub fn bounds(&self) -> (isize, isize, isize, isize) {
+        self.points
+            .iter()
+            .flat_map(|p| std::iter::once(p.ship).chain(p.waypoint_position()))
+            .fold(
+                (isize::MAX, isize::MAX, isize::MIN, isize::MIN),
+                |(min_x, min_y, max_x, max_y), (x, y)| {
+                    (min_x.min(x), min_y.min(y), max_x.max(x), max_y.max(y))
+                },
+            )
+    }
+
+    /// Render route as an SVG document.
+    /// The ship path is a solid line, the recorded waypoint path a dashed line.
+    pub fn to_svg(&self) -> String {
+        let (min_x, min_y, max_x, max_y) = self.bounds();
+        // SVG y axis points down, so north is flipped up
+        let (width, height) = (max_x - min_x, max_y - min_y);
+        let margin = (width.max(height) / 20).max(1);
+        let stroke = (width.max(height) / 400).max(1);
+
+        let polyline = |points: Vec<(isize, isize)>| {
+            points
+                .iter()
+                .map(|(x, y)| format!("{},{}", x, -y))
+                .collect::<Vec<_>>()
+                .join(" ")
+        };
+
+        let ship = polyline(self.points.iter().map(|p| p.ship).collect());
+        let waypoints: Vec<_> = self
+            .points
+            .iter()
+            .filter_map(RoutePoint::waypoint_position)
+            .collect();
+        let (sx, sy) = self.points[0].ship;
+        let (ex, ey) = self.points[self.points.len() - 1].ship;
+
+        let mut svg = format!(
+            "<svg xmlns=\"http://www.w3.org/2000/svg\" viewBox=\"{} {} {} {}\">\n",
+            min_x - margin,
+            -max_y - margin,
+            width + 2 * margin,
+            height + 2 * margin
+        );
+        if !waypoints.is_empty() {
+            svg.push_str(&format!(
+                "  <polyline points=\"{}\" fill=\"none\" stroke=\"#999\" stroke-width=\"{}\" stroke-dasharray=\"{}\"/>\n",
+                polyline(waypoints),
+                stroke,
+                stroke * 4
+            ));
+        }
+        svg.push_str(&format!(
+            "  <polyline points=\"{}\" fill=\"none\" stroke=\"#1f6feb\" stroke-width=\"{}\"/>\n",
+            ship, stroke
+        ));
+        svg.push_str(&format!(
+            "  <circle cx=\"{}\" cy=\"{}\" r=\"{}\" fill=\"#2da44e\"/>\n",
+            sx,
+            -sy,
+            stroke * 3
+        ));
+        svg.push_str(&format!(
+            "  <circle cx=\"{}\" cy=\"{}\" r=\"{}\" fill=\"#cf222e\"/>\n",
+            ex,
+            -ey,
+            stroke * 3
+        ));
+        svg.push_str("</svg>\n");
+
+        svg
+    }
+
+    /// Render ship route as ASCII art, scaled to fit in `width` x `height` characters.
+    /// The path is drawn with `#`, recorded waypoints with `w`, start with `S` and end with `E`.
+    ///
+    /// # Arguments
+    ///
+    /// * `width` - Plot width
+    /// * `height` - Plot height
+    pub fn to_ascii(&self, width: usize, height: usize) -> String {
+        if width == 0 || height == 0 {
+            return String::new();
+        }
+
+        let (min_x, min_y, max_x, max_y) = self.bounds();
+        let scale = |value: isize, min: isize, max: isize, size: usize| {
+            if max == min {
+                0
+            } else {
+                ((value - min) as f64 * (size - 1) as f64 / (max - min) as f64).round() as isize
+            }
+        };
+        let project = |(x, y): (isize, isize)| {
+            (
+                scale(x, min_x, max_x, width),
+                height as isize - 1 - scale(y, min_y, max_y, height),
+            )
+        };
+
+        let mut grid = vec![vec!['.'; width]; height];
+        let mut plot = |(x, y): (isize, isize), c: char| grid[y as usize][x as usize] = c;
+
+        for waypoint in self.points.iter().filter_map(RoutePoint::waypoint_position) {
+            plot(project(waypoint), 'w');
+        }
+        for segment in self.points.windows(2) {
+            for position in line(project(segment[0].ship), project(segment[1].ship)) {
+                plot(position, '#');
+            }
+        }
+        plot(project(self.points[0].ship), 'S');
+        plot(project(self.points[self.points.len() - 1].ship), 'E');
+
+        grid.iter()
+            .map(|row| row.iter().chain(std::iter::once(&'\n')).collect::<String>())
+            .collect()
+    }
+}
+
+/// Get grid positions on a line, using Bresenham's algorithm.
+fn line((x0, y0): (isize, isize), (x1, y1): (isize, isize)) -> Vec<(isize, isize)> {
+    let (dx, dy) = ((x1 - x0).abs(), -(y1 - y0).abs());
+    let (sx, sy) = ((x1 - x0).signum(), (y1 - y0).signum());
+    let (mut x, mut y, mut err) = (x0, y0, dx + dy);
+
+    let mut positions = vec![(x, y)];
+    while (x, y) != (x1, y1) {
+        let e2 = 2 * err;
+        if e2 >= dy {
+            err += dy;
+            x += sx;
+        }
+        if e2 <= dx {
+            err += dx;
+            y += sy;
+        }
+        positions.push((x, y));
+    }
+
+    positions
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::days::day12::parse_commands;
+
+    const SAMPLE: &str = "F10\nN3\nF7\nR90\nF11";
+
+    #[test]
+    fn test_record() {
+        let route = Route::record(
+            &mut Ship::new(),
+            NavigationMode::Direct,
+            parse_commands(SAMPLE),
+        );
+        let ships: Vec<_> = route.points().iter().map(|p| p.ship).collect();
+        assert_eq!(
+            ships,
+            vec![(0, 0), (10, 0), (10, 3), (17, 3), (17, 3), (17, -8)]
+        );
+        assert_eq!(route.points()[0].command, None);
+        assert_eq!(route.points()[4].command, Some(CommandDirection::Right(90)));
+        assert_eq!(route.points()[4].waypoint_position(), None);
+
+        let route = Route::record(
+            &mut Ship::new(),
+            NavigationMode::Waypoint,
+            parse_commands(SAMPLE),
+        );
+        let last = route.points()[5];
+        assert_eq!(last.ship, (214, -72));
+        assert_eq!(last.waypoint, Some((4, -10)));
+        assert_eq!(last.waypoint_position(), Some((218, -82)));
+        assert_eq!(route.bounds(), (0, -82, 218, 42));
+    }
+
+    #[test]
+    fn test_to_svg() {
+        let route = Route::record(
+            &mut Ship::new(),
+            NavigationMode::Direct,
+            parse_commands(SAMPLE),
+        );
+        let svg = route.to_svg();
+        assert!(
+            svg.starts_with("<svg xmlns=\"http://www.w3.org/2000/svg\" viewBox=\"-1 -4 19 13\">")
+        );
+        assert!(svg.contains("points=\"0,0 10,0 10,-3 17,-3 17,-3 17,8\""));
+        assert!(!svg.contains("stroke-dasharray"));
+        assert!(svg.trim_end().ends_with("</svg>"));
+
+        let route = Route::record(
+            &mut Ship::new(),
+            NavigationMode::Waypoint,
+            parse_commands(SAMPLE),
+        );
+        assert!(route
+            .to_svg()
+            .contains("points=\"10,-1 110,-11 110,-14 180,-42 174,-28 218,82\""));
+    }
+
+    #[test]
+    fn test_to_ascii() {
+        let commands = parse_commands("F4\nN2\nL90\nF2");
+        let mut ship = Ship::new();
+        let route = Route::record(&mut ship, NavigationMode::Direct, commands);
+        assert_eq!(route.bounds(), (0, 0, 4, 4));
+
+        // Only the ship path, on a 1:1 scale
+        assert_eq!(route.to_ascii(5, 5), "....E\n....#\n....#\n....#\nS####\n");
+        assert_eq!(route.to_ascii(0, 3), "");
+    }
+}
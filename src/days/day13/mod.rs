@@ -119,6 +119,15 @@
 //!
 //! What is the earliest timestamp such that all of the listed bus IDs depart at offsets matching their positions in the list?
 
+use std::{
+    collections::BTreeMap,
+    ops::{Range, RangeInclusive},
+};
+
+use thiserror::Error;
+
+use crate::common::math::{try_crt, CrtError};
+
 const INPUT_VALUES: &str = include_str!("input.txt");
 
 /// Part one answer.
@@ -133,7 +142,81 @@ pub fn run_ex1() -> usize {
 /// Part two answer.
 pub fn run_ex2() -> usize {
     let (_, schedule) = extract_schedules(INPUT_VALUES);
-    Scheduler::from_input(schedule).compute_successive_departures_time()
+    Scheduler::from_input(schedule)
+        .compute_successive_departures_time()
+        .unwrap() as usize
+}
+
+/// Day error.
+#[derive(Debug, Error, PartialEq, Eq)]
+pub enum DayError {
+    /// Bus ID is zero
+    #[error("Invalid bus ID 0 in constraint {0}")]
+    InvalidBusId(usize),
+    /// Constraint contradicts the previous ones
+    #[error("Unsatisfiable schedule: bus {bus} can not depart at offset {offset}")]
+    UnsatisfiableSchedule {
+        /// Bus ID
+        bus: u64,
+        /// Departure offset
+        offset: i64,
+    },
+    /// Bus is not in the schedule
+    #[error("Unknown bus ID {0}")]
+    UnknownBus(usize),
+    /// Schedule period does not fit in 128 bits
+    #[error("Schedule period overflows")]
+    Overflow,
+}
+
+/// Departure constraint: bus `bus` departs `offset` minutes after the timestamp.
+/// Offsets may be negative, for buses departing before the timestamp.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DepartureConstraint {
+    /// Bus ID
+    pub bus: u64,
+    /// Departure offset, in minutes
+    pub offset: i64,
+}
+
+impl DepartureConstraint {
+    /// Creates a new constraint.
+    ///
+    /// # Arguments
+    ///
+    /// * `bus` - Bus ID
+    /// * `offset` - Departure offset, in minutes
+    pub const fn new(bus: u64, offset: i64) -> Self {
+        Self { bus, offset }
+    }
+}
+
+/// Schedule solution: matching timestamps are `time + k * period`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ScheduleSolution {
+    /// Earliest non-negative matching timestamp
+    pub time: u128,
+    /// Period between matching timestamps
+    pub period: u128,
+}
+
+impl ScheduleSolution {
+    /// Get earliest matching timestamp at or after `lower_bound`.
+    /// Returns `None` on overflow.
+    ///
+    /// # Arguments
+    ///
+    /// * `lower_bound` - Lower bound
+    pub fn earliest_from(&self, lower_bound: u128) -> Option<u128> {
+        if lower_bound <= self.time {
+            return Some(self.time);
+        }
+
+        (lower_bound - self.time)
+            .div_ceil(self.period)
+            .checked_mul(self.period)
+            .and_then(|x| x.checked_add(self.time))
+    }
 }
 
 /// Extract schedules from input string.
@@ -149,6 +232,17 @@ pub fn extract_schedules(input: &str) -> (usize, &str) {
     )
 }
 
+/// Time window where multiple buses depart.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DepartureWindow {
+    /// First departure time
+    pub start: usize,
+    /// Last departure time
+    pub end: usize,
+    /// Departing bus IDs, sorted
+    pub buses: Vec<usize>,
+}
+
 /// Bus scheduler
 pub struct Scheduler {
     data: Vec<Option<usize>>,
@@ -182,91 +276,214 @@ impl Scheduler {
     ///
     /// * `target` - Target time
     pub fn scan_buses_for_target_time(&self, target: usize) -> (usize, usize) {
-        self.data
-            .iter()
-            .filter_map(|b| b.and_then(|b| Some((b - target.rem_euclid(b), b))))
-            .min()
-            .unwrap()
+        self.rank_buses_for_target_time(target)[0]
     }
 
-    /// Compute successive departures time.
-    pub fn compute_successive_departures_time(&self) -> usize {
-        let nums = self.get_bus_numbers();
-        let offsets = self.get_offsets();
+    /// Rank buses by wait time for target time.
+    /// Returns `(wait_time, bus_id)` tuples, shortest wait first.
+    /// A bus departing at `target` has no wait time.
+    ///
+    /// # Arguments
+    ///
+    /// * `target` - Target time
+    pub fn rank_buses_for_target_time(&self, target: usize) -> Vec<(usize, usize)> {
+        let mut ranking: Vec<_> = self
+            .get_bus_numbers()
+            .into_iter()
+            .map(|b| (Self::first_departure(b, target) - target, b))
+            .collect();
+        ranking.sort_unstable();
+        ranking
+    }
 
-        let mut t = 0;
-        let mut last_lcm = 1;
-        let mut found = false;
+    /// Get next `count` departures of each bus, at or after `time`.
+    /// Returns `(bus_id, departures)` tuples, in list order.
+    ///
+    /// # Arguments
+    ///
+    /// * `time` - Start time
+    /// * `count` - Departure count per bus
+    pub fn next_departures(&self, time: usize, count: usize) -> Vec<(usize, Vec<usize>)> {
+        self.get_bus_numbers()
+            .into_iter()
+            .map(|b| {
+                let first = Self::first_departure(b, time);
+                (b, (0..count).map(|i| first + i * b).collect())
+            })
+            .collect()
+    }
 
-        while !found {
-            found = true;
+    /// Compute earliest time at or after `lower_bound` where all `buses` depart together.
+    ///
+    /// # Arguments
+    ///
+    /// * `buses` - Bus IDs, which must be in the schedule
+    /// * `lower_bound` - Lower bound
+    pub fn earliest_common_departure(
+        &self,
+        buses: &[usize],
+        lower_bound: u128,
+    ) -> Result<u128, DayError> {
+        let known = self.get_bus_numbers();
+        let constraints = buses
+            .iter()
+            .map(|&b| {
+                if known.contains(&b) {
+                    Ok(DepartureConstraint::new(b as u64, 0))
+                } else {
+                    Err(DayError::UnknownBus(b))
+                }
+            })
+            .collect::<Result<Vec<_>, _>>()?;
 
-            for idx in 1..nums.len() {
-                let n_a = nums[idx - 1];
-                let n_b = nums[idx];
-                let offset_a = offsets[idx - 1];
-                let offset_b = offsets[idx];
+        Self::earliest_time_from(&constraints, lower_bound)
+    }
 
-                let (nt, _, _) = Self::calculate_x(n_a, n_b, offset_a, offset_b, t, last_lcm);
+    /// Find windows of at most `duration` minutes where at least `min_buses`
+    /// distinct buses depart, for each window start in `range`.
+    ///
+    /// # Arguments
+    ///
+    /// * `range` - Window start range
+    /// * `min_buses` - Minimum distinct bus count
+    /// * `duration` - Maximum window duration, in minutes
+    pub fn departure_windows(
+        &self,
+        range: Range<usize>,
+        min_buses: usize,
+        duration: usize,
+    ) -> Vec<DepartureWindow> {
+        let limit = range.end.saturating_sub(1) + duration;
+        let mut departures: Vec<(usize, usize)> = self
+            .get_bus_numbers()
+            .into_iter()
+            .flat_map(|b| {
+                (Self::first_departure(b, range.start)..=limit)
+                    .step_by(b)
+                    .map(move |t| (t, b))
+            })
+            .collect();
+        departures.sort_unstable();
+
+        let mut windows = vec![];
+        let mut counts: BTreeMap<usize, usize> = BTreeMap::new();
+        let mut end = 0;
+        for (idx, &(start, _)) in departures.iter().enumerate() {
+            while end < departures.len() && departures[end].0 <= start + duration {
+                *counts.entry(departures[end].1).or_default() += 1;
+                end += 1;
+            }
 
-                t = nt;
-                last_lcm = nums[0..idx].iter().product();
+            let is_new_start = idx == 0 || departures[idx - 1].0 != start;
+            if is_new_start && range.contains(&start) && counts.len() >= min_buses {
+                windows.push(DepartureWindow {
+                    start,
+                    end: departures[end - 1].0,
+                    buses: counts.keys().copied().collect(),
+                });
+            }
 
-                if !Self::validate_t(&nums[0..idx], &offsets[0..idx], t) {
-                    // Try again
-                    found = false;
-                    break;
+            let bus = departures[idx].1;
+            if let Some(count) = counts.get_mut(&bus) {
+                *count -= 1;
+                if *count == 0 {
+                    counts.remove(&bus);
                 }
             }
         }
 
-        t
+        windows
     }
 
-    const fn calculate_x(
-        n_a: usize,
-        n_b: usize,
-        offset_a: usize,
-        offset_b: usize,
-        t_initial: usize,
-        t_increment: usize,
-    ) -> (usize, usize, usize) {
-        let mut t = t_initial;
-        let mut x;
-        let mut y;
-
-        loop {
-            x = (t + offset_a) / n_a;
-            y = (t + offset_b) / n_b;
-
-            // Try to validate equations
-            if y * n_b == t + offset_b && x * n_a == t + offset_a {
-                return (t, x, y);
-            }
-
-            t += t_increment;
+    /// Render timetable grid, one line per minute, with a `D` for each departure.
+    ///
+    /// # Arguments
+    ///
+    /// * `range` - Time range
+    pub fn timetable(&self, range: RangeInclusive<usize>) -> String {
+        let buses = self.get_bus_numbers();
+        let time_width = (range.end().to_string().len() + 2).max("time".len() + 3);
+        let labels: Vec<_> = buses.iter().map(|b| format!("bus {}", b)).collect();
+        let widths: Vec<_> = labels.iter().map(|l| (l.len() + 2).max(8)).collect();
+
+        let mut header = format!("{:<width$}", "time", width = time_width);
+        for (label, width) in labels.iter().zip(&widths) {
+            header.push_str(&format!("{:<width$}", label, width = width));
         }
-    }
 
-    fn validate_t(n: &[usize], a: &[usize], t: usize) -> bool {
-        for i in 0..n.len() {
-            if (t + a[i]) % n[i] != 0 {
-                return false;
+        let mut output = format!("{}\n", header.trim_end());
+        for time in range {
+            let mut line = format!("{:<width$}", time, width = time_width);
+            for (bus, width) in buses.iter().zip(&widths) {
+                let cell = if time % bus == 0 { 'D' } else { '.' };
+                line.push_str(&format!("  {:<width$}", cell, width = width - 2));
             }
+            output.push_str(line.trim_end());
+            output.push('\n');
         }
 
-        true
+        output
     }
 
-    fn get_offsets(&self) -> Vec<usize> {
+    fn first_departure(bus: usize, time: usize) -> usize {
+        time.div_ceil(bus) * bus
+    }
+
+    /// Get departure constraints, from bus list positions.
+    pub fn constraints(&self) -> Vec<DepartureConstraint> {
         self.data
             .iter()
             .enumerate()
-            .filter_map(|(idx, n)| n.map(|_| idx))
+            .filter_map(|(idx, n)| n.map(|n| DepartureConstraint::new(n as u64, idx as i64)))
             .collect()
     }
 
-    fn get_bus_numbers(&self) -> Vec<usize> {
+    /// Compute earliest time where each bus departs at its list position offset.
+    pub fn compute_successive_departures_time(&self) -> Result<u128, DayError> {
+        Self::solve(&self.constraints()).map(|s| s.time)
+    }
+
+    /// Solve departure constraints.
+    /// Bus IDs do not need to be pairwise coprime.
+    ///
+    /// # Arguments
+    ///
+    /// * `constraints` - Departure constraints
+    pub fn solve(constraints: &[DepartureConstraint]) -> Result<ScheduleSolution, DayError> {
+        let congruences: Vec<_> = constraints
+            .iter()
+            .map(|c| (-i128::from(c.offset), c.bus))
+            .collect();
+
+        try_crt(&congruences)
+            .map(|(time, period)| ScheduleSolution { time, period })
+            .map_err(|e| match e {
+                CrtError::ZeroModulus(idx) => DayError::InvalidBusId(idx),
+                CrtError::Unsatisfiable(idx) => DayError::UnsatisfiableSchedule {
+                    bus: constraints[idx].bus,
+                    offset: constraints[idx].offset,
+                },
+                CrtError::Overflow => DayError::Overflow,
+            })
+    }
+
+    /// Compute earliest time at or after `lower_bound` matching departure constraints.
+    ///
+    /// # Arguments
+    ///
+    /// * `constraints` - Departure constraints
+    /// * `lower_bound` - Lower bound
+    pub fn earliest_time_from(
+        constraints: &[DepartureConstraint],
+        lower_bound: u128,
+    ) -> Result<u128, DayError> {
+        Self::solve(constraints)?
+            .earliest_from(lower_bound)
+            .ok_or(DayError::Overflow)
+    }
+
+    /// Get bus IDs, in list order.
+    pub fn get_bus_numbers(&self) -> Vec<usize> {
         self.data.iter().filter_map(|n| *n).collect()
     }
 }
@@ -301,31 +518,186 @@ mod tests {
     fn test_compute_successive_departures_time() {
         assert_eq!(
             Scheduler::from_vec(vec![Some(17), None, Some(13), Some(19)])
-                .compute_successive_departures_time(),
+                .compute_successive_departures_time()
+                .unwrap(),
             3417
         );
         assert_eq!(
             Scheduler::from_vec(vec![Some(67), Some(7), Some(59), Some(61)])
-                .compute_successive_departures_time(),
+                .compute_successive_departures_time()
+                .unwrap(),
             754_018
         );
         assert_eq!(
             Scheduler::from_vec(vec![Some(67), None, Some(7), Some(59), Some(61)])
-                .compute_successive_departures_time(),
+                .compute_successive_departures_time()
+                .unwrap(),
             779_210
         );
         assert_eq!(
             Scheduler::from_vec(vec![Some(67), Some(7), None, Some(59), Some(61)])
-                .compute_successive_departures_time(),
+                .compute_successive_departures_time()
+                .unwrap(),
             1_261_476
         );
         assert_eq!(
             Scheduler::from_vec(vec![Some(1789), Some(37), Some(47), Some(1889)])
-                .compute_successive_departures_time(),
+                .compute_successive_departures_time()
+                .unwrap(),
             1_202_161_486
         );
     }
 
+    #[test]
+    fn test_solve_constraints() {
+        // Non-coprime bus IDs and negative offsets
+        let constraints = [
+            DepartureConstraint::new(4, 0),
+            DepartureConstraint::new(6, -2),
+            DepartureConstraint::new(9, 1),
+        ];
+        assert_eq!(
+            Scheduler::solve(&constraints),
+            Ok(ScheduleSolution {
+                time: 8,
+                period: 36
+            })
+        );
+        assert_eq!(Scheduler::earliest_time_from(&constraints, 8), Ok(8));
+        assert_eq!(Scheduler::earliest_time_from(&constraints, 9), Ok(44));
+        assert_eq!(
+            Scheduler::earliest_time_from(&constraints, 1_000_000_000_000_000_000_000),
+            Ok(1_000_000_000_000_000_000_016)
+        );
+        assert_eq!(
+            Scheduler::earliest_time_from(&constraints, u128::MAX),
+            Err(DayError::Overflow)
+        );
+    }
+
+    #[test]
+    fn test_solve_errors() {
+        let constraints = [
+            DepartureConstraint::new(4, 0),
+            DepartureConstraint::new(7, 3),
+            DepartureConstraint::new(6, 1),
+        ];
+        assert_eq!(
+            Scheduler::solve(&constraints),
+            Err(DayError::UnsatisfiableSchedule { bus: 6, offset: 1 })
+        );
+        assert_eq!(
+            Scheduler::from_vec(vec![Some(4), Some(0)]).compute_successive_departures_time(),
+            Err(DayError::InvalidBusId(1))
+        );
+
+        let large: Vec<_> = (0..4)
+            .map(|i| DepartureConstraint::new(u64::MAX - 2 * i, 0))
+            .collect();
+        assert_eq!(Scheduler::solve(&large), Err(DayError::Overflow));
+    }
+
+    #[test]
+    fn test_rank_and_next_departures() {
+        let (target, schedule_line) = extract_schedules(SAMPLE);
+        let scheduler = Scheduler::from_input(schedule_line);
+
+        assert_eq!(
+            scheduler.rank_buses_for_target_time(target),
+            vec![(5, 59), (6, 7), (10, 13), (11, 19), (22, 31)]
+        );
+        assert_eq!(
+            scheduler.next_departures(938, 3),
+            vec![
+                (7, vec![938, 945, 952]),
+                (13, vec![949, 962, 975]),
+                (59, vec![944, 1003, 1062]),
+                (31, vec![961, 992, 1023]),
+                (19, vec![950, 969, 988]),
+            ]
+        );
+
+        // Bus 7 departs exactly at 945
+        assert_eq!(
+            scheduler.rank_buses_for_target_time(945),
+            vec![(0, 7), (4, 13), (5, 19), (16, 31), (58, 59)]
+        );
+        assert_eq!(scheduler.scan_buses_for_target_time(945), (0, 7));
+    }
+
+    #[test]
+    fn test_earliest_common_departure() {
+        let (_, schedule_line) = extract_schedules(SAMPLE);
+        let scheduler = Scheduler::from_input(schedule_line);
+
+        assert_eq!(scheduler.earliest_common_departure(&[7, 19], 900), Ok(931));
+        assert_eq!(scheduler.earliest_common_departure(&[7, 19], 932), Ok(1064));
+        assert_eq!(scheduler.earliest_common_departure(&[], 12), Ok(12));
+        assert_eq!(
+            scheduler.earliest_common_departure(&[7, 11], 0),
+            Err(DayError::UnknownBus(11))
+        );
+    }
+
+    #[test]
+    fn test_departure_windows() {
+        let (_, schedule_line) = extract_schedules(SAMPLE);
+        let scheduler = Scheduler::from_input(schedule_line);
+
+        assert_eq!(
+            scheduler.departure_windows(929..950, 4, 6),
+            vec![
+                DepartureWindow {
+                    start: 930,
+                    end: 936,
+                    buses: vec![7, 13, 19, 31]
+                },
+                DepartureWindow {
+                    start: 944,
+                    end: 950,
+                    buses: vec![7, 13, 19, 59]
+                },
+            ]
+        );
+        let windows = scheduler.departure_windows(929..950, 3, 6);
+        let starts: Vec<_> = windows.iter().map(|w| w.start).collect();
+        assert_eq!(starts, vec![930, 931, 944, 945, 949]);
+        assert_eq!(windows[4].end, 952);
+        assert_eq!(scheduler.departure_windows(929..950, 6, 100), vec![]);
+    }
+
+    #[test]
+    fn test_timetable() {
+        let (_, schedule_line) = extract_schedules(SAMPLE);
+        let scheduler = Scheduler::from_input(schedule_line);
+
+        let timetable = scheduler.timetable(929..=949);
+        let mut lines = timetable.lines();
+        assert_eq!(
+            lines.next(),
+            Some("time   bus 7   bus 13  bus 59  bus 31  bus 19")
+        );
+        assert_eq!(
+            lines.next(),
+            Some("929      .       .       .       .       .")
+        );
+        assert_eq!(
+            lines.next(),
+            Some("930      .       .       .       D       .")
+        );
+        assert_eq!(
+            lines.next(),
+            Some("931      D       .       .       .       D")
+        );
+        assert_eq!(timetable.lines().count(), 22);
+
+        let timetable = scheduler.timetable(1_068_788..=1_068_788);
+        assert_eq!(
+            timetable,
+            "time     bus 7   bus 13  bus 59  bus 31  bus 19\n1068788    D       .       .       .       D\n"
+        );
+    }
+
     #[test]
     fn test_run_ex1() {
         assert_eq!(run_ex1(), EX1_OUTPUT);
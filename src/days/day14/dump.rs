@@ -0,0 +1,275 @@
+//! Memory dumps and comparisons
+
+use std::fmt;
+
+use super::{AddressPattern, BitmaskMemory, DayError, Decoder, Instruction};
+
+/// Maximum floating bit count of patterns expanded address by address in memory dumps.
+pub const DUMP_EXPAND_BITS: u32 = 4;
+
+/// Memory dump number format
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DumpFormat {
+    /// Decimal
+    Decimal,
+    /// Hexadecimal, zero-padded to the word width
+    Hex,
+    /// Binary, zero-padded to the word width
+    Binary,
+}
+
+impl DumpFormat {
+    /// Format number.
+    ///
+    /// # Arguments
+    ///
+    /// * `value` - Number
+    /// * `width` - Word width
+    pub fn format(self, value: u64, width: u32) -> String {
+        match self {
+            Self::Decimal => value.to_string(),
+            Self::Hex => format!("0x{:0w$x}", value, w = width.div_ceil(4) as usize),
+            Self::Binary => format!("{:0w$b}", value, w = width as usize),
+        }
+    }
+}
+
+/// Addresses holding different values in two memories.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MemoryDiff {
+    /// Address pattern
+    pub pattern: AddressPattern,
+    /// Word width
+    pub width: u32,
+    /// Value in the first memory
+    pub left: u64,
+    /// Value in the second memory
+    pub right: u64,
+}
+
+impl fmt::Display for MemoryDiff {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.pattern.floating() == 0 {
+            write!(f, "mem[{}]: ", self.pattern.fixed())?;
+        } else {
+            write!(
+                f,
+                "mem[{}] ({} addresses): ",
+                self.pattern.to_string_with_width(self.width),
+                self.pattern.count()
+            )?;
+        }
+
+        write!(f, "{} | {}", self.left, self.right)
+    }
+}
+
+/// Memories after running a program with both decoder versions.
+#[derive(Debug)]
+pub struct DecoderComparison {
+    /// Memory using version 1 decoder
+    pub v1: BitmaskMemory,
+    /// Memory using version 2 decoder
+    pub v2: BitmaskMemory,
+    /// Differences between both memories
+    pub diff: Vec<MemoryDiff>,
+}
+
+impl DecoderComparison {
+    /// Run program with both decoder versions, and compare resulting memories.
+    ///
+    /// # Arguments
+    ///
+    /// * `program` - Instructions
+    /// * `width` - Word width
+    pub fn run(program: &[Instruction], width: u32) -> Result<Self, DayError> {
+        let mut v1 = BitmaskMemory::with_width(width);
+        let mut v2 = BitmaskMemory::with_width(width);
+        v1.run(program, Decoder::V1)?;
+        v2.run(program, Decoder::V2)?;
+
+        let diff = v1.diff(&v2);
+        Ok(Self { v1, v2, diff })
+    }
+}
+
+impl BitmaskMemory {
+    /// Render memory content as `address: value` lines, sorted by address.
+    /// Patterns with more than `DUMP_EXPAND_BITS` floating bits are listed
+    /// as one line, with `X` for floating address bits.
+    ///
+    /// # Arguments
+    ///
+    /// * `format` - Number format
+    pub fn dump(&self, format: DumpFormat) -> String {
+        let width = self.width();
+        let mut rows: Vec<(u64, String)> = self
+            .patterns()
+            .iter()
+            .flat_map(|&(pattern, value)| {
+                Self::expand(pattern, width)
+                    .into_iter()
+                    .map(move |(key, label)| {
+                        (
+                            key,
+                            format!(
+                                "{}: {}",
+                                label.unwrap_or_else(|| format.format(key, width)),
+                                format.format(value, width)
+                            ),
+                        )
+                    })
+            })
+            .collect();
+
+        rows.sort();
+        rows.into_iter().map(|(_, line)| line + "\n").collect()
+    }
+
+    /// Get addresses holding different values in two memories.
+    /// Addresses never written hold zero.
+    ///
+    /// # Arguments
+    ///
+    /// * `other` - Other memory
+    pub fn diff(&self, other: &Self) -> Vec<MemoryDiff> {
+        let mut diffs = vec![];
+        let mut push = |pattern, left, right| {
+            if left != right {
+                diffs.push(MemoryDiff {
+                    pattern,
+                    width: self.width(),
+                    left,
+                    right,
+                });
+            }
+        };
+
+        for &(p, v) in self.patterns() {
+            let mut unwritten = vec![p];
+            for &(q, w) in other.patterns() {
+                if let Some(common) = p.intersection(&q) {
+                    push(common, v, w);
+                }
+                unwritten = unwritten.iter().flat_map(|x| x.subtract(&q)).collect();
+            }
+            for x in unwritten {
+                push(x, v, 0);
+            }
+        }
+
+        for &(q, w) in other.patterns() {
+            let mut unwritten = vec![q];
+            for &(p, _) in self.patterns() {
+                unwritten = unwritten.iter().flat_map(|x| x.subtract(&p)).collect();
+            }
+            for x in unwritten {
+                push(x, 0, w);
+            }
+        }
+
+        diffs.sort_by_key(|d| (d.pattern.fixed(), d.pattern.floating()));
+        diffs
+    }
+
+    /// Get sort keys of a pattern dump lines, with a label for unexpanded patterns.
+    fn expand(pattern: AddressPattern, width: u32) -> Vec<(u64, Option<String>)> {
+        if pattern.floating().count_ones() <= DUMP_EXPAND_BITS {
+            pattern.addresses().map(|a| (a, None)).collect()
+        } else {
+            vec![(
+                pattern.fixed(),
+                Some(format!(
+                    "{} ({} addresses)",
+                    pattern.to_string_with_width(width),
+                    pattern.count()
+                )),
+            )]
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SAMPLE_2: &str = "mask = 000000000000000000000000000000X1001X
+    mem[42] = 100
+    mask = 00000000000000000000000000000000X0XX
+    mem[26] = 1";
+
+    #[test]
+    fn test_dump() {
+        let program = Instruction::parse_program(SAMPLE_2).unwrap();
+        let mut memory = BitmaskMemory::with_width(36);
+        memory.run(&program, Decoder::V2).unwrap();
+
+        assert_eq!(
+            memory.dump(DumpFormat::Decimal),
+            "16: 1\n17: 1\n18: 1\n19: 1\n24: 1\n25: 1\n26: 1\n27: 1\n58: 100\n59: 100\n"
+        );
+        assert!(memory
+            .dump(DumpFormat::Hex)
+            .starts_with("0x000000010: 0x000000001\n"));
+        assert!(memory.dump(DumpFormat::Binary).ends_with(
+            "000000000000000000000000000000111011: 000000000000000000000000000001100100\n"
+        ));
+
+        let mut memory = BitmaskMemory::with_width(8);
+        memory
+            .run(
+                &Instruction::parse_program("mask = XXXXX1XX\nmem[3] = 200").unwrap(),
+                Decoder::V2,
+            )
+            .unwrap();
+        assert_eq!(
+            memory.dump(DumpFormat::Hex),
+            "XXXXX1XX (128 addresses): 0xc8\n"
+        );
+    }
+
+    #[test]
+    fn test_compare_decoders() {
+        let program = Instruction::parse_program(SAMPLE_2).unwrap();
+        let comparison = DecoderComparison::run(&program, 36).unwrap();
+
+        // Version 1 masks values: 100 becomes 50 at 42, 1 stays 1 at 26
+        assert_eq!(comparison.v1.get(42), 50);
+        assert_eq!(comparison.v1.get(26), 1);
+
+        let lines: Vec<String> = comparison.diff.iter().map(MemoryDiff::to_string).collect();
+        assert_eq!(
+            lines,
+            vec![
+                "mem[00000000000000000000000000000001X000] (2 addresses): 0 | 1",
+                "mem[00000000000000000000000000000001X0X1] (4 addresses): 0 | 1",
+                "mem[18]: 0 | 1",
+                "mem[42]: 50 | 0",
+                "mem[00000000000000000000000000000011101X] (2 addresses): 0 | 100",
+            ]
+        );
+        assert!(comparison.v1.diff(&comparison.v1).is_empty());
+    }
+
+    #[test]
+    fn test_diff_patterns() {
+        let mut left = BitmaskMemory::with_width(8);
+        let mut right = BitmaskMemory::with_width(8);
+        left.write(AddressPattern::new(0, 0b1111_0000), 3);
+        right.write(AddressPattern::new(0, 0b1100_0001), 3);
+
+        let lines: Vec<String> = left
+            .diff(&right)
+            .iter()
+            .map(MemoryDiff::to_string)
+            .collect();
+        assert_eq!(
+            lines,
+            vec![
+                "mem[XX000001] (4 addresses): 0 | 3",
+                "mem[XXX10000] (8 addresses): 3 | 0",
+                "mem[XX100000] (4 addresses): 3 | 0",
+            ]
+        );
+    }
+}
@@ -112,17 +112,22 @@
 //!
 //! Execute the initialization program using an emulator for a version 2 decoder chip. What is the sum of all values left in memory after it completes?
 
-use std::collections::HashMap;
+use std::fmt;
 
-use once_cell::sync::Lazy;
-use regex::Regex;
+use thiserror::Error;
 
-const INPUT_VALUES: &str = include_str!("input.txt");
+mod dump;
+mod pattern;
+mod program;
+
+pub use self::dump::{DecoderComparison, DumpFormat, MemoryDiff, DUMP_EXPAND_BITS};
+pub use self::pattern::{AddressPattern, PatternAddresses};
+pub use self::program::{Decoder, Instruction, TraceEntry};
 
-static RGX_MASK: Lazy<Regex> = Lazy::new(|| Regex::new(r"mask = (?P<mask>[01X]{36})").unwrap());
+const INPUT_VALUES: &str = include_str!("input.txt");
 
-static RGX_MEM: Lazy<Regex> =
-    Lazy::new(|| Regex::new(r"mem\[(?P<mem_idx>\d+)\] = (?P<mem_value>\d+)").unwrap());
+/// Default word width, in bits.
+pub const DEFAULT_WIDTH: u32 = 36;
 
 /// Part one answer.
 pub fn run_ex1() -> usize {
@@ -131,7 +136,7 @@ pub fn run_ex1() -> usize {
         mem.parse_line(l, false);
     }
 
-    mem.get_memory_sum()
+    mem.get_memory_sum() as usize
 }
 
 /// Part two answer.
@@ -141,162 +146,372 @@ pub fn run_ex2() -> usize {
         mem.parse_line(l, true);
     }
 
-    mem.get_memory_sum()
+    mem.get_memory_sum() as usize
 }
 
-/// Bitmask memory
-#[derive(Debug, Default)]
-pub struct BitmaskMemory {
-    current_mask: Vec<Option<bool>>,
-    memory: HashMap<usize, usize>,
+/// Day error.
+#[derive(Debug, Error, PartialEq, Eq)]
+pub enum DayError {
+    /// Instruction parse error
+    #[error("Invalid instruction on line {line}: '{input}'")]
+    InvalidInstruction {
+        /// Line number (starting at 1)
+        line: usize,
+        /// Line value
+        input: String,
+    },
+    /// Mask width does not match memory width
+    #[error("Mask should be {expected} bits wide, got {found}")]
+    WidthMismatch {
+        /// Memory width
+        expected: u32,
+        /// Mask width
+        found: u32,
+    },
 }
 
-impl BitmaskMemory {
-    /// Creates a new bitmask memory.
-    pub fn new() -> Self {
+/// Get bits set for a word width.
+///
+/// # Arguments
+///
+/// * `width` - Word width, from 1 to 64 bits
+pub const fn width_bits(width: u32) -> u64 {
+    u64::MAX >> (64 - width)
+}
+
+/// Bitmask, stored as bits overwritten with ones and zeros, other bits being floating.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Mask {
+    width: u32,
+    ones: u64,
+    zeros: u64,
+}
+
+impl Mask {
+    /// Creates a mask with only floating bits.
+    ///
+    /// # Arguments
+    ///
+    /// * `width` - Word width, from 1 to 64 bits
+    pub fn floating(width: u32) -> Self {
+        assert!(
+            (1..=64).contains(&width),
+            "Width should be between 1 and 64 bits"
+        );
+
         Self {
-            current_mask: vec![None; 36],
-            memory: HashMap::new(),
+            width,
+            ones: 0,
+            zeros: 0,
         }
     }
 
-    /// Set mask from str.
+    /// Parse mask from str, most significant bit first.
+    /// The mask width is its length.
     ///
     /// # Arguments
     ///
     /// * `mask` - Mask
-    pub fn set_mask_from_str(&mut self, mask: &str) {
-        if mask.len() != 36 {
-            panic!("Mask str should be 36 characters");
+    pub fn parse(mask: &str) -> Option<Self> {
+        if mask.is_empty() || mask.len() > 64 {
+            return None;
         }
 
-        for (idx, c) in mask.chars().enumerate() {
+        let mut output = Self::floating(mask.len() as u32);
+        for (idx, c) in mask.chars().rev().enumerate() {
             match c {
-                'X' => self.current_mask[idx] = None,
-                '0' => self.current_mask[idx] = Some(false),
-                '1' => self.current_mask[idx] = Some(true),
-                _ => panic!("Invalid mask character: {}", c),
+                'X' => (),
+                '0' => output.zeros |= 1 << idx,
+                '1' => output.ones |= 1 << idx,
+                _ => return None,
             }
         }
 
-        // Reverse mask
-        self.current_mask.reverse();
+        Some(output)
     }
 
-    /// Set value in memory using value mask.
+    /// Get mask width.
+    pub const fn width(&self) -> u32 {
+        self.width
+    }
+
+    /// Get bits overwritten with ones.
+    pub const fn ones(&self) -> u64 {
+        self.ones
+    }
+
+    /// Get bits overwritten with zeros.
+    pub const fn zeros(&self) -> u64 {
+        self.zeros
+    }
+
+    /// Get floating bits.
+    pub const fn floating_bits(&self) -> u64 {
+        width_bits(self.width) & !(self.ones | self.zeros)
+    }
+
+    /// Get bit value, `None` if floating.
+    ///
+    /// # Arguments
+    ///
+    /// * `idx` - Bit index, from least significant bit
+    pub const fn bit(&self, idx: u32) -> Option<bool> {
+        if self.ones >> idx & 1 == 1 {
+            Some(true)
+        } else if self.zeros >> idx & 1 == 1 {
+            Some(false)
+        } else {
+            None
+        }
+    }
+
+    /// Apply mask on a value (version 1 decoder).
     ///
     /// # Arguments
     ///
-    /// * `addr` - Address
     /// * `value` - Value
-    pub fn set_value_in_memory_using_value_mask(&mut self, addr: usize, value: usize) -> usize {
-        let output = Self::apply_mask_on_value(&self.current_mask, value);
-        self.memory.insert(addr, output);
-        output
+    pub const fn apply_on_value(&self, value: u64) -> u64 {
+        (value | self.ones) & !self.zeros & width_bits(self.width)
     }
 
-    /// Set value in memory using value mask.
+    /// Apply mask on an address (version 2 decoder).
     ///
     /// # Arguments
     ///
     /// * `addr` - Address
-    /// * `value` - Value
-    pub fn set_value_in_memory_using_address_mask(&mut self, addr: usize, value: usize) {
-        let addresses = Self::apply_mask_on_address(&self.current_mask, addr);
-        for addr in addresses {
-            self.memory.insert(addr, value);
+    pub const fn apply_on_address(&self, addr: u64) -> AddressPattern {
+        AddressPattern::new(
+            (addr | self.ones) & width_bits(self.width),
+            self.floating_bits(),
+        )
+    }
+}
+
+impl fmt::Display for Mask {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for idx in (0..self.width).rev() {
+            let c = match self.bit(idx) {
+                Some(true) => '1',
+                Some(false) => '0',
+                None => 'X',
+            };
+            write!(f, "{}", c)?;
         }
+
+        Ok(())
     }
+}
 
-    /// Get memory sum.
-    pub fn get_memory_sum(&self) -> usize {
-        self.memory.values().filter(|&&x| x != 0).sum()
+/// Bitmask memory.
+///
+/// Writes are stored as disjoint address patterns: each write removes the
+/// addresses it overwrites from previous patterns, splitting them if needed.
+/// Floating writes never enumerate their addresses, so masks with many
+/// floating bits stay cheap.
+#[derive(Debug)]
+pub struct BitmaskMemory {
+    current_mask: Mask,
+    memory: Vec<(AddressPattern, u64)>,
+}
+
+impl Default for BitmaskMemory {
+    fn default() -> Self {
+        Self::new()
     }
+}
 
-    /// Parse an input line.
+impl BitmaskMemory {
+    /// Creates a new bitmask memory, with 36-bit words.
+    pub fn new() -> Self {
+        Self::with_width(DEFAULT_WIDTH)
+    }
+
+    /// Creates a new bitmask memory.
     ///
     /// # Arguments
     ///
-    /// * `input` - Input line
-    /// * `use_address_mask` - Use address mask
-    pub fn parse_line(&mut self, input: &str, use_address_mask: bool) {
-        if let Some(captures) = RGX_MASK.captures(input.trim()) {
-            let mask = captures.name("mask").unwrap().as_str();
-            self.set_mask_from_str(mask);
-        } else {
-            let captures = RGX_MEM.captures(input.trim()).unwrap();
-            let mem_idx = captures
-                .name("mem_idx")
-                .map(|x| x.as_str().parse::<usize>().unwrap())
-                .unwrap();
-            let mem_value = captures
-                .name("mem_value")
-                .map(|x| x.as_str().parse::<usize>().unwrap())
-                .unwrap();
-            if use_address_mask {
-                self.set_value_in_memory_using_address_mask(mem_idx, mem_value);
-            } else {
-                self.set_value_in_memory_using_value_mask(mem_idx, mem_value);
-            }
+    /// * `width` - Word width, from 1 to 64 bits
+    pub fn with_width(width: u32) -> Self {
+        Self {
+            current_mask: Mask::floating(width),
+            memory: vec![],
         }
     }
 
-    fn apply_mask_on_value(mask: &[Option<bool>], value: usize) -> usize {
-        let mut output = value;
-        for (idx, x) in mask.iter().enumerate() {
-            match x {
-                Some(true) => output |= 1 << idx,
-                Some(false) => output &= !(1 << idx),
-                None => (),
-            }
+    /// Get word width.
+    pub const fn width(&self) -> u32 {
+        self.current_mask.width
+    }
+
+    /// Get current mask.
+    pub const fn mask(&self) -> &Mask {
+        &self.current_mask
+    }
+
+    /// Set mask from str.
+    ///
+    /// # Arguments
+    ///
+    /// * `mask` - Mask
+    pub fn set_mask_from_str(&mut self, mask: &str) {
+        if mask.len() != self.width() as usize {
+            panic!("Mask str should be {} characters", self.width());
         }
 
+        self.current_mask = Mask::parse(mask).unwrap_or_else(|| panic!("Invalid mask: {}", mask));
+    }
+
+    /// Set value in memory using value mask.
+    ///
+    /// # Arguments
+    ///
+    /// * `addr` - Address
+    /// * `value` - Value
+    pub fn set_value_in_memory_using_value_mask(&mut self, addr: u64, value: u64) -> u64 {
+        let output = self.current_mask.apply_on_value(value);
+        let addr = addr & width_bits(self.width());
+        self.write(AddressPattern::exact(addr), output);
         output
     }
 
-    fn apply_mask_on_address(mask: &[Option<bool>], addr: usize) -> Vec<usize> {
-        let mut addresses = vec![];
-        let mut first_value = addr;
+    /// Set value in memory using address mask.
+    ///
+    /// # Arguments
+    ///
+    /// * `addr` - Address
+    /// * `value` - Value
+    pub fn set_value_in_memory_using_address_mask(&mut self, addr: u64, value: u64) {
+        let pattern = self.current_mask.apply_on_address(addr);
+        self.write(pattern, value & width_bits(self.width()));
+    }
 
-        for (idx, x) in mask.iter().enumerate() {
-            match x {
-                Some(true) => first_value |= 1 << idx,
-                Some(false) => (),
-                None => first_value &= !(1 << idx),
-            }
+    /// Write value on every address matched by a pattern.
+    ///
+    /// # Arguments
+    ///
+    /// * `pattern` - Address pattern
+    /// * `value` - Value
+    pub fn write(&mut self, pattern: AddressPattern, value: u64) {
+        let previous = std::mem::take(&mut self.memory);
+        for (existing, existing_value) in previous {
+            self.memory.extend(
+                existing
+                    .subtract(&pattern)
+                    .into_iter()
+                    .map(|p| (p, existing_value)),
+            );
         }
-        addresses.push(first_value);
 
-        let bits: Vec<usize> = mask
+        self.memory.push((pattern, value));
+    }
+
+    /// Get value at address.
+    ///
+    /// # Arguments
+    ///
+    /// * `addr` - Address
+    pub fn get(&self, addr: u64) -> u64 {
+        self.memory
             .iter()
-            .enumerate()
-            .filter_map(|(idx, x)| if x.is_none() { Some(idx) } else { None })
-            .collect();
-        for num in Self::generate_numbers_for_bits(&bits) {
-            addresses.push(first_value + num);
-        }
+            .find(|(p, _)| p.contains(addr))
+            .map_or(0, |&(_, v)| v)
+    }
 
-        addresses
+    /// Get disjoint written patterns with their values.
+    pub fn patterns(&self) -> &[(AddressPattern, u64)] {
+        &self.memory
     }
 
-    #[allow(clippy::cast_possible_truncation)]
-    fn generate_numbers_for_bits(bits: &[usize]) -> Vec<usize> {
-        let mut output = vec![];
+    /// Get memory sum.
+    pub fn get_memory_sum(&self) -> u128 {
+        self.memory
+            .iter()
+            .map(|(p, v)| p.count() * u128::from(*v))
+            .sum()
+    }
 
-        let max_v = 2_u64.pow(bits.len() as u32);
-        for i in 1..max_v {
-            let mut number = 0;
-            let bin = format!("{:b}", i);
-            for (idx, n) in bin.chars().rev().enumerate() {
-                if n == '1' {
-                    number += 2_u64.pow(bits[idx] as u32);
+    /// Execute an instruction.
+    ///
+    /// # Arguments
+    ///
+    /// * `instruction` - Instruction
+    /// * `decoder` - Decoder version
+    pub fn execute(&mut self, instruction: &Instruction, decoder: Decoder) -> Result<(), DayError> {
+        match *instruction {
+            Instruction::Mask(mask) => {
+                if mask.width() != self.width() {
+                    return Err(DayError::WidthMismatch {
+                        expected: self.width(),
+                        found: mask.width(),
+                    });
                 }
+                self.current_mask = mask;
             }
-
-            output.push(number as usize);
+            Instruction::Mem { addr, value } => match decoder {
+                Decoder::V1 => {
+                    self.set_value_in_memory_using_value_mask(addr, value);
+                }
+                Decoder::V2 => self.set_value_in_memory_using_address_mask(addr, value),
+            },
         }
 
-        output
+        Ok(())
+    }
+
+    /// Execute a program.
+    ///
+    /// # Arguments
+    ///
+    /// * `program` - Instructions
+    /// * `decoder` - Decoder version
+    pub fn run(&mut self, program: &[Instruction], decoder: Decoder) -> Result<(), DayError> {
+        program.iter().try_for_each(|i| self.execute(i, decoder))
+    }
+
+    /// Execute a program, recording each instruction with the mask in effect.
+    ///
+    /// # Arguments
+    ///
+    /// * `program` - Instructions
+    /// * `decoder` - Decoder version
+    pub fn trace(
+        &mut self,
+        program: &[Instruction],
+        decoder: Decoder,
+    ) -> Result<Vec<TraceEntry>, DayError> {
+        program
+            .iter()
+            .enumerate()
+            .map(|(index, &instruction)| {
+                self.execute(&instruction, decoder)?;
+                Ok(TraceEntry {
+                    index,
+                    instruction,
+                    mask: self.current_mask,
+                    decoder,
+                })
+            })
+            .collect()
+    }
+
+    /// Parse an input line.
+    ///
+    /// # Arguments
+    ///
+    /// * `input` - Input line
+    /// * `use_address_mask` - Use address mask
+    pub fn parse_line(&mut self, input: &str, use_address_mask: bool) {
+        let instruction = Instruction::parse(input)
+            .unwrap_or_else(|| panic!("Invalid instruction: {}", input.trim()));
+        let decoder = if use_address_mask {
+            Decoder::V2
+        } else {
+            Decoder::V1
+        };
+
+        if let Err(e) = self.execute(&instruction, decoder) {
+            panic!("{}", e);
+        }
     }
 }
 
@@ -326,21 +541,21 @@ mod tests {
         let mask = lines.next().unwrap();
 
         memory.parse_line(mask, false);
-        assert_eq!(memory.current_mask[0], None);
-        assert_eq!(memory.current_mask[1], Some(false));
-        assert_eq!(memory.current_mask[6], Some(true));
+        assert_eq!(memory.current_mask.bit(0), None);
+        assert_eq!(memory.current_mask.bit(1), Some(false));
+        assert_eq!(memory.current_mask.bit(6), Some(true));
 
         let mem8 = lines.next().unwrap();
         memory.parse_line(mem8, false);
-        assert_eq!(memory.memory[&8], 73);
+        assert_eq!(memory.get(8), 73);
 
         let mem7 = lines.next().unwrap();
         memory.parse_line(mem7, false);
-        assert_eq!(memory.memory[&7], 101);
+        assert_eq!(memory.get(7), 101);
 
         let mem8 = lines.next().unwrap();
         memory.parse_line(mem8, false);
-        assert_eq!(memory.memory[&8], 64);
+        assert_eq!(memory.get(8), 64);
 
         assert_eq!(memory.get_memory_sum(), 165);
     }
@@ -356,19 +571,113 @@ mod tests {
         memory.parse_line(lines.next().unwrap(), true);
         memory.parse_line(lines.next().unwrap(), true);
         for x in &[26, 27, 58, 59] {
-            assert_eq!(memory.memory[x], 100);
+            assert_eq!(memory.get(*x), 100);
         }
 
         // Parse second mask
         memory.parse_line(lines.next().unwrap(), true);
         memory.parse_line(lines.next().unwrap(), true);
         for x in &[16, 17, 18, 19, 24, 25, 26, 27] {
-            assert_eq!(memory.memory[x], 1);
+            assert_eq!(memory.get(*x), 1);
         }
 
         assert_eq!(memory.get_memory_sum(), 208);
     }
 
+    #[test]
+    fn test_width() {
+        let mut memory = BitmaskMemory::with_width(8);
+        memory.parse_line("mask = 1XXXXX0X", false);
+        memory.parse_line("mem[300] = 1023", false);
+        assert_eq!(memory.get(44), 0b1111_1101);
+        assert_eq!(memory.get_memory_sum(), 253);
+
+        let mut memory = BitmaskMemory::with_width(64);
+        let mask = format!("1{}", "X".repeat(63));
+        memory.parse_line(&format!("mask = {}", mask), true);
+        memory.parse_line("mem[0] = 3", true);
+        assert_eq!(memory.get(u64::MAX), 3);
+        assert_eq!(memory.get(0), 0);
+        assert_eq!(memory.get_memory_sum(), 3 << 63);
+    }
+
+    #[test]
+    #[should_panic(expected = "Mask should be 8 bits wide, got 36")]
+    fn test_width_mismatch() {
+        BitmaskMemory::with_width(8).parse_line(SAMPLE.lines().next().unwrap(), false);
+    }
+
+    #[test]
+    fn test_many_floating_bits() {
+        let mut memory = BitmaskMemory::new();
+        memory.parse_line(&format!("mask = 000000{}", "X".repeat(30)), true);
+        memory.parse_line("mem[0] = 5", true);
+        memory.parse_line(&format!("mask = 0000000{}", "X".repeat(29)), true);
+        memory.parse_line("mem[0] = 2", true);
+        memory.parse_line(&format!("mask = 1{}", "0".repeat(35)), true);
+        memory.parse_line("mem[7] = 1000", true);
+
+        // Half of the addresses hold 5, the other half 2, and one address holds 1000
+        assert_eq!(memory.get_memory_sum(), (7 << 29) | 1000);
+        assert_eq!(memory.get(1 << 29), 5);
+        assert_eq!(memory.get(12), 2);
+        assert_eq!(memory.get((1 << 35) | 7), 1000);
+
+        // Clearing even addresses splits previous writes in disjoint patterns
+        memory.parse_line(&format!("mask = 000000{}0", "X".repeat(29)), true);
+        memory.parse_line("mem[0] = 0", true);
+        assert_eq!(memory.get_memory_sum(), (7 << 28) | 1000);
+        assert_eq!(memory.patterns().len(), 4);
+    }
+
+    #[test]
+    fn test_trace() {
+        let program = Instruction::parse_program(SAMPLE).unwrap();
+        let mut memory = BitmaskMemory::new();
+        let trace = memory.trace(&program, Decoder::V1).unwrap();
+        assert_eq!(trace.len(), 4);
+        assert_eq!(
+            trace[0].to_string(),
+            "#0 mask = XXXXXXXXXXXXXXXXXXXXXXXXXXXXX1XXXX0X"
+        );
+        assert_eq!(
+            trace[1].to_string(),
+            indoc::indoc! {"
+                #1 mem[8] = 11
+                  value:  000000000000000000000000000000001011  (decimal 11)
+                  mask:   XXXXXXXXXXXXXXXXXXXXXXXXXXXXX1XXXX0X
+                  result: 000000000000000000000000000001001001  (decimal 73)
+                  diff:                                ^    ^"}
+        );
+        assert!(trace[2].to_string().ends_with("(decimal 101)\n  diff:"));
+
+        let program = Instruction::parse_program(SAMPLE_2).unwrap();
+        let trace = BitmaskMemory::new().trace(&program, Decoder::V2).unwrap();
+        assert_eq!(
+            trace[1].to_string(),
+            indoc::indoc! {"
+                #1 mem[42] = 100
+                  address: 000000000000000000000000000000101010  (decimal 42)
+                  mask:    000000000000000000000000000000X1001X
+                  result:  000000000000000000000000000000X1101X  (4 addresses)
+                  diff:                                  X^   X"}
+        );
+    }
+
+    #[test]
+    fn test_execute_errors() {
+        let mut memory = BitmaskMemory::with_width(8);
+        let mask = Instruction::Mask(Mask::parse("X1").unwrap());
+        assert_eq!(
+            memory.execute(&mask, Decoder::V1),
+            Err(DayError::WidthMismatch {
+                expected: 8,
+                found: 2
+            })
+        );
+        assert_eq!(memory.mask(), &Mask::floating(8));
+    }
+
     #[test]
     fn test_run_ex1() {
         assert_eq!(run_ex1(), EX1_OUTPUT);
@@ -0,0 +1,199 @@
+//! Ternary address patterns
+
+/// Set of addresses sharing fixed bits, other bits being floating.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct AddressPattern {
+    fixed: u64,
+    floating: u64,
+}
+
+impl AddressPattern {
+    /// Creates a new pattern.
+    /// Fixed bits set on floating positions are ignored.
+    ///
+    /// # Arguments
+    ///
+    /// * `fixed` - Fixed bit values
+    /// * `floating` - Floating bits
+    pub const fn new(fixed: u64, floating: u64) -> Self {
+        Self {
+            fixed: fixed & !floating,
+            floating,
+        }
+    }
+
+    /// Creates a pattern matching a single address.
+    ///
+    /// # Arguments
+    ///
+    /// * `address` - Address
+    pub const fn exact(address: u64) -> Self {
+        Self::new(address, 0)
+    }
+
+    /// Get fixed bit values.
+    pub const fn fixed(&self) -> u64 {
+        self.fixed
+    }
+
+    /// Get floating bits.
+    pub const fn floating(&self) -> u64 {
+        self.floating
+    }
+
+    /// Get matched address count.
+    pub fn count(&self) -> u128 {
+        1 << self.floating.count_ones()
+    }
+
+    /// Check if pattern matches an address.
+    ///
+    /// # Arguments
+    ///
+    /// * `address` - Address
+    pub const fn contains(&self, address: u64) -> bool {
+        address & !self.floating == self.fixed
+    }
+
+    /// Get pattern matching addresses matched by both patterns, if any.
+    ///
+    /// # Arguments
+    ///
+    /// * `other` - Other pattern
+    pub const fn intersection(&self, other: &Self) -> Option<Self> {
+        if (self.fixed ^ other.fixed) & !(self.floating | other.floating) == 0 {
+            Some(Self::new(
+                self.fixed | other.fixed,
+                self.floating & other.floating,
+            ))
+        } else {
+            None
+        }
+    }
+
+    /// Get disjoint patterns matching addresses of `self` which are not matched by `other`.
+    ///
+    /// # Arguments
+    ///
+    /// * `other` - Other pattern
+    pub fn subtract(&self, other: &Self) -> Vec<Self> {
+        if self.intersection(other).is_none() {
+            return vec![*self];
+        }
+
+        // Fix each bit floating here but fixed in `other`, one at a time:
+        // first to the value `other` does not match, then to the one it does.
+        let mut output = vec![];
+        let mut current = *self;
+        let mut splits = self.floating & !other.floating;
+        while splits != 0 {
+            let bit = splits & splits.wrapping_neg();
+            splits &= !bit;
+
+            let floating = current.floating & !bit;
+            output.push(Self::new(current.fixed | (!other.fixed & bit), floating));
+            current = Self::new(current.fixed | (other.fixed & bit), floating);
+        }
+
+        output
+    }
+
+    /// Iterate over matched addresses, in increasing order.
+    pub fn addresses(&self) -> PatternAddresses {
+        PatternAddresses {
+            pattern: *self,
+            subset: Some(0),
+        }
+    }
+
+    /// Format pattern on `width` bits, most significant bit first, with `X` for floating bits.
+    ///
+    /// # Arguments
+    ///
+    /// * `width` - Bit width
+    pub fn to_string_with_width(&self, width: u32) -> String {
+        (0..width)
+            .rev()
+            .map(|idx| {
+                if self.floating >> idx & 1 == 1 {
+                    'X'
+                } else if self.fixed >> idx & 1 == 1 {
+                    '1'
+                } else {
+                    '0'
+                }
+            })
+            .collect()
+    }
+}
+
+/// Iterator over addresses matched by a pattern.
+pub struct PatternAddresses {
+    pattern: AddressPattern,
+    subset: Option<u64>,
+}
+
+impl Iterator for PatternAddresses {
+    type Item = u64;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let subset = self.subset?;
+        let floating = self.pattern.floating;
+        self.subset = if subset == floating {
+            None
+        } else {
+            Some(subset.wrapping_sub(floating) & floating)
+        };
+
+        Some(self.pattern.fixed | subset)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_addresses() {
+        let pattern = AddressPattern::new(0b01_1010, 0b10_0001);
+        assert_eq!(pattern.to_string_with_width(6), "X1101X");
+        assert_eq!(pattern.count(), 4);
+        assert_eq!(
+            pattern.addresses().collect::<Vec<_>>(),
+            vec![26, 27, 58, 59]
+        );
+        assert!(pattern.contains(58));
+        assert!(!pattern.contains(42));
+
+        let all = AddressPattern::new(0, u64::MAX);
+        assert_eq!(all.count(), 1 << 64);
+        assert_eq!(all.addresses().nth(5), Some(5));
+        assert_eq!(AddressPattern::exact(7).addresses().count(), 1);
+    }
+
+    #[test]
+    fn test_intersection() {
+        let a = AddressPattern::new(0b0100, 0b1001);
+        let b = AddressPattern::new(0b0001, 0b0110);
+        assert_eq!(a.intersection(&b), Some(AddressPattern::new(0b0101, 0)));
+        assert_eq!(a.intersection(&AddressPattern::exact(0b0110)), None);
+    }
+
+    #[test]
+    fn test_subtract() {
+        let a = AddressPattern::new(0b1_0000, 0b0_1011);
+        let b = AddressPattern::new(0b0_0010, 0b1_0100);
+        let pieces = a.subtract(&b);
+
+        let mut remaining: Vec<u64> = pieces.iter().flat_map(|p| p.addresses()).collect();
+        let total: u128 = pieces.iter().map(AddressPattern::count).sum();
+        remaining.sort_unstable();
+        assert_eq!(total, remaining.len() as u128);
+
+        let expected: Vec<u64> = a.addresses().filter(|&x| !b.contains(x)).collect();
+        assert_eq!(remaining, expected);
+
+        assert_eq!(a.subtract(&AddressPattern::exact(0)), vec![a]);
+        assert_eq!(a.subtract(&AddressPattern::new(0, u64::MAX)), vec![]);
+    }
+}
@@ -0,0 +1,207 @@
+//! Initialization program instructions and traces
+
+use std::fmt;
+
+use once_cell::sync::Lazy;
+use regex::Regex;
+
+use super::{width_bits, DayError, Mask};
+
+static RGX_MASK: Lazy<Regex> = Lazy::new(|| Regex::new(r"^mask = (?P<mask>[01X]+)$").unwrap());
+
+static RGX_MEM: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"^mem\[(?P<mem_idx>\d+)\] = (?P<mem_value>\d+)$").unwrap());
+
+/// Decoder chip version
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Decoder {
+    /// Version 1: the mask applies on written values
+    V1,
+    /// Version 2: the mask applies on addresses, with floating bits
+    V2,
+}
+
+/// Initialization program instruction
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Instruction {
+    /// Set current mask
+    Mask(Mask),
+    /// Write value in memory
+    Mem {
+        /// Address
+        addr: u64,
+        /// Value
+        value: u64,
+    },
+}
+
+impl Instruction {
+    /// Parse instruction from input line.
+    ///
+    /// # Arguments
+    ///
+    /// * `input` - Input line
+    pub fn parse(input: &str) -> Option<Self> {
+        let input = input.trim();
+        if let Some(captures) = RGX_MASK.captures(input) {
+            Mask::parse(&captures["mask"]).map(Self::Mask)
+        } else {
+            let captures = RGX_MEM.captures(input)?;
+            Some(Self::Mem {
+                addr: captures["mem_idx"].parse().ok()?,
+                value: captures["mem_value"].parse().ok()?,
+            })
+        }
+    }
+
+    /// Parse program from input string, ignoring empty lines.
+    ///
+    /// # Arguments
+    ///
+    /// * `input` - Input string
+    pub fn parse_program(input: &str) -> Result<Vec<Self>, DayError> {
+        input
+            .lines()
+            .enumerate()
+            .filter(|(_, l)| !l.trim().is_empty())
+            .map(|(idx, l)| {
+                Self::parse(l).ok_or_else(|| DayError::InvalidInstruction {
+                    line: idx + 1,
+                    input: l.trim().to_string(),
+                })
+            })
+            .collect()
+    }
+}
+
+impl fmt::Display for Instruction {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Mask(mask) => write!(f, "mask = {}", mask),
+            Self::Mem { addr, value } => write!(f, "mem[{}] = {}", addr, value),
+        }
+    }
+}
+
+/// Executed instruction, along with the mask in effect.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TraceEntry {
+    /// Instruction index in the program
+    pub index: usize,
+    /// Executed instruction
+    pub instruction: Instruction,
+    /// Mask in effect after execution
+    pub mask: Mask,
+    /// Decoder version
+    pub decoder: Decoder,
+}
+
+impl TraceEntry {
+    fn binary(&self, value: u64) -> String {
+        format!("{:0width$b}", value, width = self.mask.width() as usize)
+    }
+
+    /// Render changed bits after a prefix, most significant bit first: `^`
+    /// for overwritten bits which changed, `X` for floating bits.
+    fn diff(&self, prefix: &str, before: u64, after: u64, floating: u64) -> String {
+        let diff: String = (0..self.mask.width())
+            .rev()
+            .map(|idx| {
+                if floating >> idx & 1 == 1 {
+                    'X'
+                } else if (before ^ after) >> idx & 1 == 1 {
+                    '^'
+                } else {
+                    ' '
+                }
+            })
+            .collect();
+
+        format!("{}{}", prefix, diff).trim_end().to_string()
+    }
+}
+
+impl fmt::Display for TraceEntry {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "#{} {}", self.index, self.instruction)?;
+
+        let (addr, value) = match self.instruction {
+            Instruction::Mask(_) => return Ok(()),
+            Instruction::Mem { addr, value } => (addr, value),
+        };
+
+        match self.decoder {
+            Decoder::V1 => {
+                let value = value & width_bits(self.mask.width());
+                let result = self.mask.apply_on_value(value);
+                writeln!(f)?;
+                writeln!(f, "  value:  {}  (decimal {})", self.binary(value), value)?;
+                writeln!(f, "  mask:   {}", self.mask)?;
+                writeln!(f, "  result: {}  (decimal {})", self.binary(result), result)?;
+                write!(f, "{}", self.diff("  diff:   ", value, result, 0))
+            }
+            Decoder::V2 => {
+                let addr = addr & width_bits(self.mask.width());
+                let pattern = self.mask.apply_on_address(addr);
+                writeln!(f)?;
+                writeln!(f, "  address: {}  (decimal {})", self.binary(addr), addr)?;
+                writeln!(f, "  mask:    {}", self.mask)?;
+                writeln!(
+                    f,
+                    "  result:  {}  ({} addresses)",
+                    pattern.to_string_with_width(self.mask.width()),
+                    pattern.count()
+                )?;
+                write!(
+                    f,
+                    "{}",
+                    self.diff("  diff:    ", addr, pattern.fixed(), pattern.floating())
+                )
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_and_display() {
+        let program = Instruction::parse_program(
+            "mask = XXXXXXXXXXXXXXXXXXXXXXXXXXXXX1XXXX0X\n\nmem[8] = 11\n  mem[7] = 101  ",
+        )
+        .unwrap();
+        assert_eq!(program.len(), 3);
+        assert_eq!(
+            program[2],
+            Instruction::Mem {
+                addr: 7,
+                value: 101
+            }
+        );
+
+        let lines: Vec<String> = program.iter().map(Instruction::to_string).collect();
+        assert_eq!(
+            lines,
+            vec![
+                "mask = XXXXXXXXXXXXXXXXXXXXXXXXXXXXX1XXXX0X",
+                "mem[8] = 11",
+                "mem[7] = 101"
+            ]
+        );
+
+        assert_eq!(
+            Instruction::parse_program("mem[8] = 11\nmask = 10Y"),
+            Err(DayError::InvalidInstruction {
+                line: 2,
+                input: "mask = 10Y".into()
+            })
+        );
+        assert_eq!(
+            Instruction::parse(&format!("mask = {}", "X".repeat(65))),
+            None
+        );
+        assert_eq!(Instruction::parse("mem[99999999999999999999] = 1"), None);
+    }
+}
@@ -53,10 +53,13 @@
 //!
 //! Given your starting numbers, what will be the 30000000th number spoken?
 
-use std::collections::VecDeque;
+use std::convert::TryFrom;
+
+use thiserror::Error;
+
+mod snapshot;
 
 const INPUT_VALUES: &str = include_str!("input.txt");
-const INITIAL_BUFFER_SIZE: usize = 1_048_576; // 1 MB
 
 /// Part one answer.
 pub fn run_ex1() -> usize {
@@ -68,11 +71,40 @@ pub fn run_ex2() -> usize {
     MemoryGame::from_str_input(INPUT_VALUES).run_steps(30_000_000)
 }
 
-/// Memory game
+/// Day error.
+#[derive(Debug, Error)]
+pub enum DayError {
+    /// Snapshot is not valid
+    #[error("Invalid snapshot: {0}")]
+    InvalidSnapshot(&'static str),
+    /// I/O error
+    #[error("I/O error: {0}")]
+    IoError(#[from] std::io::Error),
+}
+
+/// Gap statistics, a gap being the turn count between two utterances of a number.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct GapStats {
+    /// Played turns
+    pub turns: usize,
+    /// Turns where a number was spoken for the first time
+    pub new_numbers: usize,
+    /// Largest gap, as a `(gap, number, turn)` tuple
+    pub max_gap: Option<(usize, usize, usize)>,
+    /// Mean gap, on turns where a number was repeated
+    pub mean_gap: f64,
+}
+
+/// Memory game.
+///
+/// Turns are stored as `u32`, so games are limited to `u32::MAX` turns.
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub struct MemoryGame {
-    memory: Vec<usize>,
-    input: VecDeque<usize>,
-    turn: usize,
+    starting: Vec<u32>,
+    // Turn where each number was last spoken, 0 if never spoken
+    memory: Vec<u32>,
+    turn: u32,
+    next: u32,
 }
 
 impl MemoryGame {
@@ -83,6 +115,7 @@ impl MemoryGame {
     /// * `input` - Input string
     pub fn from_str_input(input: &str) -> Self {
         let input: Vec<_> = input
+            .trim()
             .split(',')
             .map(|x| x.parse::<usize>().unwrap())
             .collect();
@@ -96,44 +129,160 @@ impl MemoryGame {
     ///
     /// * `input` - Input vec
     pub fn from_vec(input: Vec<usize>) -> Self {
+        let starting: Vec<u32> = input
+            .into_iter()
+            .map(|x| u32::try_from(x).expect("Starting numbers should fit in 32 bits"))
+            .collect();
+        let size = starting.iter().max().map_or(0, |&x| x as usize + 1);
+
         Self {
-            memory: vec![0; INITIAL_BUFFER_SIZE],
-            input: input.into(),
-            turn: 1,
+            starting,
+            memory: vec![0; size],
+            turn: 0,
+            next: 0,
         }
     }
 
-    /// Step.
-    pub fn step(&mut self) -> usize {
-        let i = self.input.pop_front().unwrap_or(0);
+    /// Creates new game with the same starting numbers.
+    pub fn restart(&self) -> Self {
+        Self::from_vec(self.starting.iter().map(|&x| x as usize).collect())
+    }
 
-        if i >= self.memory.len() {
-            self.memory.resize(i * 2, 0);
+    /// Get played turn count.
+    pub fn turn(&self) -> usize {
+        self.turn as usize
+    }
+
+    /// Get gap between the last spoken number and its previous utterance,
+    /// `None` if it was spoken for the first time.
+    pub fn last_gap(&self) -> Option<usize> {
+        if self.turn == 0 || self.next == 0 {
+            None
+        } else {
+            Some(self.next as usize)
         }
+    }
 
-        let t = self.memory[i];
-        if t > 0 {
-            self.input.push_back(self.turn - t);
+    /// Pre-size memory table for `turns` more turns.
+    /// Numbers spoken after the starting ones are gaps, so they are lower than the turn count.
+    ///
+    /// # Arguments
+    ///
+    /// * `turns` - Turn count
+    pub fn reserve(&mut self, turns: usize) {
+        let size = (self.turn as usize + turns).min(u32::MAX as usize);
+        if size > self.memory.len() {
+            self.memory.resize(size, 0);
         }
+    }
 
-        self.memory[i] = self.turn;
+    /// Step.
+    pub fn step(&mut self) -> usize {
+        let spoken = self
+            .starting
+            .get(self.turn as usize)
+            .copied()
+            .unwrap_or(self.next);
         self.turn += 1;
-        i
+
+        let idx = spoken as usize;
+        if idx >= self.memory.len() {
+            self.memory.resize((idx + 1).max(self.memory.len() * 2), 0);
+        }
+
+        let previous = std::mem::replace(&mut self.memory[idx], self.turn);
+        self.next = if previous == 0 {
+            0
+        } else {
+            self.turn - previous
+        };
+
+        idx
+    }
+
+    /// Iterate over spoken numbers.
+    pub fn iter(&mut self) -> Turns<'_> {
+        Turns { game: self }
     }
 
     /// Run for `n` steps.
     pub fn run_steps(&mut self, n: usize) -> usize {
-        let mut result = 0;
-
         if n == 0 {
             panic!("You need at least one step.");
         }
 
-        for _ in 0..n {
-            result = self.step();
+        self.reserve(n);
+        self.iter().take(n).last().unwrap()
+    }
+
+    /// Get turn where `value` is first spoken in a new game, if it is spoken within `max_turns` turns.
+    ///
+    /// # Arguments
+    ///
+    /// * `value` - Number
+    /// * `max_turns` - Maximum turn count
+    pub fn first_occurrence(&self, value: usize, max_turns: usize) -> Option<usize> {
+        let mut game = self.restart();
+        game.reserve(max_turns);
+        game.iter()
+            .take(max_turns)
+            .position(|x| x == value)
+            .map(|idx| idx + 1)
+    }
+
+    /// Compute gap statistics over the first `turns` turns of a new game.
+    ///
+    /// # Arguments
+    ///
+    /// * `turns` - Turn count
+    pub fn gap_stats(&self, turns: usize) -> GapStats {
+        let mut game = self.restart();
+        game.reserve(turns);
+
+        let mut new_numbers = 0;
+        let mut max_gap = None;
+        let mut gap_sum = 0_u64;
+        for turn in 1..=turns {
+            let spoken = game.step();
+            match game.last_gap() {
+                None => new_numbers += 1,
+                Some(gap) => {
+                    gap_sum += gap as u64;
+                    if max_gap.is_none_or(|(max, _, _)| gap > max) {
+                        max_gap = Some((gap, spoken, turn));
+                    }
+                }
+            }
         }
 
-        result
+        let repeats = turns - new_numbers;
+        GapStats {
+            turns,
+            new_numbers,
+            max_gap,
+            mean_gap: if repeats == 0 {
+                0.0
+            } else {
+                gap_sum as f64 / repeats as f64
+            },
+        }
+    }
+}
+
+/// Iterator over spoken numbers, ending after `u32::MAX` turns.
+pub struct Turns<'a> {
+    game: &'a mut MemoryGame,
+}
+
+impl Iterator for Turns<'_> {
+    type Item = usize;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.game.turn == u32::MAX {
+            None
+        } else {
+            Some(self.game.step())
+        }
     }
 }
 
@@ -175,6 +324,36 @@ mod tests {
         assert_eq!(MemoryGame::from_vec(vec![3, 1, 2]).run_steps(2020), 1836);
     }
 
+    #[test]
+    fn test_iter() {
+        let mut game = MemoryGame::from_vec(vec![0, 3, 6]);
+        let spoken: Vec<_> = game.iter().take(10).collect();
+        assert_eq!(spoken, vec![0, 3, 6, 0, 3, 3, 1, 0, 4, 0]);
+        assert_eq!(game.turn(), 10);
+        assert_eq!(game.last_gap(), Some(2));
+        assert_eq!(game.iter().nth(2009), Some(436));
+    }
+
+    #[test]
+    fn test_analysis() {
+        let game = MemoryGame::from_vec(vec![0, 3, 6]);
+        assert_eq!(game.first_occurrence(6, 10), Some(3));
+        assert_eq!(game.first_occurrence(4, 10), Some(9));
+        assert_eq!(game.first_occurrence(5, 10), None);
+        assert_eq!(game.first_occurrence(436, 2020), Some(2020));
+
+        assert_eq!(
+            game.gap_stats(10),
+            GapStats {
+                turns: 10,
+                new_numbers: 5,
+                max_gap: Some((4, 0, 8)),
+                mean_gap: 2.6,
+            }
+        );
+        assert_eq!(game.gap_stats(0).max_gap, None);
+    }
+
     #[test]
     fn test_run_ex1() {
         assert_eq!(run_ex1(), EX1_OUTPUT);
@@ -0,0 +1,167 @@
+//! Game state snapshots
+
+use std::{
+    fs::File,
+    io::{BufReader, BufWriter, Read, Write},
+    path::Path,
+};
+
+use super::{DayError, MemoryGame};
+
+const MAGIC: &[u8; 8] = b"AOC15MG1";
+
+impl MemoryGame {
+    /// Write game state snapshot.
+    ///
+    /// The snapshot holds the starting numbers, the turn count, the next
+    /// number and the memory table up to its last used entry, as little-endian
+    /// `u32` values after a magic header.
+    ///
+    /// # Arguments
+    ///
+    /// * `writer` - Writer
+    pub fn save<W: Write>(&self, mut writer: W) -> Result<(), DayError> {
+        let used = self
+            .memory
+            .iter()
+            .rposition(|&t| t != 0)
+            .map_or(0, |idx| idx + 1);
+
+        writer.write_all(MAGIC)?;
+        write_u32(&mut writer, self.starting.len() as u32)?;
+        for &x in &self.starting {
+            write_u32(&mut writer, x)?;
+        }
+        write_u32(&mut writer, self.turn)?;
+        write_u32(&mut writer, self.next)?;
+        write_u32(&mut writer, used as u32)?;
+        for &t in &self.memory[..used] {
+            write_u32(&mut writer, t)?;
+        }
+
+        writer.flush()?;
+        Ok(())
+    }
+
+    /// Read game state snapshot.
+    ///
+    /// # Arguments
+    ///
+    /// * `reader` - Reader
+    pub fn load<R: Read>(mut reader: R) -> Result<Self, DayError> {
+        let mut magic = [0; 8];
+        reader.read_exact(&mut magic)?;
+        if &magic != MAGIC {
+            return Err(DayError::InvalidSnapshot("unknown header"));
+        }
+
+        let count = read_u32(&mut reader)?;
+        let starting = (0..count)
+            .map(|_| read_u32(&mut reader))
+            .collect::<Result<Vec<_>, _>>()?;
+        let turn = read_u32(&mut reader)?;
+        let next = read_u32(&mut reader)?;
+        let used = read_u32(&mut reader)?;
+        let memory = (0..used)
+            .map(|_| read_u32(&mut reader))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        if memory.iter().any(|&t| t > turn) {
+            return Err(DayError::InvalidSnapshot("memory entry after current turn"));
+        }
+        if next >= turn.max(1) {
+            return Err(DayError::InvalidSnapshot("next number out of range"));
+        }
+
+        Ok(Self {
+            starting,
+            memory,
+            turn,
+            next,
+        })
+    }
+
+    /// Write game state snapshot to a file.
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - File path
+    pub fn save_to_file(&self, path: &Path) -> Result<(), DayError> {
+        self.save(BufWriter::new(File::create(path)?))
+    }
+
+    /// Read game state snapshot from a file.
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - File path
+    pub fn load_from_file(path: &Path) -> Result<Self, DayError> {
+        Self::load(BufReader::new(File::open(path)?))
+    }
+}
+
+fn write_u32<W: Write>(writer: &mut W, value: u32) -> Result<(), DayError> {
+    writer.write_all(&value.to_le_bytes())?;
+    Ok(())
+}
+
+fn read_u32<R: Read>(reader: &mut R) -> Result<u32, DayError> {
+    let mut bytes = [0; 4];
+    reader.read_exact(&mut bytes)?;
+    Ok(u32::from_le_bytes(bytes))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_snapshot_round_trip() {
+        let mut game = MemoryGame::from_vec(vec![0, 3, 6]);
+        game.run_steps(1000);
+
+        let mut buffer = vec![];
+        game.save(&mut buffer).unwrap();
+        let mut resumed = MemoryGame::load(buffer.as_slice()).unwrap();
+        assert_eq!(resumed.turn(), 1000);
+        assert_eq!(resumed.run_steps(1020), 436);
+
+        let path = std::env::temp_dir().join(format!("aoc2020-day15-{}.bin", std::process::id()));
+        game.save_to_file(&path).unwrap();
+        let mut resumed = MemoryGame::load_from_file(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(resumed.run_steps(1020), 436);
+
+        // A snapshot taken before the starting numbers are spoken
+        let mut buffer = vec![];
+        MemoryGame::from_vec(vec![3, 1, 2])
+            .save(&mut buffer)
+            .unwrap();
+        assert_eq!(
+            MemoryGame::load(buffer.as_slice()).unwrap().run_steps(2020),
+            1836
+        );
+    }
+
+    #[test]
+    fn test_invalid_snapshot() {
+        assert!(matches!(
+            MemoryGame::load(&b"AOC15MG0"[..]),
+            Err(DayError::InvalidSnapshot("unknown header"))
+        ));
+        assert!(matches!(
+            MemoryGame::load(&b"AOC15MG1\x01\x00"[..]),
+            Err(DayError::IoError(_))
+        ));
+
+        // No starting numbers, turn 1, next 0, one memory entry set at turn 5
+        let mut buffer = b"AOC15MG1".to_vec();
+        for x in &[0u32, 1, 0, 1, 5] {
+            buffer.extend_from_slice(&x.to_le_bytes());
+        }
+        assert!(matches!(
+            MemoryGame::load(buffer.as_slice()),
+            Err(DayError::InvalidSnapshot("memory entry after current turn"))
+        ));
+    }
+}
@@ -0,0 +1,245 @@
+//! Bipartite matching between rules and ticket positions
+
+/// Rule to position matcher, using augmenting paths.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FieldMatcher {
+    candidates: Vec<Vec<usize>>,
+    positions: usize,
+}
+
+/// Matching failure.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum MatchingError {
+    /// Rules competing for fewer positions than their count
+    Unsatisfiable {
+        /// Rule indices, sorted
+        rules: Vec<usize>,
+        /// Every position available to these rules, sorted
+        positions: Vec<usize>,
+    },
+}
+
+impl FieldMatcher {
+    /// Creates a new matcher.
+    ///
+    /// # Arguments
+    ///
+    /// * `candidates` - Candidate positions for each rule
+    /// * `positions` - Position count
+    pub fn new(candidates: Vec<Vec<usize>>, positions: usize) -> Self {
+        Self {
+            candidates,
+            positions,
+        }
+    }
+
+    /// Get candidate positions for each rule.
+    pub fn candidates(&self) -> &[Vec<usize>] {
+        &self.candidates
+    }
+
+    /// Find a position for each rule, each position being used at most once.
+    pub fn find(&self) -> Result<Vec<usize>, MatchingError> {
+        self.find_without(None)
+    }
+
+    /// Get rules which can be moved to another position in a consistent assignment.
+    ///
+    /// # Arguments
+    ///
+    /// * `assignment` - Consistent assignment
+    pub fn ambiguous_rules(&self, assignment: &[usize]) -> Vec<usize> {
+        assignment
+            .iter()
+            .enumerate()
+            .filter(|&(rule, &position)| self.find_without(Some((rule, position))).is_ok())
+            .map(|(rule, _)| rule)
+            .collect()
+    }
+
+    /// Iterate over all consistent assignments, lazily.
+    /// There can be up to `n!` assignments for `n` rules.
+    pub fn assignments(&self) -> Assignments<'_> {
+        // Most constrained rules first, to prune dead ends early
+        let mut order: Vec<usize> = (0..self.candidates.len()).collect();
+        order.sort_by_key(|&r| self.candidates[r].len());
+
+        Assignments {
+            matcher: self,
+            assignment: vec![usize::MAX; self.candidates.len()],
+            used: vec![false; self.positions],
+            cursors: vec![0],
+            order,
+        }
+    }
+
+    /// Find an assignment, optionally forbidding one rule position.
+    fn find_without(&self, forbidden: Option<(usize, usize)>) -> Result<Vec<usize>, MatchingError> {
+        let mut owners: Vec<Option<usize>> = vec![None; self.positions];
+
+        for rule in 0..self.candidates.len() {
+            let mut visited = vec![false; self.positions];
+            if !self.augment(rule, forbidden, &mut owners, &mut visited) {
+                // Visited positions are all the positions the failed rule could
+                // get by moving other rules, and they are all taken
+                let positions: Vec<usize> = (0..self.positions).filter(|&p| visited[p]).collect();
+                let mut rules: Vec<usize> = std::iter::once(rule)
+                    .chain(positions.iter().filter_map(|&p| owners[p]))
+                    .collect();
+                rules.sort_unstable();
+
+                return Err(MatchingError::Unsatisfiable { rules, positions });
+            }
+        }
+
+        let mut assignment = vec![0; self.candidates.len()];
+        for (position, owner) in owners.iter().enumerate() {
+            if let Some(rule) = owner {
+                assignment[*rule] = position;
+            }
+        }
+
+        Ok(assignment)
+    }
+
+    /// Try to give a position to `rule`, moving already matched rules if needed.
+    fn augment(
+        &self,
+        rule: usize,
+        forbidden: Option<(usize, usize)>,
+        owners: &mut Vec<Option<usize>>,
+        visited: &mut Vec<bool>,
+    ) -> bool {
+        for &position in &self.candidates[rule] {
+            if visited[position] || forbidden == Some((rule, position)) {
+                continue;
+            }
+
+            visited[position] = true;
+            let free = match owners[position] {
+                None => true,
+                Some(owner) => self.augment(owner, forbidden, owners, visited),
+            };
+            if free {
+                owners[position] = Some(rule);
+                return true;
+            }
+        }
+
+        false
+    }
+}
+
+/// Lazy iterator over consistent assignments, using backtracking.
+pub struct Assignments<'a> {
+    matcher: &'a FieldMatcher,
+    order: Vec<usize>,
+    assignment: Vec<usize>,
+    used: Vec<bool>,
+    // Next candidate index to try, for each rule of `order` being assigned
+    cursors: Vec<usize>,
+}
+
+impl Iterator for Assignments<'_> {
+    type Item = Vec<usize>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.order.is_empty() {
+            // Single empty assignment
+            return self.cursors.pop().map(|_| vec![]);
+        }
+
+        while let Some(&cursor) = self.cursors.last() {
+            let depth = self.cursors.len() - 1;
+            let candidates = &self.matcher.candidates[self.order[depth]];
+
+            // Release the previous choice of this rule
+            if cursor > 0 {
+                self.used[candidates[cursor - 1]] = false;
+            }
+
+            let used = &self.used;
+            match (cursor..candidates.len()).find(|&i| !used[candidates[i]]) {
+                Some(idx) => {
+                    self.cursors[depth] = idx + 1;
+                    self.used[candidates[idx]] = true;
+                    self.assignment[self.order[depth]] = candidates[idx];
+
+                    if depth + 1 == self.order.len() {
+                        return Some(self.assignment.clone());
+                    }
+                    self.cursors.push(0);
+                }
+                None => {
+                    self.cursors.pop();
+                }
+            }
+        }
+
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_find() {
+        // Greedy choice of position 0 for rule 0 must be undone
+        let matcher = FieldMatcher::new(vec![vec![0, 1], vec![0], vec![1, 2]], 3);
+        assert_eq!(matcher.find(), Ok(vec![1, 0, 2]));
+        assert!(matcher.ambiguous_rules(&[1, 0, 2]).is_empty());
+        assert_eq!(
+            matcher.assignments().collect::<Vec<_>>(),
+            vec![vec![1, 0, 2]]
+        );
+
+        let matcher = FieldMatcher::new(vec![vec![0], vec![0], vec![1, 2]], 3);
+        assert_eq!(
+            matcher.find(),
+            Err(MatchingError::Unsatisfiable {
+                rules: vec![0, 1],
+                positions: vec![0]
+            })
+        );
+        assert_eq!(matcher.assignments().next(), None);
+
+        // The conflict goes through moved rules
+        let matcher = FieldMatcher::new(
+            vec![vec![0, 1], vec![1, 2], vec![0, 2], vec![0], vec![3]],
+            4,
+        );
+        assert_eq!(
+            matcher.find(),
+            Err(MatchingError::Unsatisfiable {
+                rules: vec![0, 1, 2, 3],
+                positions: vec![0, 1, 2]
+            })
+        );
+
+        let matcher = FieldMatcher::new(vec![], 2);
+        assert_eq!(matcher.find(), Ok(vec![]));
+        assert_eq!(
+            matcher.assignments().collect::<Vec<_>>(),
+            vec![Vec::<usize>::new()]
+        );
+    }
+
+    #[test]
+    fn test_ambiguous() {
+        let matcher = FieldMatcher::new(vec![vec![0, 1], vec![0, 1], vec![2]], 3);
+        let assignment = matcher.find().unwrap();
+        assert_eq!(matcher.ambiguous_rules(&assignment), vec![0, 1]);
+        assert_eq!(
+            matcher.assignments().collect::<Vec<_>>(),
+            vec![vec![0, 1, 2], vec![1, 0, 2]]
+        );
+
+        // 20! assignments, only the first ones are computed
+        let matcher = FieldMatcher::new(vec![(0..20).collect(); 20], 20);
+        let first: Vec<_> = matcher.assignments().take(2).collect();
+        assert_eq!(first[0], (0..20).collect::<Vec<_>>());
+        assert_eq!(first[1][18..], [19, 18]);
+    }
+}
@@ -70,13 +70,14 @@
 //!
 //! Once you work out which field is which, look for the six fields on your ticket that start with the word departure. What do you get if you multiply those six values together?
 
-use std::{
-    collections::{HashMap, HashSet},
-    ops::RangeInclusive,
-};
+use std::{collections::HashMap, ops::RangeInclusive};
 
 use thiserror::Error;
 
+mod matching;
+
+pub use self::matching::{Assignments, FieldMatcher, MatchingError};
+
 const INPUT_VALUES: &str = include_str!("input.txt");
 
 /// Part one answer.
@@ -93,6 +94,7 @@ pub fn run_ex2() -> usize {
 
     input
         .map_ticket_fields()
+        .unwrap()
         .iter()
         .filter_map(|(&k, &v)| {
             if k.starts_with("departure") {
@@ -157,7 +159,7 @@ impl From<&str> for TicketRule {
                     .map(|x| x.parse::<usize>().unwrap())
                     .collect();
 
-                let first = vec.get(0).copied().unwrap();
+                let first = vec.first().copied().unwrap();
                 let second = vec.get(1).copied().unwrap();
 
                 RangeInclusive::new(first, second)
@@ -231,97 +233,110 @@ impl InputParser {
             .collect()
     }
 
-    /// Map ticket fields with position.
-    pub fn map_ticket_fields(&self) -> HashMap<&str, usize> {
-        // Filter nearby tickets
+    /// Get candidate positions for each rule, starting from 1.
+    /// A position is a candidate if every valid nearby ticket matches the rule at this position.
+    pub fn field_candidates(&self) -> Vec<(&str, Vec<usize>)> {
         let remaining_tickets: Vec<_> = self
             .nearby_tickets
             .iter()
             .filter(|t| self.validate_ticket(t).is_empty())
             .collect();
 
-        let mut rules_affectation: HashMap<&str, usize> =
-            self.rules.iter().map(|x| (&*x.name, usize::MAX)).collect();
-        let mut remaining_rules: Vec<&str> = self.rules.iter().map(|x| &*x.name).collect();
-        let mut invalid_positions: HashMap<&str, HashSet<usize>> = self
-            .rules
+        self.rules
             .iter()
-            .map(|x| (&*x.name, HashSet::new()))
-            .collect();
-        let mut positions: HashMap<&str, Vec<usize>> =
-            self.rules.iter().map(|x| (&*x.name, vec![])).collect();
-
-        // Accumulate potential positions
-        for t in &remaining_tickets {
-            for r in &self.rules {
-                for (idx, n) in t.numbers.iter().enumerate() {
-                    if r.validate_number(*n) {
-                        positions.get_mut(&*r.name).unwrap().push(idx);
-                    } else {
-                        invalid_positions.get_mut(&*r.name).unwrap().insert(idx);
-                    }
-                }
-            }
-        }
-
-        while !remaining_rules.is_empty() {
-            // Remove invalid positions from positions
-            for (rn, counter) in &mut positions {
-                let new_counter: Vec<_> = counter
-                    .iter()
-                    .filter(|x| invalid_positions.get(rn).map_or(true, |v| !v.contains(x)))
-                    .copied()
+            .map(|r| {
+                let positions = (0..self.your_ticket.numbers.len())
+                    .filter(|&idx| {
+                        remaining_tickets
+                            .iter()
+                            .all(|t| t.numbers.get(idx).is_some_and(|&n| r.validate_number(n)))
+                    })
+                    .map(|idx| idx + 1)
                     .collect();
 
-                *counter = new_counter;
-            }
-
-            // Get the most seen value per rule
-            let mut rules_to_remove = vec![];
-            for (idx, rn) in remaining_rules.iter().enumerate() {
-                let counter = positions.get(rn).unwrap();
-                let count_map = Self::count_occurences_in_vec(counter);
-
-                // Simple case, only one number available in counter
-                if count_map.len() == 1 {
-                    let position = *count_map.keys().next().unwrap();
-                    rules_affectation.insert(rn, position);
-                    rules_to_remove.push(idx);
-
-                    // Add as invalid positions for other rules
-                    for r in remaining_rules.iter().filter(|&&o_rn| o_rn != *rn) {
-                        invalid_positions.get_mut(r).unwrap().insert(position);
-                    }
-                }
-            }
+                (&*r.name, positions)
+            })
+            .collect()
+    }
 
-            // Remove already found rules
-            while let Some(r_idx) = rules_to_remove.pop() {
-                remaining_rules.remove(r_idx);
+    /// Map ticket fields with position, starting from 1.
+    ///
+    /// Fails if no consistent assignment exists, or if several exist,
+    /// reporting candidate positions of the offending rules.
+    pub fn map_ticket_fields(&self) -> Result<HashMap<&str, usize>, DayError> {
+        let candidates = self.field_candidates();
+        let matcher = self.matcher(&candidates);
+
+        let assignment = matcher.find().map_err(|e| match e {
+            MatchingError::Unsatisfiable { rules, positions } => {
+                DayError::ConfigurationError(format!(
+                    "not enough positions for rules {} (available positions: {:?})",
+                    Self::describe_rules(&candidates, &rules),
+                    positions.iter().map(|p| p + 1).collect::<Vec<_>>()
+                ))
             }
+        })?;
+
+        let ambiguous = matcher.ambiguous_rules(&assignment);
+        if !ambiguous.is_empty() {
+            return Err(DayError::ConfigurationError(format!(
+                "ambiguous rules {}",
+                Self::describe_rules(&candidates, &ambiguous)
+            )));
         }
 
-        // Increment affectations to match needed output
-        for pos in rules_affectation.values_mut() {
-            *pos += 1;
-        }
+        Ok(Self::to_field_map(&candidates, &assignment))
+    }
 
-        rules_affectation
+    /// Get up to `limit` consistent mappings of ticket fields with position, starting from 1.
+    ///
+    /// # Arguments
+    ///
+    /// * `limit` - Maximum mapping count
+    pub fn ticket_field_mappings(&self, limit: usize) -> Vec<HashMap<&str, usize>> {
+        let candidates = self.field_candidates();
+
+        self.matcher(&candidates)
+            .assignments()
+            .take(limit)
+            .map(|assignment| Self::to_field_map(&candidates, &assignment))
+            .collect()
     }
 
-    fn count_occurences_in_vec(v: &[usize]) -> HashMap<usize, usize> {
-        let mut m = HashMap::new();
-        for n in v {
-            *m.entry(*n).or_default() += 1;
-        }
-        m
+    fn matcher(&self, candidates: &[(&str, Vec<usize>)]) -> FieldMatcher {
+        FieldMatcher::new(
+            candidates
+                .iter()
+                .map(|(_, positions)| positions.iter().map(|p| p - 1).collect())
+                .collect(),
+            self.your_ticket.numbers.len(),
+        )
+    }
+
+    fn to_field_map<'a>(
+        candidates: &[(&'a str, Vec<usize>)],
+        assignment: &[usize],
+    ) -> HashMap<&'a str, usize> {
+        candidates
+            .iter()
+            .zip(assignment)
+            .map(|((name, _), position)| (*name, position + 1))
+            .collect()
+    }
+
+    fn describe_rules(candidates: &[(&str, Vec<usize>)], rules: &[usize]) -> String {
+        rules
+            .iter()
+            .map(|&r| format!("'{}' (candidates: {:?})", candidates[r].0, candidates[r].1))
+            .collect::<Vec<_>>()
+            .join(", ")
     }
 }
 
 impl From<&str> for InputParser {
     fn from(value: &str) -> Self {
         let groups: Vec<&str> = value.trim().split("\n\n").collect();
-        let rules_section = groups.get(0).cloned().unwrap();
+        let rules_section = groups.first().cloned().unwrap();
         let your_ticket_section = groups.get(1).cloned().unwrap();
         let nearby_tickets_section = groups.get(2).cloned().unwrap();
 
@@ -467,7 +482,58 @@ mod tests {
             "seat" => 3
         };
 
-        assert_eq!(parser.map_ticket_fields(), res);
+        assert_eq!(parser.map_ticket_fields().unwrap(), res);
+        assert_eq!(parser.ticket_field_mappings(10), vec![res]);
+        assert_eq!(
+            parser.field_candidates(),
+            vec![
+                ("class", vec![2, 3]),
+                ("row", vec![1, 2, 3]),
+                ("seat", vec![3])
+            ]
+        );
+    }
+
+    #[test]
+    fn test_map_ticket_fields_errors() {
+        let parser = InputParser::from(indoc::indoc! {"
+            class: 1-3
+            row: 1-3
+            seat: 4-9
+
+            your ticket:
+            1,2,3
+
+            nearby tickets:
+            1,2,5
+            3,1,6
+        "});
+        assert_eq!(
+            parser.map_ticket_fields().unwrap_err().to_string(),
+            "Configuration error: ambiguous rules 'class' (candidates: [1, 2]), \
+             'row' (candidates: [1, 2])"
+        );
+        assert_eq!(parser.ticket_field_mappings(10).len(), 2);
+        assert_eq!(parser.ticket_field_mappings(1).len(), 1);
+
+        let parser = InputParser::from(indoc::indoc! {"
+            class: 1-3
+            row: 1-3
+            seat: 4-9
+
+            your ticket:
+            1,2,3
+
+            nearby tickets:
+            1,2,5
+            4,3,6
+        "});
+        assert_eq!(
+            parser.map_ticket_fields().unwrap_err().to_string(),
+            "Configuration error: not enough positions for rules 'class' (candidates: [2]), \
+             'row' (candidates: [2]) (available positions: [2])"
+        );
+        assert!(parser.ticket_field_mappings(10).is_empty());
     }
 
     #[test]
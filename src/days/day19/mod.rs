@@ -238,7 +238,7 @@ impl RuleParser {
     fn try_parse_either(rule_components: &str) -> Result<RuleType, DayError> {
         let pipe_components = rule_components.split('|').collect::<Vec<_>>();
         if pipe_components.len() > 1 {
-            let a = Self::extract_rule_ids(pipe_components.get(0).unwrap().split_whitespace());
+            let a = Self::extract_rule_ids(pipe_components.first().unwrap().split_whitespace());
 
             let b = Self::extract_rule_ids(pipe_components.get(1).unwrap().split_whitespace());
 
@@ -140,7 +140,7 @@ pub fn parse_dishes(input: &str) -> Vec<Dish> {
 /// # Arguments
 ///
 /// * `input` - Input string
-pub fn count_ingredients_for_allergens(dishes: &[Dish]) -> AllergenCounts {
+pub fn count_ingredients_for_allergens(dishes: &[Dish]) -> AllergenCounts<'_> {
     let mut allergen_map = HashMap::new();
 
     for d in dishes {
@@ -204,7 +204,7 @@ pub fn extract_ingredients_without_allergens<'a>(
     map: AllergenMap<'a>,
 ) -> Vec<&'a Ingredient> {
     let mut output = vec![];
-    let ingrediens_with_allergens = map.into_iter().map(|(_, v)| v).collect::<HashSet<_>>();
+    let ingrediens_with_allergens = map.into_values().collect::<HashSet<_>>();
 
     for d in dishes {
         for i in &d.ingredients {
@@ -483,7 +483,6 @@ pub enum GameStepResult {
 /// Game memory.
 #[derive(Debug, Default)]
 pub struct GameMemory {
-    cache: HashMap<(Deck, Deck), Player>,
     rounds: HashMap<usize, Vec<(Deck, Deck)>>,
 }
 
@@ -666,7 +665,7 @@ fn run_recursive_game(
     }
 
     // Prepare rounds for game
-    memory.rounds.entry(game_number).or_insert_with(Vec::new);
+    memory.rounds.entry(game_number).or_default();
 
     loop {
         if let GameStepResult::Finished(player) =
@@ -155,13 +155,13 @@ impl Cups {
 
     /// Join cups to string, starting from cup one (and ignoring it).
     pub fn to_string_from_one(&self) -> String {
-        cups_to_string(&self, 1)[1..].to_string()
+        cups_to_string(self, 1)[1..].to_string()
     }
 }
 
-impl ToString for Cups {
-    fn to_string(&self) -> String {
-        cups_to_string(&self, self.head)
+impl std::fmt::Display for Cups {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", cups_to_string(self, self.head))
     }
 }
 
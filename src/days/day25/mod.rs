@@ -55,8 +55,13 @@
 
 use itertools::Itertools;
 
+use crate::common::math::{discrete_log, mod_pow};
+
 type PublicKey = usize;
 
+const SUBJECT_NUMBER: u64 = 7;
+const MODULUS: u64 = 20_201_227;
+
 const INPUT_VALUES: &str = include_str!("input.txt");
 
 /// Answer.
@@ -81,30 +86,13 @@ fn parse_keys(input: &str) -> (PublicKey, PublicKey) {
         .unwrap()
 }
 
-fn transform_subject_number_iteration(i: usize, subject: usize) -> usize {
-    (i * subject) % 20_201_227
-}
-
 fn transform_subject_number_loop(subject: usize, loop_size: usize) -> usize {
-    (0..loop_size)
-        .into_iter()
-        .fold(1, |acc, _| transform_subject_number_iteration(acc, subject))
+    mod_pow(subject as u64, loop_size as u64, MODULUS) as usize
 }
 
 fn determine_loop_size(public_key: PublicKey) -> usize {
-    let mut num = 1;
-    let mut counter = 0;
-
-    loop {
-        num = transform_subject_number_iteration(num, 7);
-        counter += 1;
-
-        if num == public_key {
-            break;
-        }
-    }
-
-    counter
+    discrete_log(SUBJECT_NUMBER, public_key as u64, MODULUS).expect("Loop size should exist")
+        as usize
 }
 
 #[cfg(test)]
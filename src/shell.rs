@@ -9,6 +9,10 @@ use super::days;
 enum Error {
     #[error("Day {0} is not in Advent of Code range (1-25)")]
     UnknownDay(usize),
+    #[error("Day {0} has no debugger")]
+    NoDebugger(usize),
+    #[error("Debugger failed: {0}")]
+    DebuggerIo(#[from] std::io::Error),
 }
 
 #[derive(Debug, StructOpt)]
@@ -20,6 +24,11 @@ enum Command {
     },
     /// Run all days
     RunAll,
+    /// Debug one specific day interactively
+    Debug {
+        /// Day
+        day: usize,
+    },
 }
 
 #[derive(Debug, StructOpt)]
@@ -71,6 +80,15 @@ fn run_day_wrapper(d: usize) -> Result<(), Error> {
     Ok(())
 }
 
+fn debug_day_wrapper(d: usize) -> Result<(), Error> {
+    match d {
+        8 => days::day08::run_debug()?,
+        d => return Err(Error::NoDebugger(d)),
+    }
+
+    Ok(())
+}
+
 /// Initialize command line arguments.
 pub fn initialize_command_line() {
     let args = Opt::from_args();
@@ -87,5 +105,10 @@ pub fn initialize_command_line() {
                 day_idx += 1;
             }
         }
+        Command::Debug { day } => {
+            if let Err(e) = debug_day_wrapper(day) {
+                eprintln!("Error: {}", e);
+            }
+        }
     }
 }